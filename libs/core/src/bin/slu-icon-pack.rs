@@ -0,0 +1,73 @@
+//! Small cli to author icon packs, usage:
+//! ```text
+//! slu-icon-pack build <folder> <id>      generates the metadata.yml of the pack from its images
+//! slu-icon-pack lint <folder>            reports mistakes on the pack
+//! slu-icon-pack export <folder> <output> creates a .slu file with the icons embedded
+//! ```
+
+use std::{path::PathBuf, process::ExitCode};
+
+use seelen_core::{
+    error::Result,
+    resource::{init_app_version, SluResource},
+    state::{IconPack, IconPackLint},
+};
+
+const USAGE: &str =
+    "usage: slu-icon-pack <build <folder> <id> | lint <folder> | export <folder> <output>>";
+
+fn print_lints(lints: &[IconPackLint]) {
+    for lint in lints {
+        eprintln!("[{:?}] {}", lint.kind, lint.message);
+    }
+}
+
+/// Loads the pack without sanitizing it, so invalid entries can be reported.
+fn load_raw(folder: &str) -> Result<IconPack> {
+    let folder = PathBuf::from(folder);
+    let mut pack = IconPack::load_from_folder(&folder)?;
+    pack.metadata.internal.path = folder;
+    Ok(pack)
+}
+
+fn run(args: &[String]) -> Result<bool> {
+    // exported packs are stamped with the targeted app version
+    init_app_version()?;
+    match args {
+        [cmd, folder, id] if cmd == "build" => {
+            let pack = IconPack::build_from_folder(&PathBuf::from(folder), id.as_str().into())?;
+            print_lints(&pack.lint());
+            pack.save()?;
+            println!("icon pack generated with {} entries", pack.entries.len());
+            Ok(true)
+        }
+        [cmd, folder] if cmd == "lint" => {
+            let lints = load_raw(folder)?.lint();
+            print_lints(&lints);
+            Ok(lints.is_empty())
+        }
+        [cmd, folder, output] if cmd == "export" => {
+            let pack = load_raw(folder)?;
+            print_lints(&pack.lint());
+            pack.export_slu(&PathBuf::from(output))?;
+            println!("icon pack exported to {output}");
+            Ok(true)
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            Ok(false)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -26,6 +26,7 @@ define_app_errors!(
     SerdeYaml(serde_yaml::Error);
     Base64Decode(base64::DecodeError);
     Grass(Box<grass::Error>);
+    QuickXml(quick_xml::DeError);
 );
 
 impl From<&str> for SeelenLibError {
@@ -141,6 +141,7 @@ slu_commands_declaration! {
     RemoveResource = remove_resource(id: ResourceId, kind: ResourceKind),
 
     StateGetThemes = state_get_themes() -> Vec<Theme>,
+    StateGetThemeDiagnostics = state_get_theme_diagnostics() -> HashMap<ThemeId, Vec<StyleDiagnostic>>,
     StateGetWegItems = state_get_weg_items(monitor_id: Option<MonitorId>) -> WegItems,
     StateWriteWegItems = state_write_weg_items(items: WegItems),
     StateGetToolbarItems = state_get_toolbar_items() -> Placeholder,
@@ -198,6 +199,7 @@ slu_commands_declaration! {
     GetPowerStatus = get_power_status() -> PowerStatus,
     GetPowerMode = get_power_mode() -> PowerMode,
     GetBatteries = get_batteries() -> Vec<Battery>,
+    GetBatteryInsights = get_battery_insights() -> Option<BatteryInsights>,
     LogOut = log_out(),
     Suspend = suspend(),
     Hibernate = hibernate(),
@@ -221,7 +223,7 @@ slu_commands_declaration! {
 
     // Slu Popups
     CreatePopup = create_popup(config: SluPopupConfig) -> uuid::Uuid,
-    UpdatePopup = update_popup(instance_id: uuid::Uuid, config: SluPopupConfig),
+    UpdatePopup = update_popup(instance_id: uuid::Uuid, config: SluPopupUpdate),
     ClosePopup = close_popup(instance_id: uuid::Uuid),
     GetPopupConfig = get_popup_config(instance_id: uuid::Uuid) -> SluPopupConfig,
 
@@ -73,6 +73,7 @@ export enum SeelenCommand {
   GetPowerStatus = "get_power_status",
   GetPowerMode = "get_power_mode",
   GetBatteries = "get_batteries",
+  GetBatteryInsights = "get_battery_insights",
   LogOut = "log_out",
   Suspend = "suspend",
   Hibernate = "hibernate",
@@ -75,6 +75,7 @@ slu_events_declaration! {
     PowerStatus(PowerStatus) as "power-status",
     PowerMode(PowerMode) as "power-mode",
     BatteriesStatus(Vec<Battery>) as "batteries-status",
+    BatteryInsights(Option<BatteryInsights>) as "battery-insights",
 
     ColorsChanged(UIColors) as "colors-changed",
 
@@ -25,6 +25,7 @@ export enum SeelenEvent {
   PowerStatus = "power-status",
   PowerMode = "power-mode",
   BatteriesStatus = "batteries-status",
+  BatteryInsights = "battery-insights",
   ColorsChanged = "colors-changed",
   ToolbarOverlaped = "set-auto-hide",
   WegOverlaped = "set-auto-hide",
@@ -0,0 +1,196 @@
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::Result;
+
+/// Version of the running app, injected by the app on startup.
+static APP_VERSION: OnceLock<Version> = OnceLock::new();
+
+/// Version of Seelen UI implemented by this library, the one targeted by the resources.
+pub const SEELEN_UI_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Sets [`SEELEN_UI_VERSION`] as the running app version, see [`set_app_version`].
+pub fn init_app_version() -> Result<()> {
+    set_app_version(SEELEN_UI_VERSION)
+}
+
+/// Sets the running app version, should be called before loading any resource.\
+/// Only the first call takes effect.
+pub fn set_app_version(version: &str) -> Result<()> {
+    let version = Version::parse(version).map_err(|e| format!("invalid app version: {e}"))?;
+    let _ = APP_VERSION.set(version);
+    Ok(())
+}
+
+/// Returns `None` if the app has not set its version yet.
+pub fn app_version() -> Option<&'static Version> {
+    APP_VERSION.get()
+}
+
+/// Semver range, ex: `>=2.3, <3`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(type = "string")]
+pub struct AppVersionRange(#[schemars(with = "String")] pub VersionReq);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(untagged)]
+pub enum AppTargetVersion {
+    /// Version used to develop the resource, ex: `[2, 3, 0]`.\
+    /// Apps with a different major version are taken as incompatible.
+    Tuple(u32, u32, u32),
+    /// Semver range of compatible versions, ex: `>=2.3, <3`
+    Range(AppVersionRange),
+}
+
+/// Result of comparing the resource target version against the running app version.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum ResourceCompatibility {
+    /// The resource doesn't declare a target version.
+    #[default]
+    Unknown,
+    Compatible,
+    /// The resource targets an older version of the app, it will be shown with a warning.
+    Outdated,
+    /// The resource targets a newer version of the app, it will be shown with a warning.
+    RequiresNewerApp,
+    /// The resource targets a different major version of the app, so it is loaded
+    /// in a quarantined state, disabled until the resource is updated.
+    Incompatible,
+}
+
+impl ResourceCompatibility {
+    pub fn is_quarantined(&self) -> bool {
+        *self == ResourceCompatibility::Incompatible
+    }
+}
+
+impl AppTargetVersion {
+    /// Compatibility against the running app, `Unknown` if the app version is not set.
+    pub fn compatibility(&self) -> ResourceCompatibility {
+        app_version()
+            .map(|app| self.compatibility_with(app))
+            .unwrap_or_default()
+    }
+
+    pub fn compatibility_with(&self, app: &Version) -> ResourceCompatibility {
+        match self {
+            AppTargetVersion::Tuple(major, minor, patch) => {
+                let target = Version::new(*major as u64, *minor as u64, *patch as u64);
+                if target.major != app.major {
+                    return ResourceCompatibility::Incompatible;
+                }
+                match target.cmp_precedence(app) {
+                    std::cmp::Ordering::Equal => ResourceCompatibility::Compatible,
+                    std::cmp::Ordering::Less => ResourceCompatibility::Outdated,
+                    std::cmp::Ordering::Greater => ResourceCompatibility::RequiresNewerApp,
+                }
+            }
+            AppTargetVersion::Range(AppVersionRange(req)) => {
+                if req.matches(app) {
+                    return ResourceCompatibility::Compatible;
+                }
+
+                // versions of the same major that could satisfy the range, the
+                // bounds of each comparator and their closest neighbours.
+                let mut candidates = vec![
+                    Version::new(app.major, 0, 0),
+                    Version::new(app.major, u64::MAX, u64::MAX),
+                ];
+                for comparator in &req.comparators {
+                    if comparator.major != app.major {
+                        continue;
+                    }
+                    let minor = comparator.minor.unwrap_or(0);
+                    let patch = comparator.patch.unwrap_or(0);
+                    candidates.push(Version::new(app.major, minor, patch));
+                    candidates.push(Version::new(app.major, minor, patch + 1));
+                    candidates.push(Version::new(app.major, minor + 1, 0));
+                    if patch > 0 {
+                        candidates.push(Version::new(app.major, minor, patch - 1));
+                    }
+                    if minor > 0 {
+                        candidates.push(Version::new(app.major, minor - 1, u64::MAX));
+                    }
+                }
+
+                let satisfied: Vec<&Version> =
+                    candidates.iter().filter(|v| req.matches(v)).collect();
+                if satisfied.iter().any(|v| *v > app) {
+                    ResourceCompatibility::RequiresNewerApp
+                } else if !satisfied.is_empty() {
+                    ResourceCompatibility::Outdated
+                } else {
+                    ResourceCompatibility::Incompatible
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(req: &str) -> AppTargetVersion {
+        AppTargetVersion::Range(AppVersionRange(VersionReq::parse(req).unwrap()))
+    }
+
+    #[test]
+    fn should_compare_tuple_targets_by_major() {
+        let app = Version::new(2, 4, 0);
+        let check =
+            |t: (u32, u32, u32)| AppTargetVersion::Tuple(t.0, t.1, t.2).compatibility_with(&app);
+        assert_eq!(check((2, 4, 0)), ResourceCompatibility::Compatible);
+        assert_eq!(check((2, 3, 12)), ResourceCompatibility::Outdated);
+        assert_eq!(check((2, 5, 0)), ResourceCompatibility::RequiresNewerApp);
+        assert_eq!(check((1, 9, 0)), ResourceCompatibility::Incompatible);
+        assert_eq!(check((3, 0, 0)), ResourceCompatibility::Incompatible);
+    }
+
+    #[test]
+    fn should_evaluate_semver_ranges() {
+        let app = Version::new(2, 4, 7);
+        assert_eq!(
+            range(">=2.3, <3").compatibility_with(&app),
+            ResourceCompatibility::Compatible
+        );
+        assert_eq!(
+            range(">=2.5").compatibility_with(&app),
+            ResourceCompatibility::RequiresNewerApp
+        );
+        assert_eq!(
+            range(">=2.0, <2.3").compatibility_with(&app),
+            ResourceCompatibility::Outdated
+        );
+        assert_eq!(
+            range("^1.9").compatibility_with(&app),
+            ResourceCompatibility::Incompatible
+        );
+        // exclusive bounds on the same major
+        assert_eq!(
+            range(">2.4.7, <2.5").compatibility_with(&app),
+            ResourceCompatibility::RequiresNewerApp
+        );
+        assert_eq!(
+            range(">2.3, <2.4.7").compatibility_with(&app),
+            ResourceCompatibility::Outdated
+        );
+        assert_eq!(
+            range("<2.4.7, >=2.4.3").compatibility_with(&app),
+            ResourceCompatibility::Outdated
+        );
+    }
+
+    #[test]
+    fn should_deserialize_both_target_formats() {
+        let tuple: AppTargetVersion = serde_json::from_str("[2, 3, 0]").unwrap();
+        assert_eq!(tuple, AppTargetVersion::Tuple(2, 3, 0));
+        let req: AppTargetVersion = serde_json::from_str("\">=2.3, <3\"").unwrap();
+        assert_eq!(req, range(">=2.3, <3"));
+    }
+}
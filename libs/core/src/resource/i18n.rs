@@ -0,0 +1,615 @@
+// Localization helpers used by `ResourceText`, this implements a small subset of
+// BCP-47 lookup and ICU MessageFormat (simple arguments, `plural` and `select`).
+
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::constants::SUPPORTED_LANGUAGES;
+
+/// Normalizes a language tag to the casing used on `SUPPORTED_LANGUAGES`, ex: `pt_br` -> `pt-BR`
+pub fn normalize_language_tag(tag: &str) -> String {
+    let mut parts = tag.trim().split(['-', '_']).filter(|p| !p.is_empty());
+    let mut normalized = match parts.next() {
+        Some(primary) => primary.to_lowercase(),
+        None => return String::new(),
+    };
+    for part in parts {
+        normalized.push('-');
+        match part.len() {
+            // region subtag, ex: BR, US
+            2 => normalized.push_str(&part.to_uppercase()),
+            // script subtag, ex: Hans, Latn
+            4 => {
+                let mut chars = part.chars();
+                if let Some(first) = chars.next() {
+                    normalized.extend(first.to_uppercase());
+                    normalized.push_str(&chars.as_str().to_lowercase());
+                }
+            }
+            _ => normalized.push_str(&part.to_lowercase()),
+        }
+    }
+    normalized
+}
+
+/// Returns the ordered list of languages to try when looking for a translation:
+/// the tag and its truncations, then the supported regional variants of the same
+/// language, and finally English.
+///
+/// ex: `pt-BR` -> `[pt-BR, pt, pt-PT, en]`
+pub fn language_fallback_chain(lang: &str) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut push = |tag: String| {
+        if !tag.is_empty() && !chain.contains(&tag) {
+            chain.push(tag);
+        }
+    };
+
+    let normalized = normalize_language_tag(lang);
+    let subtags: Vec<&str> = normalized.split('-').collect();
+    for len in (1..=subtags.len()).rev() {
+        push(subtags[..len].join("-"));
+    }
+
+    // sibling regional variants of the same language, ex: `pt-PT` for `pt-BR`
+    let primary = subtags[0];
+    for supported in SUPPORTED_LANGUAGES {
+        if supported.value.split('-').next() == Some(primary) {
+            push(supported.value.to_string());
+        }
+    }
+
+    push("en".to_string());
+    chain
+}
+
+/// Searchs a key on the map ignoring casing and `_`/`-` differences on the language tag
+pub(super) fn find_language<'a>(map: &'a HashMap<String, String>, tag: &str) -> Option<&'a str> {
+    if let Some(value) = map.get(tag).filter(|v| !v.is_empty()) {
+        return Some(value);
+    }
+    map.iter()
+        .find(|(key, value)| !value.is_empty() && normalize_language_tag(key) == tag)
+        .map(|(_, value)| value.as_str())
+}
+
+// =============================================================================
+
+/// CLDR plural categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+
+    /// Cardinal plural category of `n` for the given language.\
+    /// This covers the integer rules of the languages on `SUPPORTED_LANGUAGES`,
+    /// decimal numbers are always `Other` except for languages where CLDR says otherwise.
+    pub fn of(lang: &str, n: f64) -> Self {
+        use PluralCategory::*;
+
+        let primary = normalize_language_tag(lang);
+        let primary = primary.split('-').next().unwrap_or_default();
+
+        let abs = n.abs();
+        let is_int = abs.fract() == 0.0;
+        let i = abs.trunc() as u64;
+        let mod10 = i % 10;
+        let mod100 = i % 100;
+
+        match primary {
+            // no plural forms
+            "ja" | "ko" | "zh" | "vi" | "th" | "lo" | "km" | "id" | "ms" | "yo" => Other,
+            // 0 and 1 are singular
+            "fr" | "pt" | "hi" | "bn" | "gu" | "fa" | "am" | "pa" | "si" | "zu" => {
+                if i <= 1 {
+                    One
+                } else {
+                    Other
+                }
+            }
+            "ru" | "uk" | "bs" | "hr" | "sr" => {
+                if !is_int {
+                    Other
+                } else if mod10 == 1 && mod100 != 11 {
+                    One
+                } else if (2..=4).contains(&mod10) && !(12..=14).contains(&mod100) {
+                    Few
+                } else {
+                    Many
+                }
+            }
+            "pl" => {
+                if !is_int {
+                    Other
+                } else if i == 1 {
+                    One
+                } else if (2..=4).contains(&mod10) && !(12..=14).contains(&mod100) {
+                    Few
+                } else {
+                    Many
+                }
+            }
+            "cs" | "sk" => match (is_int, i) {
+                (false, _) => Many,
+                (true, 1) => One,
+                (true, 2..=4) => Few,
+                _ => Other,
+            },
+            "lt" => {
+                if !is_int {
+                    Many
+                } else if mod10 == 1 && !(11..=19).contains(&mod100) {
+                    One
+                } else if (2..=9).contains(&mod10) && !(11..=19).contains(&mod100) {
+                    Few
+                } else {
+                    Other
+                }
+            }
+            "lv" => {
+                if is_int && (mod10 == 0 || (11..=19).contains(&mod100)) {
+                    Zero
+                } else if mod10 == 1 && mod100 != 11 {
+                    One
+                } else {
+                    Other
+                }
+            }
+            "ro" => {
+                if is_int && i == 1 {
+                    One
+                } else if !is_int || i == 0 || (2..=19).contains(&mod100) {
+                    Few
+                } else {
+                    Other
+                }
+            }
+            "ar" => match (is_int, i, mod100) {
+                (false, _, _) => Other,
+                (true, 0, _) => Zero,
+                (true, 1, _) => One,
+                (true, 2, _) => Two,
+                (true, _, 3..=10) => Few,
+                (true, _, 11..=99) => Many,
+                _ => Other,
+            },
+            "he" => match (is_int, i) {
+                (true, 1) => One,
+                (true, 2) => Two,
+                _ => Other,
+            },
+            "cy" => match (is_int, i) {
+                (true, 0) => Zero,
+                (true, 1) => One,
+                (true, 2) => Two,
+                (true, 3) => Few,
+                (true, 6) => Many,
+                _ => Other,
+            },
+            "mt" => {
+                if !is_int {
+                    Other
+                } else if i == 1 {
+                    One
+                } else if i == 0 || (2..=10).contains(&mod100) {
+                    Few
+                } else if (11..=19).contains(&mod100) {
+                    Many
+                } else {
+                    Other
+                }
+            }
+            // english like languages
+            _ => {
+                if is_int && i == 1 {
+                    One
+                } else {
+                    Other
+                }
+            }
+        }
+    }
+}
+
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum MessagePart {
+    Text(String),
+    /// `#` inside of a plural branch
+    Hash,
+    Argument(String),
+    Plural {
+        arg: String,
+        offset: f64,
+        branches: Vec<(String, Vec<MessagePart>)>,
+    },
+    Select {
+        arg: String,
+        branches: Vec<(String, Vec<MessagePart>)>,
+    },
+}
+
+struct MessageParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl MessageParser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek()? == expected {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !matches!(c, '{' | '}' | ','))
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Parses until the end of input or until an unbalanced `}`
+    fn message(&mut self, in_plural: bool) -> Option<Vec<MessagePart>> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                '}' => break,
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(MessagePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(self.argument()?);
+                }
+                '#' if in_plural => {
+                    if !text.is_empty() {
+                        parts.push(MessagePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(MessagePart::Hash);
+                    self.pos += 1;
+                }
+                '\'' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\'') => {
+                            text.push('\'');
+                            self.pos += 1;
+                        }
+                        Some('{' | '}' | '#') => {
+                            // quoted literal until the next single apostrophe
+                            while let Some(c) = self.peek() {
+                                self.pos += 1;
+                                if c == '\'' {
+                                    break;
+                                }
+                                text.push(c);
+                            }
+                        }
+                        _ => text.push('\''),
+                    }
+                }
+                _ => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(MessagePart::Text(text));
+        }
+        Some(parts)
+    }
+
+    fn argument(&mut self) -> Option<MessagePart> {
+        self.expect('{')?;
+        let arg = self.identifier()?;
+        self.skip_whitespace();
+
+        if self.peek()? == '}' {
+            self.pos += 1;
+            return Some(MessagePart::Argument(arg));
+        }
+
+        self.expect(',')?;
+        let kind = self.identifier()?;
+        match kind.as_str() {
+            "plural" | "select" => {
+                self.expect(',')?;
+                let mut offset = 0.0;
+                let mut branches = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? == '}' {
+                        self.pos += 1;
+                        break;
+                    }
+                    let selector = self.identifier()?;
+                    if let Some(value) = selector.strip_prefix("offset:") {
+                        offset = value.parse().ok()?;
+                        continue;
+                    }
+                    self.expect('{')?;
+                    let branch = self.message(kind == "plural")?;
+                    self.expect('}')?;
+                    branches.push((selector, branch));
+                }
+
+                if !branches.iter().any(|(s, _)| s == "other") {
+                    return None;
+                }
+
+                Some(if kind == "plural" {
+                    MessagePart::Plural {
+                        arg,
+                        offset,
+                        branches,
+                    }
+                } else {
+                    MessagePart::Select { arg, branches }
+                })
+            }
+            // other formats as number, date, etc are rendered as simple arguments
+            _ => {
+                while self.peek()? != '}' {
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                Some(MessagePart::Argument(arg))
+            }
+        }
+    }
+}
+
+fn value_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn number_to_string(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn render(
+    parts: &[MessagePart],
+    lang: &str,
+    args: &serde_json::Value,
+    hash: Option<f64>,
+    out: &mut String,
+) {
+    for part in parts {
+        match part {
+            MessagePart::Text(text) => out.push_str(text),
+            MessagePart::Hash => match hash {
+                Some(n) => out.push_str(&number_to_string(n)),
+                None => out.push('#'),
+            },
+            MessagePart::Argument(name) => match args.get(name) {
+                Some(value) => out.push_str(&value_to_string(value)),
+                None => {
+                    out.push('{');
+                    out.push_str(name);
+                    out.push('}');
+                }
+            },
+            MessagePart::Plural {
+                arg,
+                offset,
+                branches,
+            } => {
+                let n = args.get(arg).and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let exact = format!("={}", number_to_string(n));
+                let category = PluralCategory::of(lang, n - offset);
+                let branch = branches
+                    .iter()
+                    .find(|(s, _)| *s == exact)
+                    .or_else(|| branches.iter().find(|(s, _)| s == category.as_str()))
+                    .or_else(|| branches.iter().find(|(s, _)| s == "other"));
+                if let Some((_, branch)) = branch {
+                    render(branch, lang, args, Some(n - offset), out);
+                }
+            }
+            MessagePart::Select { arg, branches } => {
+                let selected = args.get(arg).map(value_to_string).unwrap_or_default();
+                let branch = branches
+                    .iter()
+                    .find(|(s, _)| *s == selected)
+                    .or_else(|| branches.iter().find(|(s, _)| s == "other"));
+                if let Some((_, branch)) = branch {
+                    render(branch, lang, args, hash, out);
+                }
+            }
+        }
+    }
+}
+
+/// Formats an ICU like message, supporting simple arguments `{name}`, `{n, plural, ...}` and
+/// `{key, select, ...}`. `args` should be a json object.
+///
+/// If the message can't be parsed, it will be returned as is.
+pub fn format_message(message: &str, lang: &str, args: &serde_json::Value) -> String {
+    if !message.contains('{') {
+        return message.to_string();
+    }
+
+    let mut parser = MessageParser::new(message);
+    match parser.message(false) {
+        Some(parts) if parser.pos == parser.chars.len() => {
+            let mut out = String::with_capacity(message.len());
+            render(&parts, lang, args, None, &mut out);
+            out
+        }
+        _ => message.to_string(),
+    }
+}
+
+// =============================================================================
+
+/// Translation coverage of a resource, over the languages on `SUPPORTED_LANGUAGES`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationCoverage {
+    /// Number of supported languages evaluated
+    pub total: usize,
+    /// Languages that have all the texts translated, (regional fallbacks are taken as translated)
+    pub complete: Vec<String>,
+    /// Missing languages by text field, ex: `{ "displayName": ["de", "fr"] }`
+    pub missing: BTreeMap<String, Vec<String>>,
+}
+
+impl TranslationCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.missing.values().all(|langs| langs.is_empty())
+    }
+
+    /// Percentage of translated languages from 0 to 1
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.complete.len() as f64 / self.total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_build_bcp47_fallback_chain() {
+        assert_eq!(
+            language_fallback_chain("pt_br"),
+            vec!["pt-BR", "pt", "pt-PT", "en"]
+        );
+        assert_eq!(
+            language_fallback_chain("zh"),
+            vec!["zh", "zh-CN", "zh-TW", "en"]
+        );
+        assert_eq!(language_fallback_chain("en-US"), vec!["en-US", "en"]);
+    }
+
+    #[test]
+    fn should_prefer_sibling_regions_over_english() {
+        let text: crate::resource::ResourceText = serde_json::from_value(json!({
+            "en": "Color",
+            "pt-PT": "Cor (PT)",
+        }))
+        .unwrap();
+        assert_eq!(text.get("pt-BR"), "Cor (PT)");
+        assert!(text.is_translated("pt-BR"));
+
+        // the base language still wins over siblings
+        let text: crate::resource::ResourceText = serde_json::from_value(json!({
+            "en": "Color",
+            "pt": "Cor",
+            "pt-PT": "Cor (PT)",
+        }))
+        .unwrap();
+        assert_eq!(text.get("pt-BR"), "Cor");
+        assert_eq!(text.get("de"), "Color");
+    }
+
+    #[test]
+    fn should_select_plural_categories() {
+        assert_eq!(PluralCategory::of("en", 1.0), PluralCategory::One);
+        assert_eq!(PluralCategory::of("en", 0.0), PluralCategory::Other);
+        assert_eq!(PluralCategory::of("fr", 0.0), PluralCategory::One);
+        assert_eq!(PluralCategory::of("ru", 22.0), PluralCategory::Few);
+        assert_eq!(PluralCategory::of("ru", 11.0), PluralCategory::Many);
+        assert_eq!(PluralCategory::of("ar", 2.0), PluralCategory::Two);
+        assert_eq!(PluralCategory::of("ja", 1.0), PluralCategory::Other);
+    }
+
+    #[test]
+    fn should_format_plural_and_select_messages() {
+        let msg = "{name} has {count, plural, =0 {no files} one {# file} other {# files}}";
+        assert_eq!(
+            format_message(msg, "en", &json!({ "name": "Ana", "count": 0 })),
+            "Ana has no files"
+        );
+        assert_eq!(
+            format_message(msg, "en", &json!({ "name": "Ana", "count": 1 })),
+            "Ana has 1 file"
+        );
+        assert_eq!(
+            format_message(msg, "en", &json!({ "name": "Ana", "count": 5 })),
+            "Ana has 5 files"
+        );
+
+        let msg = "{gender, select, female {She} male {He} other {They}} left";
+        assert_eq!(
+            format_message(msg, "en", &json!({ "gender": "female" })),
+            "She left"
+        );
+        assert_eq!(format_message(msg, "en", &json!({})), "They left");
+    }
+
+    #[test]
+    fn should_keep_invalid_or_escaped_messages() {
+        assert_eq!(format_message("{broken", "en", &json!({})), "{broken");
+        assert_eq!(
+            format_message("'{literal}' and it''s {x}", "en", &json!({ "x": 1 })),
+            "{literal} and it's 1"
+        );
+    }
+}
@@ -4,8 +4,8 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
     error::Result,
-    resource::{deserialize_extended_yaml, ResourceKind, SluResourceFile},
-    utils::search_resource_entrypoint,
+    resource::{deserialize_extended_yaml, ResourceKind, SluResourceFile, TranslationCoverage},
+    utils::{search_resource_entrypoint, write_file_atomically},
 };
 
 use super::ResourceMetadata;
@@ -19,34 +19,7 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
     /// Try to load the resource from a file.\
     /// This won't run post loading processing, please use `load` instead.
     fn load_from_file(path: &Path) -> Result<Self> {
-        let ext = path
-            .extension()
-            .ok_or("Invalid file extension")?
-            .to_ascii_lowercase();
-
-        let resource: Self = match ext.to_string_lossy().as_ref() {
-            "yml" | "yaml" => deserialize_extended_yaml(path)?,
-            "json" | "jsonc" => {
-                let file = File::open(path)?;
-                file.lock_shared()?;
-                serde_json::from_reader(file)?
-            }
-            "slu" => {
-                let file = SluResourceFile::load(path)?;
-                if Self::KIND != file.resource.kind {
-                    return Err(format!(
-                        "Resource file is not of expected kind: {:?} instead is {:?}",
-                        Self::KIND,
-                        file.resource.kind
-                    )
-                    .into());
-                }
-                file.try_parse_into()?
-            }
-            _ => return Err("Invalid file extension".into()),
-        };
-
-        Ok(resource)
+        load_resource_file(path)
     }
 
     /// Try to load the resource from a folder.\
@@ -58,7 +31,8 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
 
     /// Try to load the resource from a file or directory.\
     /// After deserialization, this will run post loading processing like `sanitize` and `validate`,
-    /// Also will set the internal metadata needed to handle the resource
+    /// Also will set the internal metadata needed to handle the resource, including the
+    /// compatibility status against the running app version.
     fn load(path: &Path) -> Result<Self> {
         let mut resource = if path.is_dir() {
             Self::load_from_folder(path)?
@@ -74,9 +48,20 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
             .to_string_lossy()
             .to_string();
         meta.internal.written_at = path.metadata()?.modified()?.into();
+        meta.internal.compatibility = meta
+            .app_target_version
+            .as_ref()
+            .map(|target| target.compatibility())
+            .unwrap_or_default();
 
         resource.sanitize();
-        resource.validate()?;
+        // quarantined resources are kept loaded (disabled) even if they are not valid anymore
+        // for the current app version, so the user can see them and update or remove them.
+        if let Err(err) = resource.validate() {
+            if !resource.metadata().is_quarantined() {
+                return Err(err);
+            }
+        }
         Ok(resource)
     }
 
@@ -109,12 +94,10 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
                 slu_file.store(&save_path)?;
             }
             "yml" | "yaml" => {
-                let file = File::create(save_path)?;
-                serde_yaml::to_writer(file, self)?;
+                write_file_atomically(&save_path, serde_yaml::to_string(self)?.as_bytes())?;
             }
             "json" | "jsonc" => {
-                let file = File::create(save_path)?;
-                serde_json::to_writer_pretty(file, self)?;
+                write_file_atomically(&save_path, &serde_json::to_vec_pretty(self)?)?;
             }
             _ => {
                 return Err("Unsupported path extension".into());
@@ -123,6 +106,11 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
         Ok(())
     }
 
+    /// Report of the supported languages missing on the resource texts
+    fn translation_coverage(&self) -> TranslationCoverage {
+        self.metadata().translation_coverage()
+    }
+
     fn delete(&self) -> Result<()> {
         let path = self.metadata().internal.path.to_path_buf();
         if path.is_dir() {
@@ -133,3 +121,36 @@ pub trait SluResource: Sized + Serialize + DeserializeOwned {
         Ok(())
     }
 }
+
+/// Default implementation of [`SluResource::load_from_file`], usable by the resources
+/// that override it to add extra processing.
+pub fn load_resource_file<R: SluResource>(path: &Path) -> Result<R> {
+    let ext = path
+        .extension()
+        .ok_or("Invalid file extension")?
+        .to_ascii_lowercase();
+
+    let resource: R = match ext.to_string_lossy().as_ref() {
+        "yml" | "yaml" => deserialize_extended_yaml(path)?,
+        "json" | "jsonc" => {
+            let file = File::open(path)?;
+            file.lock_shared()?;
+            serde_json::from_reader(file)?
+        }
+        "slu" => {
+            let file = SluResourceFile::load(path)?;
+            if R::KIND != file.resource.kind {
+                return Err(format!(
+                    "Resource file is not of expected kind: {:?} instead is {:?}",
+                    R::KIND,
+                    file.resource.kind
+                )
+                .into());
+            }
+            file.try_parse_into()?
+        }
+        _ => return Err("Invalid file extension".into()),
+    };
+
+    Ok(resource)
+}
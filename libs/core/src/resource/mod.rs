@@ -1,11 +1,17 @@
+mod compatibility;
 mod file;
+mod i18n;
 mod interface;
 mod resource_id;
+mod scss;
 mod yaml_ext;
 
+pub use compatibility::*;
 pub use file::*;
+pub use i18n::*;
 pub use interface::*;
 pub use resource_id::*;
+pub use scss::*;
 pub use yaml_ext::*;
 
 use std::{
@@ -21,7 +27,7 @@ use ts_rs::TS;
 use url::Url;
 use uuid::Uuid;
 
-use crate::error::Result;
+use crate::{constants::SUPPORTED_LANGUAGES, error::Result};
 
 // =============================================================================
 
@@ -44,21 +50,51 @@ impl ResourceText {
         }
     }
 
-    /// Returns the text by lang, uses `en` as fallback.
+    /// Returns the text by lang, following the fallback chain of [`language_fallback_chain`],
+    /// ex: `pt-BR` -> `pt` -> `pt-PT` -> `en`.
     /// If no text fallback found will return `!?`
     pub fn get(&self, lang: &str) -> &str {
+        self.resolve(lang)
+            .map(|(_, text)| text)
+            .unwrap_or(Self::MISSING_TEXT)
+    }
+
+    /// Returns the language and text found for the given lang, following the fallback chain.
+    pub fn resolve(&self, lang: &str) -> Option<(String, &str)> {
         match self {
-            ResourceText::En(value) => value,
-            ResourceText::Localized(map) => match map.get(lang) {
-                Some(value) => value,
-                None => match map.get("en") {
-                    Some(value) => value,
-                    None => Self::MISSING_TEXT,
-                },
-            },
+            ResourceText::En(value) => Some(("en".to_string(), value)),
+            ResourceText::Localized(map) => language_fallback_chain(lang)
+                .into_iter()
+                .find_map(|tag| find_language(map, &tag).map(|text| (tag, text))),
+        }
+    }
+
+    /// Returns true if the lang can be resolved without falling back to `en`,
+    /// regional variants are taken as valid translations, ex: `pt` for `pt-BR`.
+    pub fn is_translated(&self, lang: &str) -> bool {
+        self.resolve(lang)
+            .is_some_and(|(tag, _)| tag != "en" || normalize_language_tag(lang) == "en")
+    }
+
+    /// Returns the text by lang with the ICU message arguments applied, ex:
+    /// `{count, plural, one {# item} other {# items}}`.\
+    /// `args` should be a json object.
+    pub fn format(&self, lang: &str, args: &serde_json::Value) -> String {
+        match self.resolve(lang) {
+            Some((tag, text)) => format_message(text, &tag, args),
+            None => Self::MISSING_TEXT.to_string(),
         }
     }
 
+    /// Supported languages that are not translated on this text
+    pub fn missing_languages(&self) -> Vec<&'static str> {
+        SUPPORTED_LANGUAGES
+            .iter()
+            .map(|l| l.value)
+            .filter(|lang| !self.is_translated(lang))
+            .collect()
+    }
+
     pub fn set(&mut self, lang: impl Into<String>, value: impl Into<String>) {
         if let ResourceText::En(v) = self {
             let mut dict = HashMap::new();
@@ -89,10 +125,12 @@ pub struct ResourceMetadata {
     pub screenshots: Vec<Url>,
     /// tags are keywords to be used for searching and indexing
     pub tags: Vec<String>,
-    /// App target version that this resource is compatible with.\
+    /// App target version that this resource is compatible with, could be a version tuple
+    /// as `[2, 3, 0]` or a semver range as `>=2.3, <3`.\
     /// Developers are responsible to update the resource so when resource does not
-    /// match the current app version, the resource will be shown with a warning message
-    pub app_target_version: Option<(u32, u32, u32)>,
+    /// match the current app version, the resource will be shown with a warning message.
+    /// Resources targeting a different major version will be loaded disabled.
+    pub app_target_version: Option<AppTargetVersion>,
     #[serde(flatten, skip_deserializing)]
     pub internal: InternalResourceMetadata,
 }
@@ -105,6 +143,43 @@ pub struct InternalResourceMetadata {
     pub bundled: bool,
     /// Last date when the metadata file was written
     pub written_at: DateTime<Utc>,
+    /// Compatibility of the resource with the running app version
+    pub compatibility: ResourceCompatibility,
+}
+
+impl ResourceMetadata {
+    /// Quarantined resources are loaded but should not be applied/enabled.
+    pub fn is_quarantined(&self) -> bool {
+        self.internal.compatibility.is_quarantined()
+    }
+
+    /// Translation report of the texts on the metadata
+    pub fn translation_coverage(&self) -> TranslationCoverage {
+        let fields = [
+            ("displayName", &self.display_name),
+            ("description", &self.description),
+        ];
+
+        let mut coverage = TranslationCoverage {
+            total: SUPPORTED_LANGUAGES.len(),
+            ..Default::default()
+        };
+        for (field, text) in fields {
+            coverage.missing.insert(
+                field.to_string(),
+                text.missing_languages()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            );
+        }
+        coverage.complete = SUPPORTED_LANGUAGES
+            .iter()
+            .map(|l| l.value.to_string())
+            .filter(|lang| !coverage.missing.values().any(|m| m.contains(lang)))
+            .collect();
+        coverage
+    }
 }
 
 impl Default for ResourceMetadata {
@@ -0,0 +1,323 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::utils::{sha256_hex, write_file_atomically};
+
+/// Bump this to invalidate all the cached stylesheets, ex: on grass updates.
+const CACHE_VERSION: &str = "v1";
+
+/// Cached stylesheets not used on this time are removed.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Max size of the cache folder, the least recently used entries are removed first.
+const CACHE_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+static SCSS_CACHE_DIR: LazyLock<RwLock<Option<PathBuf>>> = LazyLock::new(|| {
+    RwLock::new(Some(
+        std::env::temp_dir().join("seelen-ui").join("scss-cache"),
+    ))
+});
+
+thread_local! {
+    static DIAGNOSTICS_COLLECTOR: RefCell<Option<Vec<StyleDiagnostic>>> = const { RefCell::new(None) };
+}
+
+/// Error found while compiling a style file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct StyleDiagnostic {
+    pub file: PathBuf,
+    /// 1-indexed line, if known
+    pub line: Option<usize>,
+    /// 1-indexed column, if known
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for StyleDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, ":{line}:{column}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl StyleDiagnostic {
+    fn from_grass(entry: &Path, err: grass::Error) -> Self {
+        match err.kind() {
+            grass::ErrorKind::ParseError { message, loc, .. } => Self {
+                file: PathBuf::from(loc.file.name()),
+                line: Some(loc.begin.line + 1),
+                column: Some(loc.begin.column + 1),
+                message,
+            },
+            grass::ErrorKind::IoError(err) => Self::without_location(entry, err.to_string()),
+            grass::ErrorKind::FromUtf8Error(message) => Self::without_location(entry, message),
+            _ => Self::without_location(entry, "unknown scss error".to_owned()),
+        }
+    }
+
+    fn without_location(file: &Path, message: String) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line: None,
+            column: None,
+            message,
+        }
+    }
+}
+
+/// Changes the folder used to cache compiled stylesheets, `None` disables the disk cache.\
+/// By default the cache is stored on the temp folder of the system.
+pub fn set_scss_cache_dir(dir: Option<PathBuf>) {
+    *SCSS_CACHE_DIR.write().unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
+fn scss_cache_dir() -> Option<PathBuf> {
+    SCSS_CACHE_DIR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Runs `f` collecting the style diagnostics reported while it runs.
+/// While collecting, style errors on extended yaml includes are reported here instead
+/// of failing the whole deserialization.
+pub(crate) fn collect_style_diagnostics<T>(f: impl FnOnce() -> T) -> (T, Vec<StyleDiagnostic>) {
+    let previous = DIAGNOSTICS_COLLECTOR.with(|c| c.replace(Some(Vec::new())));
+    let result = f();
+    let collected = DIAGNOSTICS_COLLECTOR.with(|c| c.replace(previous));
+    (result, collected.unwrap_or_default())
+}
+
+/// Returns the diagnostic back if there is no active collector.
+pub(crate) fn report_style_diagnostic(diagnostic: StyleDiagnostic) -> Result<(), StyleDiagnostic> {
+    DIAGNOSTICS_COLLECTOR.with(|c| match c.borrow_mut().as_mut() {
+        Some(collected) => {
+            collected.push(diagnostic);
+            Ok(())
+        }
+        None => Err(diagnostic),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedDependency {
+    path: PathBuf,
+    hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedStylesheet {
+    /// Every file read while compiling, including the entry file.
+    dependencies: Vec<CachedDependency>,
+    css: String,
+}
+
+impl CachedStylesheet {
+    fn is_fresh(&self) -> bool {
+        self.dependencies.iter().all(|dep| {
+            std::fs::read(&dep.path).is_ok_and(|content| sha256_hex(&content) == dep.hash)
+        })
+    }
+}
+
+/// File system used by grass that keeps track of the files read while compiling.
+#[derive(Debug, Default)]
+struct TrackingFs {
+    read: RefCell<Vec<CachedDependency>>,
+}
+
+impl grass::Fs for TrackingFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let content = std::fs::read(path)?;
+        self.read.borrow_mut().push(CachedDependency {
+            path: path.to_path_buf(),
+            hash: sha256_hex(&content),
+        });
+        Ok(content)
+    }
+}
+
+/// Compiles a scss/sass file, reusing the cached css if neither the file nor its
+/// imports changed since the last compilation.
+pub fn compile_scss(path: &Path) -> Result<String, StyleDiagnostic> {
+    compile_scss_with_cache(path, scss_cache_dir().as_deref())
+}
+
+/// Same as [`compile_scss`] but using a specific cache folder, `None` to skip the cache.
+pub fn compile_scss_with_cache(
+    path: &Path,
+    cache_dir: Option<&Path>,
+) -> Result<String, StyleDiagnostic> {
+    let content =
+        std::fs::read(path).map_err(|e| StyleDiagnostic::without_location(path, e.to_string()))?;
+
+    let cache_file = cache_dir.map(|dir| {
+        let mut key = Vec::new();
+        key.extend_from_slice(CACHE_VERSION.as_bytes());
+        key.push(0);
+        key.extend_from_slice(path.to_string_lossy().as_bytes());
+        key.push(0);
+        key.extend_from_slice(&content);
+        dir.join(format!("{}.json", sha256_hex(&key)))
+    });
+
+    if let Some(cache_file) = &cache_file {
+        let cached = std::fs::read(cache_file)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CachedStylesheet>(&bytes).ok());
+        if let Some(cached) = cached.filter(|c| c.is_fresh()) {
+            // the modified time is used as last access time for the eviction
+            let _ = std::fs::File::options()
+                .append(true)
+                .open(cache_file)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok(cached.css);
+        }
+    }
+
+    let fs = TrackingFs::default();
+    let css = grass::from_path(path, &grass::Options::default().fs(&fs))
+        .map_err(|e| StyleDiagnostic::from_grass(path, *e))?;
+
+    if let Some(cache_file) = &cache_file {
+        let cached = CachedStylesheet {
+            dependencies: fs.read.take(),
+            css,
+        };
+        // cache failures should never break the compilation
+        if let Ok(bytes) = serde_json::to_vec(&cached) {
+            let _ = write_file_atomically(cache_file, &bytes);
+        }
+        if let Some(dir) = cache_dir {
+            let _ = prune_scss_cache(dir, CACHE_MAX_AGE, CACHE_MAX_BYTES);
+        }
+        return Ok(cached.css);
+    }
+    Ok(css)
+}
+
+/// Removes the cached stylesheets not used since `max_age`, then the least recently
+/// used ones until the folder fits on `max_bytes`. Returns the number of removed entries.
+pub fn prune_scss_cache(dir: &Path, max_age: Duration, max_bytes: u64) -> std::io::Result<usize> {
+    let now = SystemTime::now();
+    let mut entries = Vec::new();
+    for entry in dir.read_dir()?.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() || path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let used_at = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((used_at, metadata.len(), path));
+    }
+    // newest first
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+
+    let mut removed = 0;
+    let mut total = 0;
+    for (used_at, len, path) in entries {
+        let expired = now.duration_since(used_at).unwrap_or_default() > max_age;
+        if expired || total + len > max_bytes {
+            if std::fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+            continue;
+        }
+        total += len;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("slu-scss-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn should_invalidate_cache_when_imports_change() {
+        let dir = temp_folder();
+        let cache = dir.join("cache");
+        std::fs::write(dir.join("_colors.scss"), "$accent: red;").unwrap();
+        std::fs::write(
+            dir.join("main.scss"),
+            "@import 'colors';\na { color: $accent; }",
+        )
+        .unwrap();
+
+        let css = compile_scss_with_cache(&dir.join("main.scss"), Some(&cache)).unwrap();
+        assert!(css.contains("color: red"));
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+        // served from cache
+        let cached = compile_scss_with_cache(&dir.join("main.scss"), Some(&cache)).unwrap();
+        assert_eq!(css, cached);
+
+        std::fs::write(dir.join("_colors.scss"), "$accent: blue;").unwrap();
+        let css = compile_scss_with_cache(&dir.join("main.scss"), Some(&cache)).unwrap();
+        assert!(css.contains("color: blue"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_evict_old_and_oversized_entries() {
+        let dir = temp_folder();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = SystemTime::now();
+        for (name, age_days) in [("new", 0), ("old", 2), ("expired", 40)] {
+            let path = dir.join(format!("{name}.json"));
+            std::fs::write(&path, [0u8; 100]).unwrap();
+            let file = std::fs::File::options().append(true).open(&path).unwrap();
+            file.set_modified(now - day * age_days).unwrap();
+        }
+
+        assert_eq!(prune_scss_cache(&dir, day * 30, 1000).unwrap(), 1);
+        assert!(!dir.join("expired.json").exists());
+        // only one entry fits, the least recently used is removed
+        assert_eq!(prune_scss_cache(&dir, day * 30, 150).unwrap(), 1);
+        assert!(dir.join("new.json").exists());
+        assert!(!dir.join("old.json").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_report_error_location() {
+        let dir = temp_folder();
+        let file = dir.join("broken.scss");
+        std::fs::write(&file, "a {\n  color: $undefined;\n}").unwrap();
+
+        let diagnostic = compile_scss_with_cache(&file, None).unwrap_err();
+        assert_eq!(diagnostic.file, file);
+        assert_eq!(diagnostic.line, Some(2));
+        assert_eq!(diagnostic.column, Some(10));
+        assert!(diagnostic.message.contains("Undefined variable"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
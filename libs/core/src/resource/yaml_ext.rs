@@ -4,7 +4,10 @@ use std::{fs::File, path::Path};
 
 use serde_yaml::{Mapping, Value};
 
-use crate::error::Result;
+use crate::{
+    error::Result,
+    resource::{compile_scss, report_style_diagnostic},
+};
 
 /// Will deserialize a YAML file and parse the custom extended syntax
 pub fn deserialize_extended_yaml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
@@ -17,26 +20,27 @@ fn read_and_parse_yml(path: &Path) -> Result<Value> {
     file.lock_shared()?;
     let base = path.parent().ok_or("No parent directory")?;
     let value: Value = serde_yaml::from_reader(file)?;
-    parse_yaml(base, value)
+    Ok(parse_yaml(base, value)?.unwrap_or(Value::Null))
 }
 
-fn parse_yaml(base: &Path, value: Value) -> Result<Value> {
+/// `None` if the value was dropped, as a style that failed to compile.
+fn parse_yaml(base: &Path, value: Value) -> Result<Option<Value>> {
     match value {
         Value::Mapping(map) => {
             let mut new_map = Mapping::new();
             for (key, value) in map {
-                let value = parse_yaml(base, value)?;
-                new_map.insert(key, value);
+                if let Some(value) = parse_yaml(base, value)? {
+                    new_map.insert(key, value);
+                }
             }
-            Ok(Value::Mapping(new_map))
+            Ok(Some(Value::Mapping(new_map)))
         }
         Value::Sequence(seq) => {
             let mut new_seq = Vec::new();
             for value in seq {
-                let value = parse_yaml(base, value)?;
-                new_seq.push(value);
+                new_seq.extend(parse_yaml(base, value)?);
             }
-            Ok(Value::Sequence(new_seq))
+            Ok(Some(Value::Sequence(new_seq)))
         }
         Value::Tagged(tag) => {
             if tag.tag == "!include" {
@@ -46,23 +50,30 @@ fn parse_yaml(base: &Path, value: Value) -> Result<Value> {
                         .extension()
                         .is_some_and(|ext| ext == "scss" || ext == "sass")
                     {
-                        grass::from_path(&to_include, &grass::Options::default())?
+                        match compile_scss(&to_include) {
+                            Ok(css) => css,
+                            Err(diagnostic) => {
+                                // drop only the broken style if someone is collecting the errors
+                                report_style_diagnostic(diagnostic).map_err(|d| d.to_string())?;
+                                return Ok(None);
+                            }
+                        }
                     } else {
                         std::fs::read_to_string(&to_include)?
                     };
-                    return Ok(Value::String(text));
+                    return Ok(Some(Value::String(text)));
                 }
             }
 
             if tag.tag == "!extend" {
                 if let Value::String(relative_path) = tag.value {
                     let value = read_and_parse_yml(&base.join(relative_path))?;
-                    return Ok(value);
+                    return Ok(Some(value));
                 }
             }
 
-            Ok(Value::Tagged(tag))
+            Ok(Some(Value::Tagged(tag)))
         }
-        _ => Ok(value),
+        _ => Ok(Some(value)),
     }
 }
@@ -0,0 +1,673 @@
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    resource::{
+        app_version, AppTargetVersion, IconPackId, Resource, ResourceKind, ResourceStatus,
+        ResourceText, SluResourceFile,
+    },
+    state::{
+        CustomIconPackEntry, Icon, IconPack, IconPackEntry, SharedIconPackEntry,
+        UniqueIconPackEntry,
+    },
+    utils::TsUnknown,
+};
+
+/// Image extensions accepted when building a pack from a folder
+pub static ICON_PACK_IMAGE_EXTENSIONS: &[&str] =
+    &["png", "jpg", "jpeg", "ico", "svg", "webp", "gif", "bmp"];
+
+/// Subfolder whose images are added as custom entries, using the file stem as key.
+static CUSTOM_FOLDER: &str = "custom";
+/// Image file stem used as the missing icon of the pack
+static MISSING_STEM: &str = "missing";
+
+/// Max difference between width and height, relative to the biggest side,
+/// for an image to be considered approximately square.
+const SQUARE_TOLERANCE: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum IconPackLintKind {
+    /// The icon doesn't have a base nor both light and dark variants
+    InvalidIcon,
+    /// The icon file doesn't exist on the pack folder
+    MissingFile,
+    /// The icon is marked as approximately square but the image isn't
+    NotSquare,
+    /// More than one entry matches the same app, extension or key
+    DuplicateEntry,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct IconPackLint {
+    pub kind: IconPackLintKind,
+    /// Index of the entry on `entries`, `None` for the missing icon.
+    pub entry: Option<usize>,
+    pub message: String,
+}
+
+/// Reads the image size from the file header, without decoding the image.
+pub fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut header = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(64 * 1024)
+        .read_to_end(&mut header)
+        .ok()?;
+    let h = header.as_slice();
+
+    let be16 = |i: usize| Some(u16::from_be_bytes([*h.get(i)?, *h.get(i + 1)?]) as u32);
+    let le16 = |i: usize| Some(u16::from_le_bytes([*h.get(i)?, *h.get(i + 1)?]) as u32);
+    let be32 = |i: usize| Some(u32::from_be_bytes(h.get(i..i + 4)?.try_into().ok()?));
+    let le32 = |i: usize| Some(u32::from_le_bytes(h.get(i..i + 4)?.try_into().ok()?));
+
+    if h.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if h.starts_with(b"GIF8") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if h.starts_with(b"BM") {
+        return Some((le32(18)?, le32(22)?.cast_signed().unsigned_abs()));
+    }
+    if h.starts_with(&[0, 0, 1, 0]) {
+        // ico, size of the first image where 0 means 256
+        let size = |v: u8| if v == 0 { 256 } else { v as u32 };
+        return Some((size(*h.get(6)?), size(*h.get(7)?)));
+    }
+    if h.starts_with(b"RIFF") && h.get(8..12)? == b"WEBP" {
+        return match h.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = le32(21)?;
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            b"VP8X" => {
+                let w = le32(24)? & 0xffffff;
+                let h = le32(27)? & 0xffffff;
+                Some((w + 1, h + 1))
+            }
+            _ => None,
+        };
+    }
+    if h.starts_with(&[0xff, 0xd8]) {
+        // jpeg, search the start of frame marker
+        let mut i = 2;
+        while i + 9 < h.len() {
+            if h[i] != 0xff {
+                return None;
+            }
+            let marker = h[i + 1];
+            let len = be16(i + 2)? as usize;
+            if matches!(marker, 0xc0..=0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf) {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + len;
+        }
+        return None;
+    }
+    svg_dimensions(&String::from_utf8_lossy(h))
+}
+
+fn svg_dimensions(svg: &str) -> Option<(u32, u32)> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+    let attr = |name: &str| {
+        let pattern = format!("{name}=\"");
+        let from = tag.find(&pattern)? + pattern.len();
+        Some(&tag[from..from + tag[from..].find('"')?])
+    };
+    let number = |v: &str| v.trim_end_matches("px").trim().parse::<f64>().ok();
+
+    if let (Some(w), Some(h)) = (
+        attr("width").and_then(number),
+        attr("height").and_then(number),
+    ) {
+        return Some((w.round() as u32, h.round() as u32));
+    }
+    let view_box: Vec<f64> = attr("viewBox")?
+        .split([' ', ','])
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    match view_box.as_slice() {
+        [_, _, w, h] => Some((w.round() as u32, h.round() as u32)),
+        _ => None,
+    }
+}
+
+fn is_approximately_square((width, height): (u32, u32)) -> bool {
+    let max = width.max(height) as f64;
+    max > 0.0 && (width as f64 - height as f64).abs() / max <= SQUARE_TOLERANCE
+}
+
+fn image_mime(path: &str) -> &'static str {
+    match path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("svg") => "image/svg+xml",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("ico") => "image/x-icon",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("bmp") => "image/bmp",
+        _ => "image/png",
+    }
+}
+
+/// Cloud resources require the english texts to be present.
+fn ensure_english_text(text: &mut ResourceText, fallback: &str) {
+    match text {
+        ResourceText::En(value) if value.is_empty() => *value = fallback.to_owned(),
+        ResourceText::Localized(map) if map.get("en").is_none_or(|v| v.is_empty()) => {
+            map.insert("en".to_owned(), fallback.to_owned());
+        }
+        _ => {}
+    }
+}
+
+/// Remote or embedded icons are not stored on the pack folder.
+fn is_local_icon_path(path: &str) -> bool {
+    !(path.starts_with("data:") || path.starts_with("http://") || path.starts_with("https://"))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum IconVariant {
+    Base,
+    Light,
+    Dark,
+    Mask,
+}
+
+/// Splits `explorer.exe.dark` into `("explorer.exe", Dark)`.
+fn split_variant(stem: &str) -> (&str, IconVariant) {
+    for (suffix, variant) in [
+        (".light", IconVariant::Light),
+        (".dark", IconVariant::Dark),
+        (".mask", IconVariant::Mask),
+    ] {
+        if let Some(name) = stem.strip_suffix(suffix) {
+            return (name, variant);
+        }
+    }
+    (stem, IconVariant::Base)
+}
+
+impl IconPack {
+    /// Generates a pack from a folder of images named by the target, as:
+    /// - `explorer.exe.png`: app icon matched by filename.
+    /// - `Microsoft.WindowsCalculator_8wekyb3d8bbwe!App.png`: app icon matched by UMID.
+    /// - `txt.png`: icon for files with the `txt` extension.
+    /// - `custom/my-icon.png`: custom icon with key `my-icon`.
+    /// - `missing.png`: icon used when nothing matches.
+    ///
+    /// Variants are added using `.light`, `.dark` or `.mask` before the extension,
+    /// ex: `txt.dark.png`.
+    pub fn build_from_folder(folder: &Path, id: IconPackId) -> Result<IconPack> {
+        let mut pack = IconPack {
+            id,
+            ..Default::default()
+        };
+        pack.metadata.internal.path = folder.to_path_buf();
+
+        let mut files = Vec::new();
+        for (dir, prefix) in [
+            (folder.to_path_buf(), ""),
+            (folder.join(CUSTOM_FOLDER), CUSTOM_FOLDER),
+        ] {
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in dir.read_dir()?.flatten() {
+                let path = entry.path();
+                let is_image = path.extension().is_some_and(|ext| {
+                    let ext = ext.to_string_lossy().to_lowercase();
+                    ICON_PACK_IMAGE_EXTENSIONS.contains(&ext.as_str())
+                });
+                if path.is_file() && is_image {
+                    files.push((prefix, path));
+                }
+            }
+        }
+        // deterministic output
+        files.sort();
+
+        // an icon is square only if all its variants are, masks excluded
+        let mut squares: BTreeMap<(&str, String), bool> = BTreeMap::new();
+        for (prefix, path) in files {
+            let Some(stem) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let relative = match prefix.is_empty() {
+                true => file_name.to_string(),
+                false => format!("{prefix}/{file_name}"),
+            };
+
+            let (name, variant) = split_variant(&stem);
+            let icon = pack.icon_for_name(prefix, name);
+            match variant {
+                IconVariant::Base => icon.base = Some(relative),
+                IconVariant::Light => icon.light = Some(relative),
+                IconVariant::Dark => icon.dark = Some(relative),
+                IconVariant::Mask => icon.mask = Some(relative),
+            }
+            if !matches!(variant, IconVariant::Mask) {
+                let square = image_dimensions(&path).is_some_and(is_approximately_square);
+                *squares.entry((prefix, name.to_owned())).or_insert(true) &= square;
+            }
+        }
+        for ((prefix, name), square) in squares {
+            pack.icon_for_name(prefix, &name).is_aproximately_square = square;
+        }
+        Ok(pack)
+    }
+
+    /// Returns the icon that will hold the file named as `name`, creating its entry if needed.
+    fn icon_for_name(&mut self, prefix: &str, name: &str) -> &mut Icon {
+        if prefix.is_empty() && name == MISSING_STEM {
+            return self.missing.get_or_insert_with(Icon::default);
+        }
+
+        let new_entry = if prefix == CUSTOM_FOLDER {
+            IconPackEntry::Custom(CustomIconPackEntry {
+                key: name.to_owned(),
+                icon: Icon::default(),
+            })
+        } else if name.contains('!') {
+            IconPackEntry::Unique(UniqueIconPackEntry {
+                umid: Some(name.to_owned()),
+                path: None,
+                redirect: None,
+                icon: Some(Icon::default()),
+            })
+        } else if name.contains('.') {
+            IconPackEntry::Unique(UniqueIconPackEntry {
+                umid: None,
+                path: Some(PathBuf::from(name)),
+                redirect: None,
+                icon: Some(Icon::default()),
+            })
+        } else {
+            IconPackEntry::Shared(SharedIconPackEntry {
+                extension: name.to_lowercase(),
+                icon: Icon::default(),
+            })
+        };
+
+        let idx = match self.entries.iter().position(|e| e.matches(&new_entry)) {
+            Some(idx) => idx,
+            None => {
+                self.entries.push(new_entry);
+                self.entries.len() - 1
+            }
+        };
+        match &mut self.entries[idx] {
+            IconPackEntry::Unique(e) => e.icon.get_or_insert_with(Icon::default),
+            IconPackEntry::Shared(e) => &mut e.icon,
+            IconPackEntry::Custom(e) => &mut e.icon,
+        }
+    }
+
+    fn lint_icon(&self, icon: &Icon, entry: Option<usize>, lints: &mut Vec<IconPackLint>) {
+        let target = match entry {
+            Some(idx) => format!("entry {idx}"),
+            None => "missing icon".to_owned(),
+        };
+        if !icon.is_valid() {
+            lints.push(IconPackLint {
+                kind: IconPackLintKind::InvalidIcon,
+                entry,
+                message: format!("{target} needs a base icon or both light and dark variants"),
+            });
+        }
+
+        let folder = self.folder();
+        let files = [&icon.base, &icon.light, &icon.dark, &icon.mask];
+        for file in files
+            .into_iter()
+            .flatten()
+            .filter(|f| is_local_icon_path(f))
+        {
+            if !folder.join(file).is_file() {
+                lints.push(IconPackLint {
+                    kind: IconPackLintKind::MissingFile,
+                    entry,
+                    message: format!("{target} references '{file}' but it doesn't exist"),
+                });
+            }
+        }
+
+        if icon.is_aproximately_square {
+            let images = [&icon.base, &icon.light, &icon.dark];
+            for file in images
+                .into_iter()
+                .flatten()
+                .filter(|f| is_local_icon_path(f))
+            {
+                if image_dimensions(&folder.join(file))
+                    .is_some_and(|size| !is_approximately_square(size))
+                {
+                    lints.push(IconPackLint {
+                        kind: IconPackLintKind::NotSquare,
+                        entry,
+                        message: format!("{target} is marked as square but '{file}' is not"),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Checks the pack for common mistakes, this doesn't modify the pack.
+    pub fn lint(&self) -> Vec<IconPackLint> {
+        let mut lints = Vec::new();
+        if let Some(missing) = &self.missing {
+            self.lint_icon(missing, None, &mut lints);
+        }
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            match entry {
+                IconPackEntry::Unique(e) => match &e.icon {
+                    Some(icon) if e.redirect.is_none() => {
+                        self.lint_icon(icon, Some(idx), &mut lints)
+                    }
+                    None if e.redirect.is_none() => lints.push(IconPackLint {
+                        kind: IconPackLintKind::InvalidIcon,
+                        entry: Some(idx),
+                        message: format!("entry {idx} has no icon nor redirect"),
+                    }),
+                    _ => {}
+                },
+                IconPackEntry::Shared(e) => self.lint_icon(&e.icon, Some(idx), &mut lints),
+                IconPackEntry::Custom(e) => self.lint_icon(&e.icon, Some(idx), &mut lints),
+            }
+
+            if let Some(first) = self.entries[..idx]
+                .iter()
+                .position(|other| other.matches(entry))
+            {
+                lints.push(IconPackLint {
+                    kind: IconPackLintKind::DuplicateEntry,
+                    entry: Some(idx),
+                    message: format!("entry {idx} duplicates entry {first}"),
+                });
+            }
+        }
+        lints
+    }
+
+    /// Embeds the local icon files as data urls, so the pack can be shared as a single file.
+    /// The embedded icons are moved to `remote_entries` to be stored on install.
+    fn to_embedded(&self) -> Result<IconPack> {
+        let folder = self.folder();
+        let embed = |path: &mut String| -> Result<()> {
+            if is_local_icon_path(path) {
+                let bytes = std::fs::read(folder.join(&*path))
+                    .map_err(|e| format!("can't read icon '{path}': {e}"))?;
+                let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+                *path = format!("data:{};base64,{encoded}", image_mime(path));
+            }
+            Ok(())
+        };
+        let embed_icon = |icon: &mut Icon| -> Result<()> {
+            for path in [
+                &mut icon.base,
+                &mut icon.light,
+                &mut icon.dark,
+                &mut icon.mask,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                embed(path)?;
+            }
+            Ok(())
+        };
+
+        let mut embedded = self.clone();
+        if let Some(missing) = &mut embedded.missing {
+            embed_icon(missing)?;
+        }
+        for mut entry in std::mem::take(&mut embedded.entries) {
+            match &mut entry {
+                IconPackEntry::Unique(e) => match &mut e.icon {
+                    Some(icon) => embed_icon(icon)?,
+                    // redirects doesn't need to be downloaded
+                    None => {
+                        embedded.entries.push(entry);
+                        continue;
+                    }
+                },
+                IconPackEntry::Shared(e) => embed_icon(&mut e.icon)?,
+                IconPackEntry::Custom(e) => embed_icon(&mut e.icon)?,
+            }
+            embedded.remote_entries.push(entry);
+        }
+        embedded.downloaded = false;
+        Ok(embedded)
+    }
+
+    /// Exports the pack as a single `.slu` file with the icons embedded.
+    pub fn export_slu(&self, output: &Path) -> Result<()> {
+        let mut pack = self.to_embedded()?;
+
+        let metadata = &mut pack.metadata;
+        metadata.internal = Default::default();
+        ensure_english_text(&mut metadata.display_name, &pack.id);
+        ensure_english_text(&mut metadata.description, "");
+        if let (None, Some(version)) = (&metadata.app_target_version, app_version()) {
+            metadata.app_target_version = Some(AppTargetVersion::Tuple(
+                version.major as u32,
+                version.minor as u32,
+                version.patch as u32,
+            ));
+        }
+
+        let mut data = serde_json::to_value(&pack)?;
+        if let Some(object) = data.as_object_mut() {
+            // these are stored on the resource
+            object.remove("id");
+            object.remove("metadata");
+        }
+
+        let now = Utc::now();
+        let file = SluResourceFile {
+            version: 2,
+            resource: Resource {
+                id: Uuid::new_v4(),
+                data_id: Uuid::new_v4(),
+                creator_id: Uuid::nil(),
+                friendly_id: pack.id.to_string().into(),
+                kind: ResourceKind::IconPack,
+                metadata: pack.metadata,
+                created_at: now,
+                updated_at: now,
+                status: ResourceStatus::Draft,
+                rejected_reason: None,
+                reviewed_at: None,
+                reviewed_by: None,
+                deleted_at: None,
+                attributes: Default::default(),
+                version: 1,
+                stars: 0,
+                downloads: 0,
+            },
+            data: TsUnknown(data),
+        };
+        file.resource.verify()?;
+        file.store(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::HttpClient;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes
+    }
+
+    struct Offline;
+    impl HttpClient for Offline {
+        fn get(&self, _url: &str, _offset: u64) -> Result<crate::utils::HttpResponse> {
+            Err("offline".into())
+        }
+    }
+
+    fn temp_folder() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("slu-icon-authoring-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join(CUSTOM_FOLDER)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn should_build_pack_from_named_images() -> Result<()> {
+        let dir = temp_folder();
+        std::fs::write(dir.join("explorer.exe.png"), png(32, 32))?;
+        std::fs::write(dir.join("txt.light.png"), png(32, 32))?;
+        std::fs::write(dir.join("txt.dark.png"), png(32, 32))?;
+        // read after the base icon, so it could override the square flag
+        std::fs::write(dir.join("md.png"), png(32, 32))?;
+        std::fs::write(dir.join("md.dark.png"), png(32, 32))?;
+        std::fs::write(dir.join("md.light.png"), png(64, 16))?;
+        std::fs::write(
+            dir.join("App_8wekyb3d8bbwe!App.svg"),
+            "<svg viewBox=\"0 0 48 24\"></svg>",
+        )?;
+        std::fs::write(dir.join("missing.png"), png(16, 16))?;
+        std::fs::write(dir.join("custom/clock.png"), png(16, 16))?;
+        std::fs::write(dir.join("notes.md"), "not an image")?;
+
+        let pack = IconPack::build_from_folder(&dir, "@test/built".into())?;
+        assert_eq!(pack.entries.len(), 5);
+        assert!(pack.entries.iter().any(|e| matches!(e,
+            IconPackEntry::Shared(s) if s.extension == "md" && !s.icon.is_aproximately_square)));
+        assert!(pack.entries.iter().any(|e| matches!(e,
+            IconPackEntry::Shared(s) if s.extension == "txt" && s.icon.is_aproximately_square)));
+        assert!(pack
+            .missing
+            .as_ref()
+            .is_some_and(|m| m.is_aproximately_square));
+        assert!(pack.entries.iter().any(|e| matches!(e,
+            IconPackEntry::Unique(u) if u.umid.as_deref() == Some("App_8wekyb3d8bbwe!App")
+                && !u.icon.as_ref().unwrap().is_aproximately_square)));
+        assert!(pack.entries.iter().any(|e| matches!(e,
+            IconPackEntry::Shared(s) if s.extension == "txt"
+                && s.icon.light.as_deref() == Some("txt.light.png")
+                && s.icon.dark.as_deref() == Some("txt.dark.png"))));
+        assert!(pack.entries.iter().any(|e| matches!(e,
+            IconPackEntry::Custom(c) if c.key == "clock"
+                && c.icon.base.as_deref() == Some("custom/clock.png"))));
+        assert_eq!(pack.lint(), vec![]);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_lint_common_mistakes() -> Result<()> {
+        let dir = temp_folder();
+        std::fs::write(dir.join("wide.png"), png(64, 16))?;
+
+        let icon = |base: &str, square: bool| Icon {
+            base: Some(base.to_owned()),
+            is_aproximately_square: square,
+            ..Default::default()
+        };
+        let mut pack = IconPack {
+            missing: Some(Icon::default()),
+            entries: vec![
+                IconPackEntry::Shared(SharedIconPackEntry {
+                    extension: "txt".to_owned(),
+                    icon: icon("wide.png", true),
+                }),
+                IconPackEntry::Shared(SharedIconPackEntry {
+                    extension: "txt".to_owned(),
+                    icon: icon("not-found.png", false),
+                }),
+            ],
+            ..Default::default()
+        };
+        pack.metadata.internal.path = dir.clone();
+
+        let kinds: Vec<_> = pack.lint().into_iter().map(|l| (l.kind, l.entry)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (IconPackLintKind::InvalidIcon, None),
+                (IconPackLintKind::NotSquare, Some(0)),
+                (IconPackLintKind::MissingFile, Some(1)),
+                (IconPackLintKind::DuplicateEntry, Some(1)),
+            ]
+        );
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_export_and_install_embedded_pack() -> Result<()> {
+        crate::resource::init_app_version()?;
+        let dir = temp_folder();
+        std::fs::write(dir.join("txt.png"), png(32, 32))?;
+        std::fs::write(dir.join("missing.png"), png(16, 16))?;
+        let pack = IconPack::build_from_folder(&dir, "@test/exported".into())?;
+
+        let output = dir.join("exported.slu");
+        pack.export_slu(&output)?;
+
+        let file = SluResourceFile::load(&output)?;
+        assert_eq!(file.resource.kind, ResourceKind::IconPack);
+        let version = app_version().expect("app version is set");
+        assert_eq!(
+            file.resource.metadata.app_target_version,
+            Some(AppTargetVersion::Tuple(
+                version.major as u32,
+                version.minor as u32,
+                version.patch as u32
+            ))
+        );
+        let mut installed: IconPack = file.try_parse_into()?;
+        assert!(installed.entries.is_empty());
+        assert!(installed.remote_entries.iter().all(|e| matches!(e,
+            IconPackEntry::Shared(s) if s.icon.base.as_deref()
+                .is_some_and(|b| b.starts_with("data:image/png;base64,")))));
+
+        // embedded icons are stored without network
+        let install_dir = dir.join("installed");
+        std::fs::create_dir_all(&install_dir)?;
+        installed.metadata.internal.path = install_dir.clone();
+        let report = installed.download_remote_entries(&Offline)?;
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert!(installed.downloaded);
+        assert_eq!(installed.lint(), vec![]);
+        let missing = installed
+            .missing
+            .as_ref()
+            .and_then(|m| m.base.clone())
+            .unwrap();
+        assert_eq!(std::fs::read(install_dir.join(missing))?, png(16, 16));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
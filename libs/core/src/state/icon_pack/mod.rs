@@ -1,3 +1,11 @@
+mod authoring;
+mod remote;
+mod resolver;
+
+pub use authoring::*;
+pub use remote::*;
+pub use resolver::*;
+
 use std::path::PathBuf;
 
 use schemars::JsonSchema;
@@ -0,0 +1,406 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    error::Result,
+    resource::SluResource,
+    state::{Icon, IconPack, IconPackEntry},
+    utils::{sha256_hex, write_file_atomically, HttpClient},
+};
+
+/// Folder inside the icon pack where the remote icons are stored
+pub static ICON_PACK_REMOTE_FOLDER: &str = "remote";
+/// Unfinished downloads, named by the url hash to be resumed later
+static PARTIAL_FOLDER: &str = ".partial";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteIconFailure {
+    pub url: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteIconsReport {
+    /// Number of icons stored on this run
+    pub downloaded: usize,
+    /// Number of icons that continued a previously interrupted download
+    pub resumed: usize,
+    /// If not empty, the pack was not marked as downloaded
+    pub failed: Vec<RemoteIconFailure>,
+}
+
+impl Icon {
+    fn paths_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        [
+            self.base.as_mut(),
+            self.light.as_mut(),
+            self.dark.as_mut(),
+            self.mask.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl IconPackEntry {
+    fn icons_mut(&mut self) -> impl Iterator<Item = &mut Icon> {
+        match self {
+            IconPackEntry::Unique(e) => e.icon.as_mut(),
+            IconPackEntry::Shared(e) => Some(&mut e.icon),
+            IconPackEntry::Custom(e) => Some(&mut e.icon),
+        }
+        .into_iter()
+    }
+}
+
+fn is_remote_icon_path(path: &str) -> bool {
+    path.starts_with("data:") || path.starts_with("http://") || path.starts_with("https://")
+}
+
+fn icon_extension(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let last = url.path_segments()?.next_back()?.to_owned();
+            let (_, ext) = last.rsplit_once('.')?;
+            let ext = ext.to_ascii_lowercase();
+            match !ext.is_empty()
+                && ext.len() <= 5
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                true => Some(ext),
+                false => None,
+            }
+        })
+        .unwrap_or_else(|| "png".to_owned())
+}
+
+/// Decodes embedded icons as `data:image/png;base64,...`, returns `None` for other urls.
+fn decode_data_url(url: &str) -> Result<Option<(String, Vec<u8>)>> {
+    let Some(rest) = url.strip_prefix("data:") else {
+        return Ok(None);
+    };
+    let (header, payload) = rest.split_once(',').ok_or("invalid data url")?;
+    let Some(mime) = header.strip_suffix(";base64") else {
+        return Ok(Some((header.to_owned(), payload.as_bytes().to_vec())));
+    };
+    let content = base64::engine::general_purpose::STANDARD.decode(payload)?;
+    Ok(Some((mime.to_owned(), content)))
+}
+
+fn mime_extension(mime: &str) -> &'static str {
+    match mime {
+        "image/svg+xml" => "svg",
+        "image/jpeg" => "jpg",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        _ => "png",
+    }
+}
+
+/// Downloads the url into the remote folder, continuing any partial download.\
+/// Returns the path relative to the pack folder and if the download was resumed.
+fn fetch_remote_icon(client: &dyn HttpClient, url: &str, remote: &Path) -> Result<(String, bool)> {
+    if let Some((mime, content)) = decode_data_url(url)? {
+        let name = format!("{}.{}", sha256_hex(&content), mime_extension(&mime));
+        let target = remote.join(&name);
+        if !target.exists() {
+            write_file_atomically(&target, &content)?;
+        }
+        return Ok((format!("{ICON_PACK_REMOTE_FOLDER}/{name}"), false));
+    }
+
+    let partial_dir = remote.join(PARTIAL_FOLDER);
+    std::fs::create_dir_all(&partial_dir)?;
+    let partial = partial_dir.join(format!("{}.part", sha256_hex(url.as_bytes())));
+
+    let mut resumed = false;
+    for _attempt in 0..2 {
+        let offset = partial.metadata().map(|m| m.len()).unwrap_or(0);
+        let mut response = client.get(url, offset)?;
+
+        let mut file = match response.status {
+            206 if offset > 0 => {
+                resumed = true;
+                OpenOptions::new().append(true).open(&partial)?
+            }
+            // the server ignored the range, so we start again
+            200..=299 => File::create(&partial)?,
+            // partial file is corrupted or bigger than the remote one
+            416 => {
+                std::fs::remove_file(&partial)?;
+                continue;
+            }
+            status => return Err(format!("unexpected http status {status}").into()),
+        };
+        std::io::copy(&mut response.body, &mut file)?;
+        file.flush()?;
+        drop(file);
+
+        let content = std::fs::read(&partial)?;
+        let name = format!("{}.{}", sha256_hex(&content), icon_extension(url));
+        let target = remote.join(&name);
+        if target.exists() {
+            std::fs::remove_file(&partial)?;
+        } else {
+            std::fs::rename(&partial, &target)?;
+        }
+        return Ok((format!("{ICON_PACK_REMOTE_FOLDER}/{name}"), resumed));
+    }
+    Err("the server rejected the download range".into())
+}
+
+impl IconPack {
+    /// Downloads the icons of `remote_entries` into the pack folder and adds the entries
+    /// pointing to the local files. The pack is only modified and marked as downloaded
+    /// if every icon was stored, failed downloads are kept to be resumed on the next call.\
+    /// On success the pack metadata file is saved atomically.
+    pub fn download_remote_entries(
+        &mut self,
+        client: &dyn HttpClient,
+    ) -> Result<RemoteIconsReport> {
+        let mut report = RemoteIconsReport::default();
+        if self.downloaded {
+            return Ok(report);
+        }
+
+        let folder = &self.metadata.internal.path;
+        if !folder.is_dir() {
+            return Err("remote entries can only be stored on folder based icon packs".into());
+        }
+        let remote = folder.join(ICON_PACK_REMOTE_FOLDER);
+
+        let mut stored: HashMap<String, String> = HashMap::new();
+        let mut entries = self.remote_entries.clone();
+        let mut missing = self.missing.clone();
+        let icons = entries
+            .iter_mut()
+            .flat_map(|e| e.icons_mut())
+            .chain(missing.as_mut());
+        for icon in icons {
+            for path in icon.paths_mut() {
+                if !is_remote_icon_path(path) {
+                    continue;
+                }
+                if let Some(local) = stored.get(path.as_str()) {
+                    *path = local.clone();
+                    continue;
+                }
+                match fetch_remote_icon(client, path, &remote) {
+                    Ok((local, resumed)) => {
+                        report.downloaded += 1;
+                        report.resumed += resumed as usize;
+                        stored.insert(path.clone(), local.clone());
+                        *path = local;
+                    }
+                    Err(err) => report.failed.push(RemoteIconFailure {
+                        url: path.clone(),
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+        }
+
+        if report.failed.is_empty() {
+            // keep self untouched if the updated pack can't be written
+            let mut updated = self.clone();
+            for entry in entries {
+                updated.add_entry(entry);
+            }
+            updated.missing = missing;
+            updated.downloaded = true;
+            updated.save()?;
+            *self = updated;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        io::{Cursor, Read},
+        path::PathBuf,
+    };
+
+    use super::*;
+    use crate::{state::SharedIconPackEntry, utils::HttpResponse};
+
+    /// Local http stand-in supporting ranges, it can cut the first response of an url.
+    #[derive(Default)]
+    struct StandInServer {
+        files: HashMap<String, Vec<u8>>,
+        cut_after: RefCell<HashMap<String, usize>>,
+        requests: RefCell<Vec<(String, u64)>>,
+    }
+
+    struct CutReader {
+        inner: Cursor<Vec<u8>>,
+        remaining: usize,
+    }
+
+    impl Read for CutReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionReset,
+                    "cut",
+                ));
+            }
+            let max = buf.len().min(self.remaining);
+            let read = self.inner.read(&mut buf[..max])?;
+            self.remaining -= read;
+            Ok(read)
+        }
+    }
+
+    impl HttpClient for StandInServer {
+        fn get(&self, url: &str, offset: u64) -> Result<HttpResponse> {
+            self.requests.borrow_mut().push((url.to_owned(), offset));
+            let Some(content) = self.files.get(url) else {
+                return Ok(HttpResponse {
+                    status: 404,
+                    body: Box::new(std::io::empty()),
+                });
+            };
+            let body = content[offset as usize..].to_vec();
+            let status = if offset > 0 { 206 } else { 200 };
+            match self.cut_after.borrow_mut().remove(url) {
+                Some(remaining) => Ok(HttpResponse {
+                    status,
+                    body: Box::new(CutReader {
+                        inner: Cursor::new(body),
+                        remaining,
+                    }),
+                }),
+                None => Ok(HttpResponse {
+                    status,
+                    body: Box::new(Cursor::new(body)),
+                }),
+            }
+        }
+    }
+
+    fn shared(extension: &str, url: &str) -> IconPackEntry {
+        IconPackEntry::Shared(SharedIconPackEntry {
+            extension: extension.to_owned(),
+            icon: Icon {
+                base: Some(url.to_owned()),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn temp_pack(remote_entries: Vec<IconPackEntry>) -> IconPack {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("slu-icon-pack-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut pack = IconPack {
+            id: "@test/remote".into(),
+            remote_entries,
+            ..Default::default()
+        };
+        pack.metadata.internal.path = dir;
+        pack
+    }
+
+    #[test]
+    fn should_resume_and_store_by_content_hash() -> Result<()> {
+        let txt = b"txt icon content".to_vec();
+        let mut server = StandInServer::default();
+        server
+            .files
+            .insert("https://icons.test/txt.svg".to_owned(), txt.clone());
+        server
+            .files
+            .insert("https://icons.test/same.svg".to_owned(), txt.clone());
+        server
+            .cut_after
+            .borrow_mut()
+            .insert("https://icons.test/txt.svg".to_owned(), 5);
+
+        let mut pack = temp_pack(vec![
+            shared("txt", "https://icons.test/txt.svg"),
+            shared("md", "https://icons.test/same.svg"),
+        ]);
+
+        let report = pack.download_remote_entries(&server)?;
+        assert_eq!(report.failed.len(), 1);
+        assert!(!pack.downloaded);
+        assert!(pack.entries.is_empty());
+
+        let report = pack.download_remote_entries(&server)?;
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.resumed, 1);
+        assert!(pack.downloaded);
+        assert!(server
+            .requests
+            .borrow()
+            .contains(&("https://icons.test/txt.svg".to_owned(), 5)));
+
+        let local = format!("{ICON_PACK_REMOTE_FOLDER}/{}.svg", sha256_hex(&txt));
+        for entry in &pack.entries {
+            let IconPackEntry::Shared(entry) = entry else {
+                panic!("unexpected entry");
+            };
+            assert_eq!(entry.icon.base.as_deref(), Some(local.as_str()));
+        }
+        let folder = pack.metadata.internal.path.clone();
+        assert_eq!(std::fs::read(folder.join(&local))?, txt);
+
+        // the downloaded state is persisted
+        let reloaded = IconPack::load(&folder)?;
+        assert!(reloaded.downloaded);
+        assert_eq!(reloaded.entries.len(), pack.entries.len());
+
+        std::fs::remove_dir_all(folder)?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_flip_downloaded_on_http_errors() -> Result<()> {
+        let server = StandInServer::default();
+        let mut pack = temp_pack(vec![shared("txt", "https://icons.test/404.png")]);
+
+        let report = pack.download_remote_entries(&server)?;
+        assert_eq!(report.failed[0].url, "https://icons.test/404.png");
+        assert!(!pack.downloaded);
+
+        std::fs::remove_dir_all(&pack.metadata.internal.path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_pack_untouched_if_it_cannot_be_saved() -> Result<()> {
+        let mut server = StandInServer::default();
+        server
+            .files
+            .insert("https://icons.test/txt.svg".to_owned(), b"txt".to_vec());
+        let mut pack = temp_pack(vec![shared("txt", "https://icons.test/txt.svg")]);
+        let folder = pack.metadata.internal.path.clone();
+        // a folder in place of the metadata file makes the save fail
+        std::fs::create_dir_all(folder.join("metadata.yml"))?;
+
+        assert!(pack.download_remote_entries(&server).is_err());
+        assert!(!pack.downloaded);
+        assert!(pack.entries.is_empty());
+
+        std::fs::remove_dir_all(folder)?;
+        Ok(())
+    }
+}
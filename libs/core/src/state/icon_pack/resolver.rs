@@ -0,0 +1,449 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    resource::IconPackId,
+    state::{Icon, IconPack, IconPackEntry, UniqueIconPackEntry},
+};
+
+/// Max number of redirects to follow before giving up, this also breaks long chains.
+const MAX_REDIRECTS: usize = 16;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum IconColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// How the icon was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum IconMatchKind {
+    Umid,
+    Path,
+    /// Only used for executable files
+    Filename,
+    Extension,
+    Custom,
+    /// Nothing matched so the `missing` icon of a pack was used
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedIcon {
+    /// Pack that supplied the icon
+    pub pack: IconPackId,
+    /// Absolute path to the icon file for the requested color scheme
+    pub path: PathBuf,
+    /// Absolute path to the mask of the icon, if any
+    pub mask: Option<PathBuf>,
+    pub matched_by: IconMatchKind,
+    pub is_aproximately_square: bool,
+}
+
+impl Icon {
+    /// Relative path of the icon to use on the color scheme, fallbacks to `base`.
+    pub fn path_for(&self, scheme: IconColorScheme) -> Option<&str> {
+        let specific = match scheme {
+            IconColorScheme::Light => self.light.as_deref(),
+            IconColorScheme::Dark => self.dark.as_deref(),
+        };
+        specific.or(self.base.as_deref())
+    }
+}
+
+impl IconPack {
+    /// Folder used as base for the relative icon paths
+    pub fn folder(&self) -> &Path {
+        let path = &self.metadata.internal.path;
+        match path.is_file() {
+            true => path.parent().unwrap_or(path),
+            false => path,
+        }
+    }
+}
+
+/// Windows paths are case insensitive and can use both kind of separators.
+fn normalize_path(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase().replace('/', "\\")
+}
+
+fn file_name_of(normalized: &str) -> &str {
+    normalized.rsplit('\\').next().unwrap_or(normalized)
+}
+
+/// Resolves icons over the active icon packs, the last activated pack has the highest priority.
+#[derive(Debug, Clone)]
+pub struct IconResolver<'a> {
+    /// Packs sorted by priority, highest first
+    packs: Vec<&'a IconPack>,
+}
+
+impl<'a> IconResolver<'a> {
+    /// Quarantined packs are ignored.
+    pub fn new(available: &'a [IconPack], active: &[IconPackId]) -> Self {
+        let packs = active
+            .iter()
+            .rev()
+            .filter_map(|id| available.iter().find(|p| &p.id == id))
+            .filter(|p| !p.metadata.is_quarantined())
+            .collect();
+        Self { packs }
+    }
+
+    fn to_resolved(
+        pack: &IconPack,
+        icon: &Icon,
+        scheme: IconColorScheme,
+        matched_by: IconMatchKind,
+    ) -> Option<ResolvedIcon> {
+        let folder = pack.folder();
+        Some(ResolvedIcon {
+            pack: pack.id.clone(),
+            path: folder.join(icon.path_for(scheme)?),
+            mask: icon.mask.as_ref().map(|mask| folder.join(mask)),
+            matched_by,
+            is_aproximately_square: icon.is_aproximately_square,
+        })
+    }
+
+    /// Returns the unique entry by UMID, full path or filename in that order.
+    fn find_unique(
+        pack: &'a IconPack,
+        umid: Option<&str>,
+        path: Option<&str>,
+    ) -> Option<(&'a UniqueIconPackEntry, IconMatchKind)> {
+        let uniques = || {
+            pack.entries.iter().filter_map(|e| match e {
+                IconPackEntry::Unique(unique) => Some(unique),
+                _ => None,
+            })
+        };
+
+        if let Some(umid) = umid {
+            if let Some(entry) = uniques().find(|e| e.umid.as_deref() == Some(umid)) {
+                return Some((entry, IconMatchKind::Umid));
+            }
+        }
+
+        let path = path?;
+        if let Some(entry) =
+            uniques().find(|e| e.path.as_deref().map(normalize_path).as_deref() == Some(path))
+        {
+            return Some((entry, IconMatchKind::Path));
+        }
+
+        // only search by filename in case of executable files
+        if !path.ends_with(".exe") {
+            return None;
+        }
+        let filename = file_name_of(path);
+        uniques()
+            .find(|e| {
+                e.path
+                    .as_deref()
+                    .map(normalize_path)
+                    .is_some_and(|p| file_name_of(&p) == filename)
+            })
+            .map(|entry| (entry, IconMatchKind::Filename))
+    }
+
+    fn find_app_icon(
+        &self,
+        umid: Option<&str>,
+        path: Option<&Path>,
+        scheme: IconColorScheme,
+        seen: &mut HashSet<String>,
+    ) -> Option<ResolvedIcon> {
+        let path = path.map(normalize_path);
+        for pack in &self.packs {
+            let Some((entry, matched_by)) = Self::find_unique(pack, umid, path.as_deref()) else {
+                continue;
+            };
+
+            if let Some(redirect) = &entry.redirect {
+                // break circular and too long references
+                if seen.len() >= MAX_REDIRECTS || !seen.insert(normalize_path(redirect)) {
+                    return None;
+                }
+                return self.find_app_icon(None, Some(redirect), scheme, seen);
+            }
+
+            if let Some(icon) = &entry.icon {
+                if let Some(resolved) = Self::to_resolved(pack, icon, scheme, matched_by) {
+                    return Some(resolved);
+                }
+            }
+        }
+
+        let extension = path
+            .as_deref()
+            .and_then(|p| file_name_of(p).rsplit_once('.'))
+            .map(|(_, ext)| ext)?;
+        self.packs.iter().find_map(|pack| {
+            pack.entries.iter().find_map(|e| match e {
+                IconPackEntry::Shared(shared)
+                    if shared.extension.eq_ignore_ascii_case(extension) =>
+                {
+                    Self::to_resolved(pack, &shared.icon, scheme, IconMatchKind::Extension)
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// Searches the icon of an app or file by UMID, then full path, then filename
+    /// and then extension, without falling back to the missing icon.
+    pub fn find(
+        &self,
+        umid: Option<&str>,
+        path: Option<&Path>,
+        scheme: IconColorScheme,
+    ) -> Option<ResolvedIcon> {
+        if umid.is_none() && path.is_none() {
+            return None;
+        }
+        self.find_app_icon(umid, path, scheme, &mut HashSet::new())
+    }
+
+    /// Same as [`IconResolver::find`] but fallbacks to the missing icon.
+    pub fn resolve(
+        &self,
+        umid: Option<&str>,
+        path: Option<&Path>,
+        scheme: IconColorScheme,
+    ) -> Option<ResolvedIcon> {
+        self.find(umid, path, scheme)
+            .or_else(|| self.resolve_missing(scheme))
+    }
+
+    /// Custom icon from the highest priority pack that has it.
+    pub fn resolve_custom(&self, key: &str, scheme: IconColorScheme) -> Option<ResolvedIcon> {
+        self.packs.iter().find_map(|pack| {
+            pack.entries.iter().find_map(|e| match e {
+                IconPackEntry::Custom(custom) if custom.key == key => {
+                    Self::to_resolved(pack, &custom.icon, scheme, IconMatchKind::Custom)
+                }
+                _ => None,
+            })
+        })
+    }
+
+    /// Missing icon from the highest priority pack that has it.
+    pub fn resolve_missing(&self, scheme: IconColorScheme) -> Option<ResolvedIcon> {
+        self.packs.iter().find_map(|pack| {
+            let icon = pack.missing.as_ref()?;
+            Self::to_resolved(pack, icon, scheme, IconMatchKind::Missing)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        resource::ResourceCompatibility,
+        state::{CustomIconPackEntry, SharedIconPackEntry},
+    };
+
+    fn base(path: &str) -> Icon {
+        Icon {
+            base: Some(path.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn unique(
+        umid: Option<&str>,
+        path: Option<&str>,
+        redirect: Option<&str>,
+        icon: Option<Icon>,
+    ) -> IconPackEntry {
+        IconPackEntry::Unique(UniqueIconPackEntry {
+            umid: umid.map(str::to_owned),
+            path: path.map(PathBuf::from),
+            redirect: redirect.map(PathBuf::from),
+            icon,
+        })
+    }
+
+    fn pack(id: &str, missing: Option<Icon>, entries: Vec<IconPackEntry>) -> IconPack {
+        let mut pack = IconPack {
+            id: id.into(),
+            missing,
+            entries,
+            ..Default::default()
+        };
+        pack.metadata.internal.path = PathBuf::from(format!("C:\\packs\\{id}"));
+        pack
+    }
+
+    fn packs() -> Vec<IconPack> {
+        vec![
+            pack(
+                "a",
+                Some(base("missing_a.png")),
+                vec![
+                    unique(
+                        Some("MSEdge"),
+                        Some("C:\\Edge\\msedge.exe"),
+                        None,
+                        Some(base("umid.png")),
+                    ),
+                    unique(
+                        None,
+                        Some("C:\\Windows\\explorer.exe"),
+                        None,
+                        Some(base("path_a.png")),
+                    ),
+                    unique(
+                        None,
+                        Some("C:\\Apps\\loop1.lnk"),
+                        Some("C:\\Apps\\loop2.lnk"),
+                        None,
+                    ),
+                    unique(
+                        None,
+                        Some("C:\\Apps\\loop2.lnk"),
+                        Some("C:\\Apps\\loop1.lnk"),
+                        None,
+                    ),
+                    unique(
+                        None,
+                        Some("C:\\Apps\\app.lnk"),
+                        Some("C:\\Apps\\app.exe"),
+                        None,
+                    ),
+                    IconPackEntry::Shared(SharedIconPackEntry {
+                        extension: "txt".to_owned(),
+                        icon: Icon {
+                            light: Some("txt_light.png".to_owned()),
+                            dark: Some("txt_dark.png".to_owned()),
+                            ..Default::default()
+                        },
+                    }),
+                ],
+            ),
+            pack(
+                "b",
+                Some(base("missing_b.png")),
+                vec![
+                    unique(
+                        None,
+                        Some("C:\\Windows\\explorer.exe"),
+                        None,
+                        Some(base("path_b.png")),
+                    ),
+                    unique(None, Some("app.exe"), None, Some(base("filename_b.png"))),
+                    IconPackEntry::Custom(CustomIconPackEntry {
+                        key: "custom".to_owned(),
+                        icon: base("custom_b.png"),
+                    }),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn should_follow_priority_and_match_order() {
+        let packs = packs();
+        let resolver = IconResolver::new(&packs, &["a".into(), "b".into()]);
+        let scheme = IconColorScheme::Dark;
+
+        let by_umid = resolver
+            .find(
+                Some("MSEdge"),
+                Some(Path::new("C:\\Edge\\msedge.exe")),
+                scheme,
+            )
+            .unwrap();
+        assert_eq!(by_umid.matched_by, IconMatchKind::Umid);
+        assert_eq!(by_umid.path, PathBuf::from("C:\\packs\\a").join("umid.png"));
+
+        // pack b has more priority
+        let by_path = resolver
+            .find(None, Some(Path::new("c:/windows/EXPLORER.exe")), scheme)
+            .unwrap();
+        assert_eq!(by_path.pack, IconPackId::from("b"));
+        assert_eq!(by_path.matched_by, IconMatchKind::Path);
+
+        let by_filename = resolver
+            .find(None, Some(Path::new("D:\\Other\\App.exe")), scheme)
+            .unwrap();
+        assert_eq!(by_filename.matched_by, IconMatchKind::Filename);
+
+        let by_extension = resolver
+            .find(None, Some(Path::new("C:\\notes.TXT")), scheme)
+            .unwrap();
+        assert_eq!(by_extension.matched_by, IconMatchKind::Extension);
+        assert_eq!(
+            by_extension.path,
+            PathBuf::from("C:\\packs\\a").join("txt_dark.png")
+        );
+        let light = resolver
+            .find(
+                None,
+                Some(Path::new("C:\\notes.txt")),
+                IconColorScheme::Light,
+            )
+            .unwrap();
+        assert_eq!(
+            light.path,
+            PathBuf::from("C:\\packs\\a").join("txt_light.png")
+        );
+
+        assert_eq!(
+            resolver.resolve_custom("custom", scheme).unwrap().pack,
+            IconPackId::from("b")
+        );
+    }
+
+    #[test]
+    fn should_follow_redirects_and_break_loops() {
+        let packs = packs();
+        let resolver = IconResolver::new(&packs, &["a".into(), "b".into()]);
+        let scheme = IconColorScheme::Light;
+
+        let redirected = resolver
+            .find(None, Some(Path::new("C:\\Apps\\app.lnk")), scheme)
+            .unwrap();
+        assert_eq!(
+            redirected.path,
+            PathBuf::from("C:\\packs\\b").join("filename_b.png")
+        );
+
+        assert_eq!(
+            resolver.find(None, Some(Path::new("C:\\Apps\\loop1.lnk")), scheme),
+            None
+        );
+        let missing = resolver
+            .resolve(None, Some(Path::new("C:\\Apps\\loop1.lnk")), scheme)
+            .unwrap();
+        assert_eq!(missing.matched_by, IconMatchKind::Missing);
+        assert_eq!(missing.pack, IconPackId::from("b"));
+    }
+
+    #[test]
+    fn should_ignore_quarantined_packs() {
+        let mut packs = packs();
+        packs[1].metadata.internal.compatibility = ResourceCompatibility::Incompatible;
+        let resolver = IconResolver::new(&packs, &["a".into(), "b".into()]);
+        let by_path = resolver
+            .find(
+                None,
+                Some(Path::new("C:\\Windows\\explorer.exe")),
+                IconColorScheme::Dark,
+            )
+            .unwrap();
+        assert_eq!(by_path.pack, IconPackId::from("a"));
+    }
+}
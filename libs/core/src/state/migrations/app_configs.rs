@@ -0,0 +1,99 @@
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+
+use super::{camel_case_keys, camel_case_keys_deep, rename_value, Migration, MigrationChain};
+
+/// App configs are stored as a list, so these steps are applied on every load.
+pub static APP_CONFIGS_MIGRATIONS: MigrationChain = MigrationChain {
+    name: "app_configs",
+    steps: &[
+        Migration {
+            name: "snake_case_keys",
+            run: snake_case_keys,
+        },
+        Migration {
+            name: "enum_names",
+            run: enum_names,
+        },
+    ],
+};
+
+fn configs(list: &mut Value) -> Result<impl Iterator<Item = &mut Map<String, Value>>> {
+    let Some(list) = list.as_array_mut() else {
+        return Err("app configs should be a list".into());
+    };
+    Ok(list.iter_mut().filter_map(Value::as_object_mut))
+}
+
+fn snake_case_keys(list: &mut Value) -> Result<()> {
+    for config in configs(list)? {
+        if let Some(identifier) = config.get_mut("identifier") {
+            camel_case_keys_deep(identifier);
+        }
+        camel_case_keys(config);
+    }
+    Ok(())
+}
+
+fn identifier_enum_names(identifier: &mut Map<String, Value>) {
+    rename_value(
+        identifier,
+        "kind",
+        &[
+            ("exe", "Exe"),
+            ("class", "Class"),
+            ("title", "Title"),
+            ("path", "Path"),
+        ],
+    );
+    rename_value(
+        identifier,
+        "matchingStrategy",
+        &[
+            ("equals", "Equals"),
+            ("legacy", "Equals"),
+            ("Legacy", "Equals"),
+            ("startsWith", "StartsWith"),
+            ("endsWith", "EndsWith"),
+            ("contains", "Contains"),
+            ("regex", "Regex"),
+        ],
+    );
+    for key in ["and", "or"] {
+        if let Some(Value::Array(nested)) = identifier.get_mut(key) {
+            nested
+                .iter_mut()
+                .filter_map(Value::as_object_mut)
+                .for_each(identifier_enum_names);
+        }
+    }
+}
+
+const LEGACY_FLAGS: [(&str, &str); 9] = [
+    ("no-interactive", "NoInteractive"),
+    ("float", "WmFloat"),
+    ("wm-float", "WmFloat"),
+    ("force", "WmForce"),
+    ("wm-force", "WmForce"),
+    ("unmanage", "WmUnmanage"),
+    ("wm-unmanage", "WmUnmanage"),
+    ("pinned", "VdPinned"),
+    ("vd-pinned", "VdPinned"),
+];
+
+fn enum_names(list: &mut Value) -> Result<()> {
+    for config in configs(list)? {
+        if let Some(identifier) = config.get_mut("identifier").and_then(Value::as_object_mut) {
+            identifier_enum_names(identifier);
+        }
+        if let Some(Value::Array(options)) = config.get_mut("options") {
+            for option in options {
+                if let Some((_, new)) = LEGACY_FLAGS.iter().find(|(old, _)| option == old) {
+                    *option = (*new).into();
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,233 @@
+mod app_configs;
+mod settings;
+#[cfg(test)]
+mod tests;
+mod toolbar_items;
+mod weg_items;
+
+pub use app_configs::*;
+pub use settings::*;
+pub use toolbar_items::*;
+pub use weg_items::*;
+
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+use crate::{error::Result, utils::write_file_atomically};
+
+/// Named step of a migration chain, should be pure and idempotent.
+pub struct Migration {
+    pub name: &'static str,
+    pub run: fn(&mut Value) -> Result<()>,
+}
+
+/// Ordered list of migrations of a document, the schema version of a document
+/// is the amount of steps already applied to it.
+pub struct MigrationChain {
+    pub name: &'static str,
+    pub steps: &'static [Migration],
+}
+
+/// Schema version stored on the migrated documents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(default)]
+pub struct MigrationRecord {
+    #[serde(rename = "$schemaVersion")]
+    pub schema_version: u32,
+    /// Names of the migrations applied to the document
+    #[serde(rename = "$migrations")]
+    pub applied: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    pub applied: Vec<&'static str>,
+}
+
+impl MigrationReport {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+impl MigrationChain {
+    pub fn latest_version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+
+    /// Record of a document created on the latest version.
+    pub fn record(&self) -> MigrationRecord {
+        MigrationRecord {
+            schema_version: self.latest_version(),
+            applied: Vec::new(),
+        }
+    }
+
+    /// Applies the pending steps, on error the document is not modified.\
+    /// Objects store its version as `$schemaVersion` and the applied steps on `$migrations`,
+    /// other documents (as lists) can't store it, so all the steps are applied on each run.
+    /// Documents from a newer version are left untouched.
+    pub fn migrate(&self, document: &mut Value) -> Result<MigrationReport> {
+        let from = match document.get("$schemaVersion") {
+            Some(version) => version
+                .as_u64()
+                .ok_or(format!("{}: invalid schema version {version}", self.name))?
+                as u32,
+            None => 0,
+        };
+
+        let mut report = MigrationReport {
+            from,
+            to: from,
+            applied: Vec::new(),
+        };
+        if from >= self.latest_version() {
+            return Ok(report);
+        }
+
+        let mut next = document.clone();
+        for step in &self.steps[from as usize..] {
+            (step.run)(&mut next).map_err(|e| format!("{}::{}: {e}", self.name, step.name))?;
+            report.applied.push(step.name);
+        }
+        report.to = self.latest_version();
+
+        if let Value::Object(object) = &mut next {
+            object.insert("$schemaVersion".to_owned(), report.to.into());
+            let applied = object
+                .entry("$migrations")
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(applied) = applied {
+                applied.extend(report.applied.iter().map(|name| Value::from(*name)));
+            }
+        }
+        *document = next;
+        Ok(report)
+    }
+
+    /// Migrates a json or yaml file in place, writing a backup of the original first.\
+    /// The file is only written if the migration changed the document.
+    pub fn migrate_file(&self, path: &Path) -> Result<MigrationReport> {
+        Ok(self.migrate_file_document(path)?.1)
+    }
+
+    /// Loads a json or yaml file migrated to the latest version, see [`Self::migrate_file`].
+    pub fn load_file<T: DeserializeOwned>(&self, path: &Path) -> Result<T> {
+        let (document, _) = self.migrate_file_document(path)?;
+        serde_json::from_value(document).map_err(|e| format!("{}: {e}", self.name).into())
+    }
+
+    fn migrate_file_document(&self, path: &Path) -> Result<(Value, MigrationReport)> {
+        let contents = std::fs::read(path)?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yml" || ext == "yaml");
+
+        let mut document: Value = match is_yaml {
+            true => serde_yaml::from_slice(&contents)?,
+            false => serde_json::from_slice(&contents)?,
+        };
+        let original = document.clone();
+        let report = self.migrate(&mut document)?;
+        // lists re-run all the steps on each load, so an empty report is not enough
+        if report.is_empty() || document == original {
+            return Ok((document, report));
+        }
+
+        write_backup(path, &contents, report.from)?;
+        let migrated = match is_yaml {
+            true => serde_yaml::to_string(&document)?.into_bytes(),
+            false => serde_json::to_vec_pretty(&document)?,
+        };
+        write_file_atomically(path, &migrated)?;
+        Ok((document, report))
+    }
+}
+
+/// Path of the backup of a file on the given schema version.
+pub fn migration_backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{version}.bak"));
+    path.with_file_name(name)
+}
+
+/// Writes the original contents of a file before upgrading it.
+/// An existing backup is kept, as it will be the oldest one.
+pub fn write_backup(path: &Path, contents: &[u8], version: u32) -> Result<PathBuf> {
+    let backup = migration_backup_path(path, version);
+    if !backup.exists() {
+        write_file_atomically(&backup, contents)?;
+    }
+    Ok(backup)
+}
+
+// ======================== Shared Helpers ===============================
+
+fn snake_to_camel_case(key: &str) -> String {
+    let mut result = String::with_capacity(key.len());
+    let mut upper = false;
+    for c in key.chars() {
+        match c {
+            '_' if !result.is_empty() => upper = true,
+            c if upper => {
+                result.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Renames the snake_case keys of the object to camelCase, existing camelCase keys win.
+fn camel_case_keys(object: &mut Map<String, Value>) {
+    let keys: Vec<String> = object.keys().filter(|k| k.contains('_')).cloned().collect();
+    for key in keys {
+        let renamed = snake_to_camel_case(&key);
+        if renamed == key {
+            continue;
+        }
+        if let Some(value) = object.remove(&key) {
+            object.entry(renamed).or_insert(value);
+        }
+    }
+}
+
+/// Same as [`camel_case_keys`] but also on nested objects and lists of objects.
+fn camel_case_keys_deep(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            camel_case_keys(object);
+            object.values_mut().for_each(camel_case_keys_deep);
+        }
+        Value::Array(list) => list.iter_mut().for_each(camel_case_keys_deep),
+        _ => {}
+    }
+}
+
+/// Replaces the string on `key` if it is one of the legacy names.
+fn rename_value(object: &mut Map<String, Value>, key: &str, names: &[(&str, &str)]) {
+    if let Some(Value::String(value)) = object.get_mut(key) {
+        if let Some((_, new)) = names.iter().find(|(old, _)| old == value) {
+            *value = (*new).to_owned();
+        }
+    }
+}
+
+/// Items of the zoned lists (`left`, `center` and `right`) of the document.
+fn zoned_items(document: &mut Value) -> impl Iterator<Item = &mut Map<String, Value>> {
+    document
+        .as_object_mut()
+        .into_iter()
+        .flat_map(|object| object.iter_mut())
+        .filter(|(key, _)| matches!(key.as_str(), "left" | "center" | "right"))
+        .filter_map(|(_, items)| items.as_array_mut())
+        .flatten()
+        .filter_map(|item| item.as_object_mut())
+}
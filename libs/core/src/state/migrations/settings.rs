@@ -0,0 +1,94 @@
+use serde_json::Value;
+
+use crate::error::Result;
+
+use super::{camel_case_keys, camel_case_keys_deep, rename_value, Migration, MigrationChain};
+
+/// Sections stored on the root of the settings before v2.1.0, and its widget id.
+const LEGACY_WIDGET_SECTIONS: [(&str, &str); 5] = [
+    ("fancyToolbar", "@seelen/fancy-toolbar"),
+    ("seelenweg", "@seelen/weg"),
+    ("windowManager", "@seelen/window-manager"),
+    ("wall", "@seelen/wallpaper-manager"),
+    ("launcher", "@seelen/launcher"),
+];
+
+pub static SETTINGS_MIGRATIONS: MigrationChain = MigrationChain {
+    name: "settings",
+    steps: &[
+        Migration {
+            name: "snake_case_keys",
+            run: snake_case_keys,
+        },
+        Migration {
+            name: "widget_sections_by_id",
+            run: widget_sections_by_id,
+        },
+        Migration {
+            name: "weg_mode_names",
+            run: weg_mode_names,
+        },
+        Migration {
+            name: "selected_themes",
+            run: selected_themes,
+        },
+    ],
+};
+
+/// Before v1.9.8 the keys were stored as snake_case.
+fn snake_case_keys(settings: &mut Value) -> Result<()> {
+    let Some(root) = settings.as_object_mut() else {
+        return Err("settings should be an object".into());
+    };
+    camel_case_keys(root);
+    for (section, _) in LEGACY_WIDGET_SECTIONS {
+        if let Some(section) = root.get_mut(section) {
+            camel_case_keys_deep(section);
+        }
+    }
+    Ok(())
+}
+
+/// Before v2.1.0 the settings of the bundled widgets were stored on the root.\
+/// If the widget already has a section on `byWidget` it is kept, as it is the newer one.
+fn widget_sections_by_id(settings: &mut Value) -> Result<()> {
+    let Some(root) = settings.as_object_mut() else {
+        return Err("settings should be an object".into());
+    };
+    for (section, widget_id) in LEGACY_WIDGET_SECTIONS {
+        let Some(value) = root.remove(section) else {
+            continue;
+        };
+        let by_widget = root
+            .entry("byWidget")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Some(by_widget) = by_widget.as_object_mut() {
+            by_widget.entry(widget_id).or_insert(value);
+        }
+    }
+    Ok(())
+}
+
+fn weg_mode_names(settings: &mut Value) -> Result<()> {
+    if let Some(weg) = settings
+        .pointer_mut("/byWidget/@seelen~1weg")
+        .and_then(Value::as_object_mut)
+    {
+        rename_value(
+            weg,
+            "mode",
+            &[("Full-Width", "FullWidth"), ("Min-Content", "MinContent")],
+        );
+    }
+    Ok(())
+}
+
+/// Before v2.3.8 the themes were selected by filename.
+fn selected_themes(settings: &mut Value) -> Result<()> {
+    if let Some(root) = settings.as_object_mut() {
+        if let Some(themes) = root.remove("selectedThemes") {
+            root.entry("oldActiveThemes").or_insert(themes);
+        }
+    }
+    Ok(())
+}
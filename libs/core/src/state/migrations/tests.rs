@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{
+    error::Result,
+    state::{AppConfig, AppsConfigurationList, Placeholder, Settings, WegItems},
+};
+
+use super::*;
+
+fn read_json(path: &Path) -> Result<Value> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Runs the chain over each `*.input.json` of the fixtures folder comparing with its
+/// `*.expected.json`, the result should also be parsed by the typed struct.
+fn check_golden<T: serde::de::DeserializeOwned>(chain: &MigrationChain) -> Result<()> {
+    let folder = Path::new("./mocks/migrations").join(chain.name);
+    let mut checked = 0;
+    for entry in std::fs::read_dir(&folder)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let Some(case) = name.strip_suffix(".input.json") else {
+            continue;
+        };
+
+        let mut document = read_json(&path)?;
+        let expected = read_json(&folder.join(format!("{case}.expected.json")))?;
+        chain.migrate(&mut document)?;
+        assert_eq!(document, expected, "{}/{case}", chain.name);
+
+        // migrations are idempotent
+        let mut again = document.clone();
+        chain.migrate(&mut again)?;
+        assert_eq!(again, expected, "{}/{case} (second run)", chain.name);
+
+        serde_json::from_value::<T>(document).map_err(|e| format!("{}/{case}: {e}", chain.name))?;
+        checked += 1;
+    }
+    assert!(checked > 0, "no fixtures for {}", chain.name);
+    Ok(())
+}
+
+#[test]
+fn test_golden_settings() -> Result<()> {
+    check_golden::<Settings>(&SETTINGS_MIGRATIONS)
+}
+
+#[test]
+fn test_golden_weg_items() -> Result<()> {
+    check_golden::<WegItems>(&WEG_ITEMS_MIGRATIONS)
+}
+
+#[test]
+fn test_golden_toolbar_items() -> Result<()> {
+    check_golden::<Placeholder>(&TOOLBAR_ITEMS_MIGRATIONS)
+}
+
+#[test]
+fn test_golden_app_configs() -> Result<()> {
+    check_golden::<Vec<AppConfig>>(&APP_CONFIGS_MIGRATIONS)
+}
+
+#[test]
+fn test_settings_are_backed_up_before_upgrading() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("slu-migrations-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("settings.json");
+    let original = std::fs::read("./mocks/migrations/settings/v0_snake_case.input.json")?;
+    std::fs::write(&path, &original)?;
+
+    let settings = Settings::load(&path)?;
+    assert_eq!(settings.migrations.schema_version, 4);
+    assert_eq!(settings.migrations.applied.len(), 4);
+    assert_eq!(settings.by_widget.fancy_toolbar.height, 30);
+    assert_eq!(std::fs::read(migration_backup_path(&path, 0))?, original);
+
+    // saved settings are on the latest version, so no other backup is written
+    settings.save(&path)?;
+    Settings::load(&path)?;
+    assert!(!migration_backup_path(&path, 4).exists());
+
+    // files are migrated in place
+    let items = dir.join("weg_items.yml");
+    std::fs::write(
+        &items,
+        "center:\n  - type: PinnedApp\n    id: a\n    path: a.txt\n",
+    )?;
+    let report = WEG_ITEMS_MIGRATIONS.migrate_file(&items)?;
+    assert_eq!((report.from, report.to), (0, 3));
+    assert!(migration_backup_path(&items, 0).exists());
+    let items: WegItems = serde_yaml::from_slice(&std::fs::read(&items)?)?;
+    assert_eq!(items.migrations.schema_version, 3);
+    assert!(WEG_ITEMS_MIGRATIONS
+        .migrate_file(&dir.join("weg_items.yml"))?
+        .is_empty());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_documents_are_migrated_on_load() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("slu-migrations-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+
+    let path = dir.join("toolbar_items.yml");
+    std::fs::write(&path, "left:\n  - type: text\n    id: a\n")?;
+    let toolbar = Placeholder::load(&path)?;
+    assert_eq!(toolbar.migrations.schema_version, 1);
+    assert!(migration_backup_path(&path, 0).exists());
+
+    let path = dir.join("weg_items.yml");
+    std::fs::write(&path, "center:\n  - type: Separator\n    id: a\n")?;
+    assert_eq!(WegItems::load(&path)?.migrations.schema_version, 3);
+
+    let path = dir.join("applications.yml");
+    std::fs::write(
+        &path,
+        "- name: App\n  category: null\n  bound_monitor: null\n  bound_workspace: null\n  identifier:\n    id: app.exe\n    kind: exe\n    matching_strategy: equals\n  options: [float]\n",
+    )?;
+    let configs = AppsConfigurationList::load(&path)?;
+    assert!(configs.search("", "", "APP.EXE", "").is_some());
+
+    // already migrated lists are not written again, keeping the comments of the user
+    let commented = format!("# my apps\n{}", std::fs::read_to_string(&path)?);
+    std::fs::write(&path, &commented)?;
+    AppsConfigurationList::load(&path)?;
+    assert_eq!(std::fs::read_to_string(&path)?, commented);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_by_widget_sections_win_over_legacy_ones() -> Result<()> {
+    let mut settings = serde_json::json!({
+        "seelenweg": { "size": 20 },
+        "byWidget": { "@seelen/weg": { "size": 50 } },
+    });
+    SETTINGS_MIGRATIONS.migrate(&mut settings)?;
+    let settings: Settings = serde_json::from_value(settings)?;
+    assert_eq!(settings.by_widget.weg.size, 50);
+    Ok(())
+}
+
+#[test]
+fn test_settings_parsed_without_the_chain_can_be_migrated() -> Result<()> {
+    let mut settings: Settings = serde_json::from_value(serde_json::json!({
+        "seelenweg": { "size": 20 },
+        "fancyToolbar": { "height": 40 },
+    }))?;
+    settings.migrate()?;
+    assert_eq!(settings.by_widget.weg.size, 20);
+    assert_eq!(settings.by_widget.fancy_toolbar.height, 40);
+    // the legacy sections are consumed
+    settings.by_widget.weg.size = 30;
+    settings.migrate()?;
+    assert_eq!(settings.by_widget.weg.size, 30);
+    Ok(())
+}
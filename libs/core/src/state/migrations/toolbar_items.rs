@@ -0,0 +1,37 @@
+use serde_json::Value;
+
+use crate::error::Result;
+
+use super::{rename_value, zoned_items, Migration, MigrationChain};
+
+pub static TOOLBAR_ITEMS_MIGRATIONS: MigrationChain = MigrationChain {
+    name: "toolbar_items",
+    steps: &[Migration {
+        name: "item_type_names",
+        run: item_type_names,
+    }],
+};
+
+const LEGACY_ITEM_TYPES: [(&str, &str); 13] = [
+    ("text", "Text"),
+    ("generic", "Generic"),
+    ("date", "Date"),
+    ("power", "Power"),
+    ("keyboard", "Keyboard"),
+    ("network", "Network"),
+    ("bluetooth", "Bluetooth"),
+    ("media", "Media"),
+    ("user", "User"),
+    ("notifications", "Notifications"),
+    ("device", "Device"),
+    ("settings", "Settings"),
+    ("workspaces", "Workspaces"),
+];
+
+/// Inline items used lowercase type names, plugin items are strings so they are skipped.
+fn item_type_names(placeholder: &mut Value) -> Result<()> {
+    for item in zoned_items(placeholder) {
+        rename_value(item, "type", &LEGACY_ITEM_TYPES);
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use serde_json::Value;
+
+use crate::{error::Result, state::WegItems};
+
+use super::{rename_value, zoned_items, Migration, MigrationChain};
+
+pub static WEG_ITEMS_MIGRATIONS: MigrationChain = MigrationChain {
+    name: "weg_items",
+    steps: &[
+        Migration {
+            name: "pinned_app_type",
+            run: pinned_app_type,
+        },
+        Migration {
+            name: "pinned_subtype",
+            run: pinned_subtype,
+        },
+        Migration {
+            name: "relaunch_program",
+            run: relaunch_program,
+        },
+    ],
+};
+
+fn is_pinned_or_temporal(item: &serde_json::Map<String, Value>) -> bool {
+    matches!(
+        item.get("type").and_then(Value::as_str),
+        Some("Pinned" | "Temporal")
+    )
+}
+
+fn pinned_app_type(items: &mut Value) -> Result<()> {
+    for item in zoned_items(items) {
+        rename_value(item, "type", &[("PinnedApp", "Pinned")]);
+    }
+    Ok(())
+}
+
+/// Items before v2.1.6 have no subtype, so it is guessed from the item.
+fn pinned_subtype(items: &mut Value) -> Result<()> {
+    for item in zoned_items(items).filter(|item| is_pinned_or_temporal(item)) {
+        let subtype = item.get("subtype").and_then(Value::as_str);
+        if subtype.is_some_and(|s| s != "UnknownV2_1_6") {
+            continue;
+        }
+        let is_dir = item.get("isDir").and_then(Value::as_bool) == Some(true);
+        let is_exe = item
+            .get("relaunchCommand")
+            .and_then(Value::as_str)
+            .is_some_and(|cmd| cmd.to_lowercase().contains(".exe"));
+        let subtype = match (is_dir, is_exe) {
+            (true, _) => "Folder",
+            (false, true) => "App",
+            (false, false) => "File",
+        };
+        item.insert("subtype".to_owned(), subtype.into());
+    }
+    Ok(())
+}
+
+/// Before v2.2.6 the program and its arguments were stored as an inline command.
+fn relaunch_program(items: &mut Value) -> Result<()> {
+    for item in zoned_items(items).filter(|item| is_pinned_or_temporal(item)) {
+        let Some(command) = item.remove("relaunchCommand") else {
+            continue;
+        };
+        let Some(command) = command.as_str() else {
+            continue;
+        };
+        let has_program = item
+            .get("relaunchProgram")
+            .and_then(Value::as_str)
+            .is_some_and(|p| !p.is_empty());
+        if has_program {
+            continue;
+        }
+        let (program, args) = WegItems::get_parts_of_deprecated_inline_command(command);
+        item.insert("relaunchProgram".to_owned(), program.into());
+        if !args.is_empty() {
+            item.insert("relaunchArgs".to_owned(), args.into());
+        }
+    }
+    Ok(())
+}
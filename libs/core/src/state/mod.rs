@@ -1,4 +1,6 @@
 mod icon_pack;
+mod migrations;
+mod performance_mode;
 mod placeholder;
 mod plugin;
 mod popups;
@@ -10,9 +12,13 @@ mod wallpaper;
 mod weg_items;
 mod widget;
 mod wm_layout;
+mod work_area;
 mod workspaces;
+mod zoned_list;
 
 pub use icon_pack::*;
+pub use migrations::*;
+pub use performance_mode::*;
 pub use placeholder::*;
 pub use plugin::*;
 pub use popups::*;
@@ -24,4 +30,6 @@ pub use wallpaper::*;
 pub use weg_items::*;
 pub use widget::*;
 pub use wm_layout::*;
+pub use work_area::*;
 pub use workspaces::*;
+pub use zoned_list::*;
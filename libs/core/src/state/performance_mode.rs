@@ -0,0 +1,289 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    state::{PerformanceMode, PerformanceModeSettings},
+    system_state::{Battery, FocusedApp, PowerMode, PowerStatus},
+};
+
+/// Condition over the power and foreground state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PerformanceCondition {
+    OnBattery,
+    OnAc,
+    /// Effective power mode of the system is one of the list
+    PowerMode {
+        modes: Vec<PowerMode>,
+    },
+    BatteryBelow {
+        percentage: f32,
+    },
+    BatteryAbove {
+        percentage: f32,
+    },
+    /// The focused app is fullscreened, ignoring our overlays
+    FullscreenApp,
+    /// Executable filename of the focused app, case insensitive. e.g. `game.exe`
+    FocusedExe {
+        names: Vec<String>,
+    },
+}
+
+/// Mode to use while all the conditions match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceModeRule {
+    pub when: Vec<PerformanceCondition>,
+    pub mode: PerformanceMode,
+}
+
+/// Snapshot of the system state used to choose the mode
+#[derive(Debug, Clone, Copy)]
+pub struct PerformanceModeInputs<'a> {
+    pub power_status: &'a PowerStatus,
+    pub power_mode: PowerMode,
+    pub batteries: &'a [Battery],
+    pub focused_app: Option<&'a FocusedApp>,
+}
+
+impl PerformanceModeInputs<'_> {
+    fn on_battery(&self) -> bool {
+        self.power_status.ac_line_status == 0
+    }
+
+    /// Mean of the batteries, or the system reported one (255 means unknown).
+    fn battery_percentage(&self) -> Option<f32> {
+        if !self.batteries.is_empty() {
+            let total: f32 = self.batteries.iter().map(|b| b.percentage).sum();
+            return Some(total / self.batteries.len() as f32);
+        }
+        match self.power_status.battery_life_percent {
+            255 => None,
+            percent => Some(percent as f32),
+        }
+    }
+}
+
+/// Chooses the performance mode from the settings rules with hysteresis:
+/// battery thresholds keep matching until the battery moves `battery_margin` back,
+/// and a new mode is only applied after being stable for `switch_delay_ms`.
+#[derive(Debug, Default)]
+pub struct PerformanceModeEngine {
+    current: Option<PerformanceMode>,
+    pending: Option<(PerformanceMode, Instant)>,
+    /// Battery conditions currently matching, by rule and condition index
+    latched: HashSet<(usize, usize)>,
+}
+
+impl PerformanceModeEngine {
+    pub fn current(&self) -> Option<PerformanceMode> {
+        self.current
+    }
+
+    /// Forgets the hysteresis state, should be called when the rules change.
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.latched.clear();
+    }
+
+    fn matches(
+        &mut self,
+        key: (usize, usize),
+        condition: &PerformanceCondition,
+        inputs: &PerformanceModeInputs,
+        margin: f32,
+    ) -> bool {
+        match condition {
+            PerformanceCondition::OnBattery => inputs.on_battery(),
+            PerformanceCondition::OnAc => !inputs.on_battery(),
+            PerformanceCondition::PowerMode { modes } => modes.contains(&inputs.power_mode),
+            PerformanceCondition::BatteryBelow { percentage }
+            | PerformanceCondition::BatteryAbove { percentage } => {
+                let Some(current) = inputs.battery_percentage() else {
+                    self.latched.remove(&key);
+                    return false;
+                };
+                let below = matches!(condition, PerformanceCondition::BatteryBelow { .. });
+                let threshold = match (self.latched.contains(&key), below) {
+                    (false, _) => *percentage,
+                    (true, true) => percentage + margin,
+                    (true, false) => percentage - margin,
+                };
+                let matched = match below {
+                    true => current < threshold,
+                    false => current > threshold,
+                };
+                match matched {
+                    true => self.latched.insert(key),
+                    false => self.latched.remove(&key),
+                };
+                matched
+            }
+            PerformanceCondition::FullscreenApp => inputs
+                .focused_app
+                .is_some_and(|app| app.is_fullscreened && !app.is_seelen_overlay),
+            PerformanceCondition::FocusedExe { names } => inputs
+                .focused_app
+                .and_then(|app| app.exe.as_ref())
+                .is_some_and(|exe| {
+                    let exe = exe.to_string_lossy();
+                    let filename = exe.rsplit(['\\', '/']).next().unwrap_or_default();
+                    names.iter().any(|name| name.eq_ignore_ascii_case(filename))
+                }),
+        }
+    }
+
+    /// Mode wanted by the settings for the inputs, without the switch delay.
+    pub fn desired(
+        &mut self,
+        settings: &PerformanceModeSettings,
+        inputs: &PerformanceModeInputs,
+    ) -> PerformanceMode {
+        let mut desired = None;
+        for (rule_idx, rule) in settings.rules.iter().enumerate() {
+            // all the conditions are evaluated to keep the battery latches updated
+            let mut matched = true;
+            for (idx, condition) in rule.when.iter().enumerate() {
+                matched &=
+                    self.matches((rule_idx, idx), condition, inputs, settings.battery_margin);
+            }
+            if matched && desired.is_none() {
+                desired = Some(rule.mode);
+            }
+        }
+        desired.unwrap_or(if inputs.power_mode == PowerMode::BatterySaver {
+            settings.on_energy_saver
+        } else if inputs.on_battery() {
+            settings.on_battery
+        } else {
+            settings.default
+        })
+    }
+
+    /// Returns the new mode if it changed.
+    pub fn update(
+        &mut self,
+        settings: &PerformanceModeSettings,
+        inputs: &PerformanceModeInputs,
+        now: Instant,
+    ) -> Option<PerformanceMode> {
+        let desired = self.desired(settings, inputs);
+        if self.current.is_none() {
+            self.current = Some(desired);
+            return self.current;
+        }
+        if self.current == Some(desired) {
+            self.pending = None;
+            return None;
+        }
+
+        let since = match self.pending {
+            Some((mode, since)) if mode == desired => since,
+            _ => {
+                self.pending = Some((desired, now));
+                now
+            }
+        };
+        if now.duration_since(since) < Duration::from_millis(settings.switch_delay_ms) {
+            return None;
+        }
+        self.pending = None;
+        self.current = Some(desired);
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{error::Result, system_state::MonitorId};
+
+    fn power_status(on_ac: bool, percent: u8) -> PowerStatus {
+        PowerStatus {
+            ac_line_status: on_ac as u8,
+            battery_flag: 0,
+            battery_life_percent: percent,
+            system_status_flag: 0,
+            battery_life_time: 0,
+            battery_full_life_time: 0,
+        }
+    }
+
+    fn game(fullscreen: bool) -> FocusedApp {
+        FocusedApp {
+            hwnd: 1,
+            monitor: MonitorId("M1".into()),
+            title: "Game".into(),
+            name: "Game".into(),
+            exe: Some("C:\\Games\\Game.exe".into()),
+            umid: None,
+            is_maximized: false,
+            is_fullscreened: fullscreen,
+            is_seelen_overlay: false,
+        }
+    }
+
+    #[test]
+    fn should_switch_modes_with_hysteresis() -> Result<()> {
+        let settings: PerformanceModeSettings = serde_json::from_value(json!({
+            "rules": [
+                { "when": [{ "type": "fullscreenApp" }, { "type": "focusedExe", "names": ["game.exe"] }], "mode": "Minimal" },
+                { "when": [{ "type": "onBattery" }, { "type": "batteryBelow", "percentage": 20 }], "mode": "Extreme" },
+                { "when": [{ "type": "onAc" }], "mode": "Disabled" }
+            ],
+            "switchDelayMs": 1000
+        }))?;
+
+        let mut engine = PerformanceModeEngine::default();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut update = |status: &PowerStatus, app: Option<&FocusedApp>, ms: u64| {
+            let inputs = PerformanceModeInputs {
+                power_status: status,
+                power_mode: PowerMode::Balanced,
+                batteries: &[],
+                focused_app: app,
+            };
+            engine.update(&settings, &inputs, at(ms))
+        };
+
+        let playing = game(true);
+        assert_eq!(
+            update(&power_status(true, 80), None, 0),
+            Some(PerformanceMode::Disabled)
+        );
+        // fullscreen game focused, applied after the switch delay
+        assert_eq!(update(&power_status(true, 80), Some(&playing), 100), None);
+        assert_eq!(
+            update(&power_status(true, 80), Some(&playing), 1100),
+            Some(PerformanceMode::Minimal)
+        );
+
+        // unplugged with low battery
+        assert_eq!(update(&power_status(false, 19), None, 2000), None);
+        assert_eq!(update(&power_status(false, 19), None, 2500), None);
+        assert_eq!(
+            update(&power_status(false, 18), None, 3000),
+            Some(PerformanceMode::Extreme)
+        );
+
+        // charging a bit over the threshold keeps the mode until the margin is passed
+        assert_eq!(update(&power_status(false, 21), None, 5000), None);
+        assert_eq!(update(&power_status(false, 22), None, 7000), None);
+        assert_eq!(update(&power_status(false, 24), None, 8000), None);
+        assert_eq!(
+            update(&power_status(false, 24), None, 9000),
+            Some(PerformanceMode::Minimal)
+        );
+        Ok(())
+    }
+}
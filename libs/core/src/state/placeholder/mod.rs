@@ -1,11 +1,27 @@
-use std::collections::{HashMap, HashSet};
+mod remote_data;
+mod template;
+
+pub use remote_data::*;
+pub use template::*;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use url::Url;
 
-use crate::{resource::PluginId, utils::TsUnknown};
+use crate::{
+    error::Result,
+    resource::PluginId,
+    state::{
+        keep_unique, ItemZone, MigrationRecord, ZonedItem, ZonedList, TOOLBAR_ITEMS_MIGRATIONS,
+    },
+    utils::TsUnknown,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
@@ -32,19 +48,19 @@ macro_rules! common_item {
                 pub id: String,
                 /// Content to display in the item.
                 ///
-                /// Should follow the [mathjs expression syntax](https://mathjs.org/docs/expressions/syntax.html).
+                /// Javascript code evaluated by the ui on a sandbox, the returned value is rendered.
                 ///
                 $(#[$scope])*
                 pub template: String,
                 /// Content to display in tooltip of the item.
                 ///
-                /// Should follow the [mathjs expression syntax](https://mathjs.org/docs/expressions/syntax.html).
+                /// Javascript code evaluated by the ui on a sandbox, the returned value is rendered.
                 ///
                 $(#[$scope])*
                 pub tooltip: Option<String>,
                 /// Badge will be displayed over the item, useful as notifications.
                 ///
-                /// Should follow the [mathjs expression syntax](https://mathjs.org/docs/expressions/syntax.html).
+                /// Javascript code evaluated by the ui on a sandbox, the returned value is rendered.
                 ///
                 $(#[$scope])*
                 pub badge: Option<String>,
@@ -52,7 +68,8 @@ macro_rules! common_item {
                 pub on_click: Option<String>,
                 /// This code will be parsed and executed when the item is clicked.
                 ///
-                /// Should follow the [mathjs expression syntax](https://mathjs.org/docs/expressions/syntax.html).
+                /// Javascript code evaluated by the ui on a sandbox, with access to the actions
+                /// (`invoke`, `emit`, `open`, `run`, ...) instead of the component creators.
                 ///
                 $(#[$scope])*
                 pub on_click_v2: Option<String>,
@@ -85,21 +102,21 @@ pub enum WorkspaceToolbarItemMode {
 
 common_item! {
     /// ## Base Item Scope
-    /// Have all icons defined on [React Icons](https://react-icons.github.io/react-icons) as properties of the object.
-    /// ```js
-    /// const icon: object;
-    /// ```
     /// Haves all environment variables defined on the system as properties of the object.
     /// ```js
     /// const env: object;
+    /// function t(path: string): string
+    /// function trigger(widgetId: string): void
     /// ```
-    /// Functions to add images to the item.
+    /// Functions to create components, only on `template`, `tooltip` and `badge`.
+    /// Icon names are the ones defined on [React Icons](https://react-icons.github.io/react-icons).
     /// ```js
-    /// function getIcon(name: string, size: number = 16): string
-    /// function imgFromUrl (url: string, size: number = 16): string
-    /// function imgFromPath (path: string, size: number = 16): string
-    /// function imgFromExe (exe_path: string, size: number = 16): string
-    /// function t(path: string): string
+    /// function icon(name: string, size?: number): object
+    /// function Icon(props: object): object
+    /// function AppIcon(props: object): object
+    /// function Image(props: object): object
+    /// function Button(props: object): object
+    /// function Group(props: object): object
     /// ```
     struct TextToolbarItem {}
 
@@ -172,6 +189,7 @@ common_item! {
     /// const powerPlan: PowerPlan;
     /// const batteries: Battery[];
     /// const battery: Battery | null;
+    /// const batteryInsights: BatteryInsights | null;
     /// ```
     struct PowerToolbarItem {}
 
@@ -410,6 +428,154 @@ impl ToolbarItem {
     }
 }
 
+/// Variables available on every template of every item, see [`TEMPLATE_HELPERS`] and
+/// [`ACTION_HELPERS`] for the functions that depend on the field.
+pub static TOOLBAR_BASE_SCOPE: &[&str] = &["env", "t", "trigger"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+#[ts(repr(enum = name))]
+pub enum ToolbarTemplateField {
+    Template,
+    Tooltip,
+    Badge,
+    OnClickV2,
+}
+
+/// Problem found while statically checking the templates of a toolbar item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolbarTemplateIssue {
+    /// Id of the toolbar item
+    pub item: String,
+    pub field: ToolbarTemplateField,
+    /// Identifiers not present on the scope of the item, sorted.
+    pub unknown_identifiers: Vec<String>,
+    /// If set, the template could not be parsed.
+    pub syntax_error: Option<String>,
+}
+
+macro_rules! with_common_item {
+    ($value:expr, $item:ident => $body:expr) => {
+        match $value {
+            ToolbarItem::Text($item) => $body,
+            ToolbarItem::Generic($item) => $body,
+            ToolbarItem::Date($item) => $body,
+            ToolbarItem::Power($item) => $body,
+            ToolbarItem::Keyboard($item) => $body,
+            ToolbarItem::Network($item) => $body,
+            ToolbarItem::Bluetooth($item) => $body,
+            ToolbarItem::Media($item) => $body,
+            ToolbarItem::User($item) => $body,
+            ToolbarItem::Notifications($item) => $body,
+            ToolbarItem::Device($item) => $body,
+            ToolbarItem::Settings($item) => $body,
+            ToolbarItem::Workspaces($item) => $body,
+        }
+    };
+}
+
+impl ToolbarItem {
+    /// Variables added to the base scope by the item type, as documented on each item.
+    pub fn scope_variables(&self) -> &'static [&'static str] {
+        match self {
+            ToolbarItem::Generic(_) => &["window"],
+            ToolbarItem::Date(_) => &["date"],
+            ToolbarItem::Power(_) => &[
+                "power",
+                "powerPlan",
+                "powerMode",
+                "batteries",
+                "battery",
+                "batteryInsights",
+            ],
+            ToolbarItem::Keyboard(_) => &[
+                "languages",
+                "activeLang",
+                "activeKeyboard",
+                "activeLangPrefix",
+                "activeKeyboardPrefix",
+            ],
+            ToolbarItem::Network(_) => &["online", "interfaces", "usingInterface"],
+            ToolbarItem::Bluetooth(_) => &["bluetoothState", "devices", "connectedDevices"],
+            ToolbarItem::Media(_) => &[
+                "volume",
+                "isMuted",
+                "inputVolume",
+                "inputIsMuted",
+                "mediaSession",
+            ],
+            ToolbarItem::User(_) => &["user", "count"],
+            ToolbarItem::Notifications(_) => &["count"],
+            ToolbarItem::Text(_)
+            | ToolbarItem::Device(_)
+            | ToolbarItem::Settings(_)
+            | ToolbarItem::Workspaces(_) => &[],
+        }
+    }
+
+    fn templates(&self) -> Vec<(ToolbarTemplateField, &str)> {
+        with_common_item!(self, item => {
+            let mut templates = vec![(ToolbarTemplateField::Template, item.template.as_str())];
+            templates.extend(item.tooltip.as_deref().map(|t| (ToolbarTemplateField::Tooltip, t)));
+            templates.extend(item.badge.as_deref().map(|t| (ToolbarTemplateField::Badge, t)));
+            templates.extend(
+                item.on_click_v2
+                    .as_deref()
+                    .map(|t| (ToolbarTemplateField::OnClickV2, t)),
+            );
+            templates
+        })
+    }
+
+    pub fn remote_data(&self) -> &HashMap<String, RemoteDataDeclaration> {
+        with_common_item!(self, item => &item.remote_data)
+    }
+
+    /// Parses the templates of the item and reports the identifiers that would not
+    /// be defined when evaluated on the ui.
+    pub fn validate_templates(&self) -> Vec<ToolbarTemplateIssue> {
+        let mut known: HashSet<&str> = TOOLBAR_BASE_SCOPE
+            .iter()
+            .chain(TEMPLATE_GLOBALS)
+            .chain(self.scope_variables())
+            .copied()
+            .collect();
+        known.extend(self.remote_data().keys().map(String::as_str));
+
+        let mut issues = Vec::new();
+        for (field, source) in self.templates() {
+            let mut issue = ToolbarTemplateIssue {
+                item: self.id(),
+                field,
+                unknown_identifiers: Vec::new(),
+                syntax_error: None,
+            };
+            match Template::parse(source) {
+                Ok(template) => {
+                    issue.unknown_identifiers = template
+                        .identifiers()
+                        .into_iter()
+                        .filter(|name| !known.contains(name.as_str()))
+                        .filter(|name| {
+                            let helpers = match field {
+                                ToolbarTemplateField::OnClickV2 => ACTION_HELPERS,
+                                _ => TEMPLATE_HELPERS,
+                            };
+                            !helpers.contains(&name.as_str())
+                        })
+                        .collect();
+                }
+                Err(err) => issue.syntax_error = Some(err.to_string()),
+            }
+            if !issue.unknown_identifiers.is_empty() || issue.syntax_error.is_some() {
+                issues.push(issue);
+            }
+        }
+        issues
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(untagged)]
 pub enum ToolbarItem2 {
@@ -417,7 +583,27 @@ pub enum ToolbarItem2 {
     Inline(Box<ToolbarItem>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema, TS)]
+impl ZonedItem for ToolbarItem2 {
+    fn item_id(&self) -> String {
+        match self {
+            ToolbarItem2::Plugin(id) => id.to_string(),
+            ToolbarItem2::Inline(item) => item.id(),
+        }
+    }
+
+    /// Plugin items are identified by the plugin id, so only inline items can be changed.
+    fn set_item_id(&mut self, id: String) {
+        if let ToolbarItem2::Inline(item) = self {
+            item.set_id(id);
+        }
+    }
+
+    fn new_separator(_id: String) -> Option<Self> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(default, rename_all = "camelCase")]
 #[cfg_attr(feature = "gen-binds", ts(export))]
 pub struct Placeholder {
@@ -429,38 +615,125 @@ pub struct Placeholder {
     pub center: Vec<ToolbarItem2>,
     /// Items to be displayed in the toolbar
     pub right: Vec<ToolbarItem2>,
+    #[serde(flatten)]
+    pub migrations: MigrationRecord,
+}
+
+impl Default for Placeholder {
+    fn default() -> Self {
+        Self {
+            is_reorder_disabled: false,
+            left: Vec::new(),
+            center: Vec::new(),
+            right: Vec::new(),
+            migrations: TOOLBAR_ITEMS_MIGRATIONS.record(),
+        }
+    }
 }
 
 impl Placeholder {
     fn sanitize_items(dict: &mut HashSet<String>, items: Vec<ToolbarItem2>) -> Vec<ToolbarItem2> {
-        let mut result = Vec::new();
-        for item in items {
-            match item {
-                ToolbarItem2::Plugin(id) => {
-                    let str_id = id.to_string();
-                    if !dict.contains(&str_id) && id.is_valid() {
-                        dict.insert(str_id);
-                        result.push(ToolbarItem2::Plugin(id));
-                    }
-                }
-                ToolbarItem2::Inline(mut item) => {
-                    if item.id().is_empty() {
-                        item.set_id(uuid::Uuid::new_v4().to_string());
-                    }
-                    if !dict.contains(&item.id()) {
-                        dict.insert(item.id());
-                        result.push(ToolbarItem2::Inline(item));
-                    }
-                }
-            }
-        }
-        result
+        items
+            .into_iter()
+            .filter(|item| match item {
+                ToolbarItem2::Plugin(id) => id.is_valid(),
+                ToolbarItem2::Inline(_) => true,
+            })
+            .filter_map(|item| keep_unique(dict, item))
+            .collect()
     }
 
-    pub fn sanitize(&mut self) {
+    /// Loads the items file (json or yaml), migrating it to the latest version.\
+    /// Call [`Self::sanitize`] after loading to get the template issues.
+    pub fn load(path: &Path) -> Result<Self> {
+        TOOLBAR_ITEMS_MIGRATIONS.load_file(path)
+    }
+
+    /// Removes duplicated items and returns the template issues of the inline items.
+    #[must_use]
+    pub fn sanitize(&mut self) -> Vec<ToolbarTemplateIssue> {
         let mut dict = HashSet::new();
         self.left = Self::sanitize_items(&mut dict, std::mem::take(&mut self.left));
         self.center = Self::sanitize_items(&mut dict, std::mem::take(&mut self.center));
         self.right = Self::sanitize_items(&mut dict, std::mem::take(&mut self.right));
+
+        self.left
+            .iter()
+            .chain(&self.center)
+            .chain(&self.right)
+            .filter_map(|item| match item {
+                ToolbarItem2::Inline(item) => Some(item.validate_templates()),
+                ToolbarItem2::Plugin(_) => None,
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl ZonedList for Placeholder {
+    type Item = ToolbarItem2;
+
+    fn is_reorder_disabled(&self) -> bool {
+        self.is_reorder_disabled
+    }
+
+    fn zone(&self, zone: ItemZone) -> &Vec<ToolbarItem2> {
+        match zone {
+            ItemZone::Left => &self.left,
+            ItemZone::Center => &self.center,
+            ItemZone::Right => &self.right,
+        }
+    }
+
+    fn zone_mut(&mut self, zone: ItemZone) -> &mut Vec<ToolbarItem2> {
+        match zone {
+            ItemZone::Left => &mut self.left,
+            ItemZone::Center => &mut self.center,
+            ItemZone::Right => &mut self.right,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_unknown_identifiers_per_item() {
+        let mut placeholder = Placeholder {
+            left: vec![ToolbarItem2::Inline(Box::new(ToolbarItem::Power(
+                PowerToolbarItem {
+                    id: "battery".to_owned(),
+                    template:
+                        "return battery ? Math.round(battery.percentage) + '%' : t('power.none')"
+                            .to_owned(),
+                    tooltip: Some("return window.title".to_owned()),
+                    on_click_v2: Some("invoke(SeelenCommand.OpenSettings)".to_owned()),
+                    ..Default::default()
+                },
+            )))],
+            right: vec![ToolbarItem2::Inline(Box::new(ToolbarItem::Text(
+                TextToolbarItem {
+                    id: "clock".to_owned(),
+                    template: "return weather.temp + (".to_owned(),
+                    badge: Some("return icon('PiBell')".to_owned()),
+                    on_click_v2: Some("open(env.USERPROFILE); icon('PiBell')".to_owned()),
+                    ..Default::default()
+                },
+            )))],
+            ..Default::default()
+        };
+
+        let issues = placeholder.sanitize();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].item, "battery");
+        assert_eq!(issues[0].field, ToolbarTemplateField::Tooltip);
+        assert_eq!(issues[0].unknown_identifiers, ["window"]);
+        assert_eq!(issues[1].item, "clock");
+        assert_eq!(issues[1].field, ToolbarTemplateField::Template);
+        assert!(issues[1].syntax_error.is_some());
+        // component creators are not available on actions
+        assert_eq!(issues[2].field, ToolbarTemplateField::OnClickV2);
+        assert_eq!(issues[2].unknown_identifiers, ["icon"]);
     }
 }
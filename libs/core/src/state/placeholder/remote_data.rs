@@ -0,0 +1,567 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+    io::Read,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    error::Result,
+    state::{Placeholder, ToolbarItem2},
+    utils::{sha256_hex, write_file_atomically, HttpClient},
+};
+
+use super::RemoteDataDeclaration;
+
+/// Shorter intervals are raised to this value
+pub const REMOTE_DATA_MIN_INTERVAL: Duration = Duration::from_secs(10);
+/// Longer intervals are lowered to this value
+pub const REMOTE_DATA_MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Upper limit of the wait between retries of a failing source
+pub const REMOTE_DATA_MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+impl RemoteDataDeclaration {
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn request_init(&self) -> Option<&serde_json::Value> {
+        self.request_init.as_ref().map(|init| &init.0)
+    }
+
+    /// Clamped update interval, `None` means the data is fetched only once.
+    pub fn update_interval(&self) -> Option<Duration> {
+        self.update_interval_seconds.map(|secs| {
+            Duration::from_secs(secs as u64)
+                .clamp(REMOTE_DATA_MIN_INTERVAL, REMOTE_DATA_MAX_INTERVAL)
+        })
+    }
+
+    /// Identical requests share the same key, so they are fetched once for all the items.
+    fn source_key(&self) -> String {
+        match self.request_init() {
+            Some(init) => format!("{} {}", self.url, init),
+            None => self.url.to_string(),
+        }
+    }
+}
+
+/// Item and remote data key, as declared on `remoteData` of the toolbar item.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RemoteDataSubscriber {
+    pub item: String,
+    pub key: String,
+}
+
+/// New value to be pushed to the scope of a toolbar item
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteDataUpdate {
+    pub item: String,
+    pub key: String,
+    pub value: serde_json::Value,
+    /// The value comes from the disk cache instead of a fresh response
+    pub cached: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedRemoteData {
+    url: Url,
+    fetched_at: u64,
+    value: serde_json::Value,
+}
+
+#[derive(Debug)]
+struct SubscriberState {
+    /// Own interval of the subscriber, the source may be fetched more often
+    interval: Option<Duration>,
+    /// `None` when the subscriber doesn't want more updates
+    next_due: Option<SystemTime>,
+    /// Version of the source value last pushed to the subscriber
+    seen: u64,
+}
+
+#[derive(Debug)]
+struct RemoteSource {
+    declaration: RemoteDataDeclaration,
+    subscribers: BTreeMap<RemoteDataSubscriber, SubscriberState>,
+    /// Shortest interval of the subscribers
+    interval: Option<Duration>,
+    /// `None` when there is nothing else to fetch
+    next_fetch: Option<SystemTime>,
+    /// Consecutive failures, reset on success
+    failures: u32,
+    fetches: u64,
+    last_good: Option<serde_json::Value>,
+    /// Incremented each time `last_good` changes
+    version: u64,
+}
+
+/// Fetches the `remoteData` of the toolbar items, deduplicating identical requests
+/// across items. The scheduler has no thread of its own, the owner should call
+/// [`RemoteDataScheduler::poll`] when [`RemoteDataScheduler::next_wakeup`] is reached.
+#[derive(Debug, Default)]
+pub struct RemoteDataScheduler {
+    sources: HashMap<String, RemoteSource>,
+    /// Last good responses are stored here, `None` disables the disk cache.
+    cache_dir: Option<PathBuf>,
+}
+
+impl RemoteDataScheduler {
+    pub fn new(cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            sources: HashMap::new(),
+            cache_dir,
+        }
+    }
+
+    /// Replaces the subscriptions by the remote data of the inline items of the placeholder.\
+    /// Returns the cached values of the new subscribers, so items don't start empty.
+    pub fn subscribe_placeholder(
+        &mut self,
+        placeholder: &Placeholder,
+        now: SystemTime,
+    ) -> Vec<RemoteDataUpdate> {
+        let mut wanted: HashMap<String, (RemoteDataDeclaration, Vec<RemoteDataSubscriber>)> =
+            HashMap::new();
+        let items = placeholder
+            .left
+            .iter()
+            .chain(&placeholder.center)
+            .chain(&placeholder.right);
+        for item in items {
+            let ToolbarItem2::Inline(item) = item else {
+                continue;
+            };
+            for (key, declaration) in item.remote_data() {
+                let subscriber = RemoteDataSubscriber {
+                    item: item.id(),
+                    key: key.clone(),
+                };
+                wanted
+                    .entry(declaration.source_key())
+                    .or_insert_with(|| (declaration.clone(), Vec::new()))
+                    .1
+                    .push(subscriber);
+            }
+        }
+
+        self.sources.retain(|key, _| wanted.contains_key(key));
+
+        let mut updates = Vec::new();
+        for (source_key, (declaration, subscribers)) in wanted {
+            let subscribers: BTreeMap<RemoteDataSubscriber, Option<Duration>> = subscribers
+                .into_iter()
+                .map(|s| {
+                    let interval = item_interval(placeholder, &s);
+                    (s, interval)
+                })
+                .collect();
+            // the source is shared, so it is fetched at the shortest interval
+            let interval = subscribers.values().flatten().min().copied();
+            let last_good = match self.sources.contains_key(&source_key) {
+                true => None,
+                false => self.read_cache(&source_key),
+            };
+            let source = self
+                .sources
+                .entry(source_key)
+                .or_insert_with(|| RemoteSource {
+                    declaration,
+                    subscribers: BTreeMap::new(),
+                    interval,
+                    next_fetch: Some(now),
+                    failures: 0,
+                    fetches: 0,
+                    version: last_good.is_some() as u64,
+                    last_good,
+                });
+
+            // a shorter interval should be applied without waiting the current one
+            if interval != source.interval {
+                if let (Some(interval), Some(next)) = (interval, source.next_fetch) {
+                    source.next_fetch = Some(next.min(now + interval));
+                } else if source.next_fetch.is_none() && interval.is_some() && source.failures == 0
+                {
+                    source.next_fetch = Some(now);
+                }
+                source.interval = interval;
+            }
+
+            let mut states = std::mem::take(&mut source.subscribers);
+            for (subscriber, interval) in subscribers {
+                let state = match states.remove(&subscriber) {
+                    Some(mut state) => {
+                        if interval != state.interval {
+                            state.next_due = match (interval, state.next_due) {
+                                (Some(interval), Some(next)) => Some(next.min(now + interval)),
+                                (Some(_), None) => Some(now),
+                                (None, next) => next,
+                            };
+                            state.interval = interval;
+                        }
+                        state
+                    }
+                    None => {
+                        if let Some(value) = &source.last_good {
+                            updates.push(RemoteDataUpdate {
+                                item: subscriber.item.clone(),
+                                key: subscriber.key.clone(),
+                                value: value.clone(),
+                                cached: true,
+                            });
+                        }
+                        SubscriberState {
+                            interval,
+                            next_due: Some(now),
+                            seen: source.version,
+                        }
+                    }
+                };
+                source.subscribers.insert(subscriber, state);
+            }
+        }
+        updates.sort_by(|a, b| (&a.item, &a.key).cmp(&(&b.item, &b.key)));
+        updates
+    }
+
+    /// Earliest time a source should be fetched
+    pub fn next_wakeup(&self) -> Option<SystemTime> {
+        self.sources.values().filter_map(|s| s.next_fetch).min()
+    }
+
+    /// Number of distinct requests being tracked
+    pub fn sources_len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Fetches the due sources and returns the updates for their subscribers whose
+    /// own interval has elapsed.\
+    /// Failing sources are retried with exponential backoff keeping their last good value.
+    pub fn poll(&mut self, client: &dyn HttpClient, now: SystemTime) -> Vec<RemoteDataUpdate> {
+        let mut updates = Vec::new();
+        let mut stored = Vec::new();
+
+        for (source_key, source) in &mut self.sources {
+            if source.next_fetch.is_none_or(|next| next > now) {
+                continue;
+            }
+            source.fetches += 1;
+
+            let value = match fetch(client, &source.declaration) {
+                Ok(value) => value,
+                Err(_) => {
+                    source.failures += 1;
+                    let backoff = REMOTE_DATA_MIN_INTERVAL
+                        .saturating_mul(2u32.saturating_pow(source.failures - 1))
+                        .min(REMOTE_DATA_MAX_BACKOFF);
+                    source.next_fetch =
+                        Some(now + backoff + jitter(source_key, source.fetches, backoff));
+                    continue;
+                }
+            };
+
+            source.failures = 0;
+            source.next_fetch = source
+                .interval
+                .map(|interval| now + interval + jitter(source_key, source.fetches, interval));
+
+            if source.last_good.as_ref() != Some(&value) {
+                source.version += 1;
+                source.last_good = Some(value.clone());
+                stored.push((source_key.clone(), source.declaration.url.clone(), value));
+            }
+
+            for (subscriber, state) in &mut source.subscribers {
+                if state.next_due.is_none_or(|due| due > now) {
+                    continue;
+                }
+                state.next_due = state.interval.map(|interval| now + interval);
+                // same value, nothing pushed
+                if state.seen == source.version {
+                    continue;
+                }
+                state.seen = source.version;
+                updates.push(RemoteDataUpdate {
+                    item: subscriber.item.clone(),
+                    key: subscriber.key.clone(),
+                    value: source.last_good.clone().unwrap_or_default(),
+                    cached: false,
+                });
+            }
+        }
+
+        for (source_key, url, value) in stored {
+            self.write_cache(&source_key, url, value, now);
+        }
+        updates.sort_by(|a, b| (&a.item, &a.key).cmp(&(&b.item, &b.key)));
+        updates
+    }
+
+    fn cache_file(&self, source_key: &str) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        Some(dir.join(format!("{}.json", sha256_hex(source_key.as_bytes()))))
+    }
+
+    fn read_cache(&self, source_key: &str) -> Option<serde_json::Value> {
+        let content = std::fs::read(self.cache_file(source_key)?).ok()?;
+        let cached: CachedRemoteData = serde_json::from_slice(&content).ok()?;
+        Some(cached.value)
+    }
+
+    fn write_cache(&self, source_key: &str, url: Url, value: serde_json::Value, now: SystemTime) {
+        let Some(path) = self.cache_file(source_key) else {
+            return;
+        };
+        let cached = CachedRemoteData {
+            url,
+            fetched_at: now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            value,
+        };
+        // cache failures should never stop the updates
+        if let Ok(bytes) = serde_json::to_vec(&cached) {
+            let _ = write_file_atomically(&path, &bytes);
+        }
+    }
+}
+
+fn item_interval(placeholder: &Placeholder, subscriber: &RemoteDataSubscriber) -> Option<Duration> {
+    placeholder
+        .left
+        .iter()
+        .chain(&placeholder.center)
+        .chain(&placeholder.right)
+        .find_map(|item| match item {
+            ToolbarItem2::Inline(item) if item.id() == subscriber.item => {
+                item.remote_data().get(&subscriber.key)
+            }
+            _ => None,
+        })
+        .and_then(RemoteDataDeclaration::update_interval)
+}
+
+/// Up to 10% of `base`, stable for the same source and attempt so
+/// sources sharing an interval don't fire all at once.
+fn jitter(source_key: &str, attempt: u64, base: Duration) -> Duration {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (source_key, attempt).hash(&mut hasher);
+    let max = base.as_millis() as u64 / 10;
+    match max {
+        0 => Duration::ZERO,
+        max => Duration::from_millis(hasher.finish() % max),
+    }
+}
+
+/// Json responses are parsed, any other content is kept as text.
+fn fetch(
+    client: &dyn HttpClient,
+    declaration: &RemoteDataDeclaration,
+) -> Result<serde_json::Value> {
+    let mut response = client.request(declaration.url.as_str(), declaration.request_init())?;
+    if !(200..300).contains(&response.status) {
+        return Err(format!("unexpected http status {}", response.status).into());
+    }
+    let mut body = String::new();
+    response.body.read_to_string(&mut body)?;
+    Ok(serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io::Cursor};
+
+    use super::*;
+    use crate::{
+        state::{TextToolbarItem, ToolbarItem},
+        utils::{HttpResponse, TsUnknown},
+    };
+
+    #[derive(Default)]
+    struct MockServer {
+        responses: RefCell<HashMap<String, (u16, String)>>,
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl MockServer {
+        fn respond(&self, url: &str, status: u16, body: &str) {
+            self.responses
+                .borrow_mut()
+                .insert(url.to_owned(), (status, body.to_owned()));
+        }
+    }
+
+    impl HttpClient for MockServer {
+        fn get(&self, url: &str, _offset: u64) -> Result<HttpResponse> {
+            self.requests.borrow_mut().push(url.to_owned());
+            let (status, body) = self
+                .responses
+                .borrow()
+                .get(url)
+                .cloned()
+                .unwrap_or((404, String::new()));
+            Ok(HttpResponse {
+                status,
+                body: Box::new(Cursor::new(body.into_bytes())),
+            })
+        }
+    }
+
+    fn item(id: &str, remote: &[(&str, &str, Option<u32>)]) -> ToolbarItem2 {
+        let remote_data = remote
+            .iter()
+            .map(|(key, url, interval)| {
+                let declaration = RemoteDataDeclaration {
+                    url: Url::parse(url).unwrap(),
+                    request_init: None,
+                    update_interval_seconds: *interval,
+                };
+                (key.to_string(), declaration)
+            })
+            .collect();
+        ToolbarItem2::Inline(Box::new(ToolbarItem::Text(TextToolbarItem {
+            id: id.to_owned(),
+            remote_data,
+            ..Default::default()
+        })))
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn should_dedup_urls_and_backoff_on_failures() {
+        let server = MockServer::default();
+        server.respond("https://api.test/weather", 200, r#"{"temp": 20}"#);
+
+        let placeholder = Placeholder {
+            left: vec![
+                item("a", &[("weather", "https://api.test/weather", Some(1))]),
+                item("b", &[("w", "https://api.test/weather", Some(600))]),
+            ],
+            ..Default::default()
+        };
+        let mut scheduler = RemoteDataScheduler::new(None);
+        assert!(scheduler
+            .subscribe_placeholder(&placeholder, at(0))
+            .is_empty());
+        assert_eq!(scheduler.sources_len(), 1);
+
+        let updates = scheduler.poll(&server, at(0));
+        assert_eq!(server.requests.borrow().len(), 1);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].value, serde_json::json!({"temp": 20}));
+
+        // 1 second interval is clamped
+        let next = scheduler.next_wakeup().unwrap();
+        assert!(next >= at(10) && next <= at(11));
+        assert!(scheduler.poll(&server, at(5)).is_empty());
+
+        // same value, nothing pushed
+        assert!(scheduler.poll(&server, at(11)).is_empty());
+        assert_eq!(server.requests.borrow().len(), 2);
+
+        server.respond("https://api.test/weather", 500, "");
+        scheduler.poll(&server, at(22));
+        scheduler.poll(&server, at(33));
+        let next = scheduler.next_wakeup().unwrap();
+        assert!(next >= at(53) && next <= at(55));
+    }
+
+    #[test]
+    fn should_push_to_each_subscriber_at_its_own_interval() {
+        let server = MockServer::default();
+        let url = "https://api.test/stocks";
+        server.respond(url, 200, "1");
+
+        let placeholder = Placeholder {
+            left: vec![
+                item("fast", &[("stocks", url, Some(10))]),
+                item("slow", &[("stocks", url, Some(60))]),
+            ],
+            ..Default::default()
+        };
+        let mut scheduler = RemoteDataScheduler::new(None);
+        scheduler.subscribe_placeholder(&placeholder, at(0));
+
+        let pushed = |updates: Vec<RemoteDataUpdate>| -> Vec<(String, serde_json::Value)> {
+            updates.into_iter().map(|u| (u.item, u.value)).collect()
+        };
+        assert_eq!(pushed(scheduler.poll(&server, at(0))).len(), 2);
+
+        // the source is fetched at the shortest interval, only the fast item is due
+        server.respond(url, 200, "2");
+        assert_eq!(
+            pushed(scheduler.poll(&server, at(11))),
+            [("fast".to_owned(), serde_json::json!(2))]
+        );
+        server.respond(url, 200, "3");
+        for secs in [22, 33, 44, 55] {
+            let updates = pushed(scheduler.poll(&server, at(secs)));
+            assert!(
+                updates.iter().all(|(item, _)| item == "fast"),
+                "{updates:?}"
+            );
+        }
+
+        // the slow item receives the latest value once its interval elapses
+        assert_eq!(
+            pushed(scheduler.poll(&server, at(66))),
+            [("slow".to_owned(), serde_json::json!(3))]
+        );
+        assert_eq!(server.requests.borrow().len(), 7);
+    }
+
+    #[test]
+    fn should_not_send_requests_with_init_as_plain_get() {
+        let server = MockServer::default();
+        server.respond("https://api.test/login", 200, "ok");
+
+        let mut item = item("login", &[("login", "https://api.test/login", None)]);
+        if let ToolbarItem2::Inline(inline) = &mut item {
+            if let ToolbarItem::Text(text) = inline.as_mut() {
+                let declaration = text.remote_data.get_mut("login").unwrap();
+                declaration.request_init = Some(TsUnknown(serde_json::json!({ "method": "POST" })));
+            }
+        }
+        let placeholder = Placeholder {
+            left: vec![item],
+            ..Default::default()
+        };
+
+        let mut scheduler = RemoteDataScheduler::new(None);
+        scheduler.subscribe_placeholder(&placeholder, at(0));
+        assert!(scheduler.poll(&server, at(0)).is_empty());
+        assert!(server.requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn should_serve_last_good_response_from_disk() {
+        let dir = std::env::temp_dir().join(format!("slu-remote-data-{}", uuid::Uuid::new_v4()));
+        let server = MockServer::default();
+        server.respond("https://api.test/quote", 200, "keep going");
+
+        let placeholder = Placeholder {
+            right: vec![item("quote", &[("quote", "https://api.test/quote", None)])],
+            ..Default::default()
+        };
+
+        let mut scheduler = RemoteDataScheduler::new(Some(dir.clone()));
+        scheduler.subscribe_placeholder(&placeholder, at(0));
+        scheduler.poll(&server, at(0));
+        // fetched only once without interval
+        assert_eq!(scheduler.next_wakeup(), None);
+
+        let mut scheduler = RemoteDataScheduler::new(Some(dir.clone()));
+        let updates = scheduler.subscribe_placeholder(&placeholder, at(100));
+        assert_eq!(updates.len(), 1);
+        assert!(updates[0].cached);
+        assert_eq!(updates[0].value, serde_json::json!("keep going"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1347 @@
+use std::collections::{BTreeSet, HashSet};
+
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+
+/// Component creators available on the rendered templates (`template`, `tooltip`
+/// and `badge`), as defined by the toolbar ui.
+pub static TEMPLATE_HELPERS: &[&str] = &["icon", "Icon", "AppIcon", "Image", "Button", "Group"];
+
+/// Functions only available on `onClickV2` actions, these are executed by the ui.
+pub static ACTION_HELPERS: &[&str] = &[
+    "SeelenCommand",
+    "SeelenEvent",
+    "invoke",
+    "emit",
+    "emitTo",
+    "open",
+    "run",
+    "copyToClipboard",
+];
+
+/// Javascript globals exposed by the sandbox where the templates run.
+pub static TEMPLATE_GLOBALS: &[&str] = &[
+    "undefined",
+    "NaN",
+    "Infinity",
+    "Math",
+    "JSON",
+    "Date",
+    "Intl",
+    "RegExp",
+    "Object",
+    "Array",
+    "String",
+    "Number",
+    "Boolean",
+    "Map",
+    "Set",
+    "Promise",
+    "Error",
+    "console",
+    "isNaN",
+    "isFinite",
+    "parseInt",
+    "parseFloat",
+    "encodeURI",
+    "encodeURIComponent",
+    "decodeURI",
+    "decodeURIComponent",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplateChunk {
+    Text(String),
+    /// Source of a `${...}` placeholder
+    Code(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Template(Vec<TemplateChunk>),
+    Ident(String),
+    Punct(&'static str),
+    Newline,
+}
+
+/// Longer operators first so they are matched before their prefixes.
+static PUNCTUATION: &[&str] = &[
+    "===", "!==", "=>", "?.", "??", "==", "!=", "<=", ">=", "&&", "||", "**", "+", "-", "*", "/",
+    "%", "^", "&", "|", "<", ">", "!", "?", ":", ".", ",", "(", ")", "[", "]", "{", "}", ";", "=",
+];
+
+fn unescape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        other => other,
+    }
+}
+
+/// Reads the quoted string starting at `i`, returns its value and the position after it.
+fn string_literal(chars: &[char], mut i: usize) -> Result<(String, usize)> {
+    let quote = chars[i];
+    i += 1;
+    let mut value = String::new();
+    loop {
+        let Some(&next) = chars.get(i) else {
+            return Err("unterminated string".into());
+        };
+        i += 1;
+        match next {
+            '\\' => {
+                let escaped = chars.get(i).ok_or("unterminated string")?;
+                i += 1;
+                value.push(unescape(*escaped));
+            }
+            c if c == quote => return Ok((value, i)),
+            other => value.push(other),
+        }
+    }
+}
+
+/// Reads the template literal starting at the backtick on `i`, returns its chunks and
+/// the position after it.
+fn template_literal(chars: &[char], mut i: usize) -> Result<(Vec<TemplateChunk>, usize)> {
+    i += 1;
+    let mut chunks = Vec::new();
+    let mut text = String::new();
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err("unterminated template literal".into());
+        };
+        i += 1;
+        match c {
+            '`' => break,
+            '\\' => {
+                let escaped = chars.get(i).ok_or("unterminated template literal")?;
+                i += 1;
+                text.push(unescape(*escaped));
+            }
+            '$' if chars.get(i) == Some(&'{') => {
+                chunks.push(TemplateChunk::Text(std::mem::take(&mut text)));
+                let start = i + 1;
+                i = closing_brace(chars, start)?;
+                chunks.push(TemplateChunk::Code(chars[start..i].iter().collect()));
+                i += 1;
+            }
+            other => text.push(other),
+        }
+    }
+    chunks.push(TemplateChunk::Text(text));
+    Ok((chunks, i))
+}
+
+/// Position of the `}` closing a block that starts at `i`, nested strings are skipped.
+fn closing_brace(chars: &[char], mut i: usize) -> Result<usize> {
+    let mut depth = 0;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            '}' if depth == 0 => return Ok(i),
+            '}' => depth -= 1,
+            '{' => depth += 1,
+            '"' | '\'' => {
+                i = string_literal(chars, i)?.1;
+                continue;
+            }
+            '`' => {
+                i = template_literal(chars, i)?.1;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Err("unterminated template literal".into())
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            tokens.push(Token::Newline);
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let number = literal
+                .parse()
+                .map_err(|_| format!("invalid number `{literal}`"))?;
+            tokens.push(Token::Number(number));
+        } else if c == '"' || c == '\'' {
+            let (value, end) = string_literal(&chars, i)?;
+            tokens.push(Token::Str(value));
+            i = end;
+        } else if c == '`' {
+            let (chunks, end) = template_literal(&chars, i)?;
+            tokens.push(Token::Template(chunks));
+            i = end;
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|p| {
+                    p.chars()
+                        .enumerate()
+                        .all(|(j, pc)| chars.get(i + j) == Some(&pc))
+                })
+                .ok_or_else(|| format!("unexpected character `{c}`"))?;
+            // `a?.5:1` is a ternary, not an optional chain
+            if *punct == "?." && chars.get(i + 2).is_some_and(|c| c.is_ascii_digit()) {
+                tokens.push(Token::Punct("?"));
+                i += 1;
+                continue;
+            }
+            tokens.push(Token::Punct(punct));
+            i += punct.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Literal(Value),
+    Ident(String),
+    Array(Vec<Expr>),
+    Object(Vec<(String, Expr)>),
+    Member {
+        object: Box<Expr>,
+        property: Box<Expr>,
+        optional: bool,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    Unary {
+        op: &'static str,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: &'static str,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Conditional {
+        test: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// Text and placeholders of a template literal
+    TemplateLiteral(Vec<Expr>),
+    Arrow {
+        params: Vec<String>,
+        body: Vec<Statement>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    Expr(Expr),
+    Assign(String, Expr),
+    Return(Expr),
+}
+
+/// Max depth of nested expressions, deeper templates would overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Newlines are ignored inside parentheses, brackets and braces.
+    nesting: usize,
+    /// Nested expressions being parsed, limited by [`MAX_DEPTH`].
+    depth: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            pos: 0,
+            nesting: 0,
+            depth: 0,
+        })
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        if self.nesting > 0 {
+            self.skip_newlines();
+        }
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peek();
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.tokens.get(self.pos) == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(k)) if k == keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        match self.eat_punct(punct) {
+            true => Ok(()),
+            false => Err(format!("expected `{punct}`, found {}", self.describe_next()).into()),
+        }
+    }
+
+    fn describe_next(&mut self) -> String {
+        match self.peek() {
+            Some(Token::Number(n)) => format!("`{n}`"),
+            Some(Token::Str(s)) => format!("\"{s}\""),
+            Some(Token::Template(_)) => "template literal".to_owned(),
+            Some(Token::Ident(i)) => format!("`{i}`"),
+            Some(Token::Punct(p)) => format!("`{p}`"),
+            Some(Token::Newline) => "new line".to_owned(),
+            None => "end of input".to_owned(),
+        }
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.nesting += 1;
+        let result = f(self);
+        self.nesting -= 1;
+        result
+    }
+
+    fn deeper<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err("template is nested too deeply".into());
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Statements until the end of input, or until the closing `}` if `block`.
+    fn statements(&mut self, block: bool) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+        loop {
+            while matches!(self.peek(), Some(Token::Newline | Token::Punct(";"))) {
+                self.pos += 1;
+            }
+            match self.peek() {
+                None if block => return Err("expected `}`, found end of input".into()),
+                None => return Ok(statements),
+                Some(Token::Punct("}")) if block => {
+                    self.pos += 1;
+                    return Ok(statements);
+                }
+                _ => {}
+            }
+            statements.push(self.statement()?);
+            match self.peek() {
+                None | Some(Token::Newline | Token::Punct(";")) => {}
+                Some(Token::Punct("}")) if block => {}
+                Some(_) => {
+                    return Err(format!("unexpected {}", self.describe_next()).into());
+                }
+            }
+        }
+    }
+
+    /// Body of an arrow function, new lines separate statements again.
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        let nesting = std::mem::take(&mut self.nesting);
+        let statements = self.statements(true);
+        self.nesting = nesting;
+        statements
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        if self.eat_keyword("return") {
+            if matches!(
+                self.peek(),
+                None | Some(Token::Newline | Token::Punct(";" | "}"))
+            ) {
+                return Ok(Statement::Return(Expr::Literal(Value::Null)));
+            }
+            return Ok(Statement::Return(self.expression()?));
+        }
+        let declaration =
+            self.eat_keyword("const") || self.eat_keyword("let") || self.eat_keyword("var");
+        if let (Some(Token::Ident(name)), Some(Token::Punct("="))) =
+            (self.peek_at(0), self.peek_at(1))
+        {
+            let name = name.clone();
+            self.pos += 2;
+            self.skip_newlines();
+            return Ok(Statement::Assign(name, self.expression()?));
+        }
+        if declaration {
+            return Err("expected a variable declaration".into());
+        }
+        Ok(Statement::Expr(self.expression()?))
+    }
+
+    /// Parameters of the arrow function starting at the current token, if any.
+    fn arrow_params(&mut self) -> Option<Vec<String>> {
+        self.peek();
+        let mut params = Vec::new();
+        let mut offset = 1;
+        match self.peek_at(0)? {
+            Token::Ident(name) => params.push(name.clone()),
+            Token::Punct("(") => loop {
+                match self.peek_at(offset)? {
+                    Token::Punct(")") => {
+                        offset += 1;
+                        break;
+                    }
+                    Token::Ident(name) => params.push(name.clone()),
+                    Token::Punct(",") | Token::Newline => {}
+                    _ => return None,
+                }
+                offset += 1;
+            },
+            _ => return None,
+        }
+        if self.peek_at(offset) != Some(&Token::Punct("=>")) {
+            return None;
+        }
+        self.pos += offset + 1;
+        Some(params)
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        self.deeper(Self::arrow_or_conditional)
+    }
+
+    fn arrow_or_conditional(&mut self) -> Result<Expr> {
+        if let Some(params) = self.arrow_params() {
+            self.skip_newlines();
+            let body = match self.eat_punct("{") {
+                true => self.block()?,
+                false => vec![Statement::Return(self.expression()?)],
+            };
+            return Ok(Expr::Arrow { params, body });
+        }
+        let test = self.binary(0)?;
+        if !self.eat_punct("?") {
+            return Ok(test);
+        }
+        self.skip_newlines();
+        let then = self.nested(|p| p.expression())?;
+        self.skip_newlines();
+        self.expect_punct(":")?;
+        self.skip_newlines();
+        let otherwise = self.expression()?;
+        Ok(Expr::Conditional {
+            test: Box::new(test),
+            then: Box::new(then),
+            otherwise: Box::new(otherwise),
+        })
+    }
+
+    /// Binary operators by precedence, from lowest to highest.
+    const BINARY_LEVELS: &'static [&'static [&'static str]] = &[
+        &["??"],
+        &["||"],
+        &["&&"],
+        &["|"],
+        &["^"],
+        &["&"],
+        &["==", "!=", "===", "!=="],
+        &["<", ">", "<=", ">="],
+        &["+", "-"],
+        &["*", "/", "%"],
+    ];
+
+    fn binary_operator(&mut self, level: usize) -> Option<&'static str> {
+        let ops = Self::BINARY_LEVELS[level];
+        let op = match self.peek()? {
+            Token::Punct(p) => ops.iter().find(|op| *op == p)?,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level == Self::BINARY_LEVELS.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.binary_operator(level) {
+            self.skip_newlines();
+            let right = self.binary(level + 1)?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let op = if self.eat_punct("-") {
+            "-"
+        } else if self.eat_punct("+") {
+            "+"
+        } else if self.eat_punct("!") {
+            "!"
+        } else {
+            return self.power();
+        };
+        Ok(Expr::Unary {
+            op,
+            expr: Box::new(self.deeper(Self::unary)?),
+        })
+    }
+
+    fn power(&mut self) -> Result<Expr> {
+        let base = self.postfix()?;
+        if self.eat_punct("**") {
+            self.skip_newlines();
+            return Ok(Expr::Binary {
+                op: "**",
+                left: Box::new(base),
+                right: Box::new(self.deeper(Self::unary)?),
+            });
+        }
+        Ok(base)
+    }
+
+    fn property_name(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(Expr::Literal(Value::String(name))),
+            _ => Err("expected a property name".into()),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat_punct(".") {
+                expr = Expr::Member {
+                    object: Box::new(expr),
+                    property: Box::new(self.property_name()?),
+                    optional: false,
+                };
+            } else if self.eat_punct("?.") {
+                let property = match self.eat_punct("[") {
+                    true => self.nested(|p| {
+                        let property = p.expression()?;
+                        p.expect_punct("]")?;
+                        Ok(property)
+                    })?,
+                    false => self.property_name()?,
+                };
+                expr = Expr::Member {
+                    object: Box::new(expr),
+                    property: Box::new(property),
+                    optional: true,
+                };
+            } else if self.eat_punct("[") {
+                let property = self.nested(|p| {
+                    let property = p.expression()?;
+                    p.expect_punct("]")?;
+                    Ok(property)
+                })?;
+                expr = Expr::Member {
+                    object: Box::new(expr),
+                    property: Box::new(property),
+                    optional: false,
+                };
+            } else if self.eat_punct("(") {
+                let args = self.nested(|p| p.list(")"))?;
+                expr = Expr::Call {
+                    callee: Box::new(expr),
+                    args,
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Comma separated expressions until `close`, trailing commas are allowed.
+    fn list(&mut self, close: &str) -> Result<Vec<Expr>> {
+        let mut items = Vec::new();
+        while !self.eat_punct(close) {
+            items.push(self.expression()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn object(&mut self) -> Result<Expr> {
+        let mut entries = Vec::new();
+        while !self.eat_punct("}") {
+            let key = match self.next() {
+                Some(Token::Ident(key) | Token::Str(key)) => key,
+                Some(Token::Number(n)) => display(&number(n)),
+                _ => return Err("expected an object key".into()),
+            };
+            let value = match self.eat_punct(":") {
+                true => self.expression()?,
+                // shorthand `{ name }`
+                false => Expr::Ident(key.clone()),
+            };
+            entries.push((key, value));
+            if !self.eat_punct(",") {
+                self.expect_punct("}")?;
+                break;
+            }
+        }
+        Ok(Expr::Object(entries))
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.next().ok_or("unexpected end of input")?;
+        Ok(match token {
+            Token::Number(n) => Expr::Literal(number(n)),
+            Token::Str(s) => Expr::Literal(Value::String(s)),
+            Token::Template(chunks) => {
+                let mut parts = Vec::new();
+                for chunk in chunks {
+                    parts.push(match chunk {
+                        TemplateChunk::Text(text) => Expr::Literal(Value::String(text)),
+                        TemplateChunk::Code(code) => {
+                            let mut parser = Parser::new(&code)?;
+                            parser.nesting = 1;
+                            parser.depth = self.depth;
+                            let expr = parser.expression()?;
+                            if parser.peek().is_some() {
+                                let next = parser.describe_next();
+                                return Err(format!("unexpected {next} on template literal").into());
+                            }
+                            expr
+                        }
+                    });
+                }
+                Expr::TemplateLiteral(parts)
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" | "undefined" => Expr::Literal(Value::Null),
+                _ => Expr::Ident(ident),
+            },
+            Token::Punct("(") => self.nested(|p| {
+                let expr = p.expression()?;
+                p.expect_punct(")")?;
+                Ok(expr)
+            })?,
+            Token::Punct("[") => Expr::Array(self.nested(|p| p.list("]"))?),
+            Token::Punct("{") => self.nested(|p| p.object())?,
+            Token::Punct(p) => return Err(format!("unexpected `{p}`").into()),
+            Token::Newline => return Err("unexpected new line".into()),
+        })
+    }
+}
+
+/// Values available while evaluating a template, see the scope documented on each toolbar item.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateScope {
+    variables: Map<String, Value>,
+    translations: Value,
+}
+
+impl TemplateScope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: Value) {
+        self.variables.insert(name.into(), value);
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Translations used by `t(path)`, paths are dot separated keys on this object.
+    pub fn with_translations(mut self, translations: Value) -> Self {
+        self.translations = translations;
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    fn translate(&self, path: &str) -> String {
+        let mut current = &self.translations;
+        for key in path.split('.') {
+            match current.get(key) {
+                Some(next) => current = next,
+                None => return path.to_owned(),
+            }
+        }
+        match current {
+            Value::String(text) => text.clone(),
+            _ => path.to_owned(),
+        }
+    }
+}
+
+/// Parsed toolbar template, follows a subset of the javascript run by the ui sandbox:
+/// literals, template literals, arrays, objects, member access, function calls, arrow
+/// functions, arithmetic, bitwise, comparison, logical and ternary operators, plus
+/// variable declarations and `return`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    statements: Vec<Statement>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self> {
+        Ok(Self {
+            statements: Parser::new(source)?.statements(false)?,
+        })
+    }
+
+    /// Identifiers read from the scope, excluding the ones declared by the template itself.
+    pub fn identifiers(&self) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        collect_statements(&self.statements, &mut HashSet::new(), &mut found);
+        found
+    }
+
+    /// Returns the value of the `return` statement, `null` if there is none like the sandbox.
+    pub fn evaluate(&self, scope: &TemplateScope) -> Result<Value> {
+        let mut evaluator = Evaluator {
+            scope,
+            locals: Map::new(),
+        };
+        evaluator.run(&self.statements)
+    }
+}
+
+fn collect_statements(
+    statements: &[Statement],
+    assigned: &mut HashSet<String>,
+    found: &mut BTreeSet<String>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Expr(expr) | Statement::Return(expr) => {
+                collect_identifiers(expr, assigned, found)
+            }
+            Statement::Assign(name, expr) => {
+                collect_identifiers(expr, assigned, found);
+                assigned.insert(name.clone());
+            }
+        }
+    }
+}
+
+fn collect_identifiers(expr: &Expr, assigned: &HashSet<String>, found: &mut BTreeSet<String>) {
+    match expr {
+        Expr::Literal(_) => {}
+        Expr::Ident(name) => {
+            if !assigned.contains(name) {
+                found.insert(name.clone());
+            }
+        }
+        Expr::Array(items) | Expr::TemplateLiteral(items) => {
+            for item in items {
+                collect_identifiers(item, assigned, found);
+            }
+        }
+        Expr::Object(entries) => {
+            for (_, value) in entries {
+                collect_identifiers(value, assigned, found);
+            }
+        }
+        Expr::Member {
+            object, property, ..
+        } => {
+            collect_identifiers(object, assigned, found);
+            collect_identifiers(property, assigned, found);
+        }
+        Expr::Call { callee, args } => {
+            collect_identifiers(callee, assigned, found);
+            for arg in args {
+                collect_identifiers(arg, assigned, found);
+            }
+        }
+        Expr::Unary { expr, .. } => collect_identifiers(expr, assigned, found),
+        Expr::Binary { left, right, .. } => {
+            collect_identifiers(left, assigned, found);
+            collect_identifiers(right, assigned, found);
+        }
+        Expr::Conditional {
+            test,
+            then,
+            otherwise,
+        } => {
+            collect_identifiers(test, assigned, found);
+            collect_identifiers(then, assigned, found);
+            collect_identifiers(otherwise, assigned, found);
+        }
+        Expr::Arrow { params, body } => {
+            let mut assigned = assigned.clone();
+            assigned.extend(params.iter().cloned());
+            collect_statements(body, &mut assigned, found);
+        }
+    }
+}
+
+/// Numbers without decimals are kept as integers, so they display as `3` instead of `3.0`
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9e15 {
+        return Value::from(n as i64);
+    }
+    serde_json::Number::from_f64(n)
+        .map(Value::Number)
+        .unwrap_or(Value::Null)
+}
+
+fn to_number(value: &Value) -> f64 {
+    match value {
+        Value::Null => 0.0,
+        Value::Bool(b) => *b as u8 as f64,
+        Value::Number(n) => n.as_f64().unwrap_or(f64::NAN),
+        Value::String(s) if s.trim().is_empty() => 0.0,
+        Value::String(s) => s.trim().parse().unwrap_or(f64::NAN),
+        Value::Array(_) | Value::Object(_) => f64::NAN,
+    }
+}
+
+/// Javascript `ToInt32`, used by the bitwise operators
+fn to_int32(value: &Value) -> i32 {
+    let n = to_number(value);
+    if !n.is_finite() {
+        return 0;
+    }
+    n.trunc().rem_euclid(4_294_967_296.0) as u32 as i32
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0 && !n.is_nan()),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+/// String conversion following javascript rules
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(display).collect::<Vec<_>>().join(","),
+        Value::Object(_) => "[object Object]".to_owned(),
+    }
+}
+
+fn loose_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => to_number(a) == to_number(b),
+        _ => a == b,
+    }
+}
+
+fn component(kind: &str, props: Value) -> Result<Value> {
+    let Value::Object(mut props) = props else {
+        return Err(format!("{kind} expects an object").into());
+    };
+    props.insert("@component".to_owned(), Value::String(kind.to_owned()));
+    Ok(Value::Object(props))
+}
+
+fn math(name: &str, args: &[Value]) -> Result<Value> {
+    let arg = |i: usize| args.get(i).map(to_number).unwrap_or(f64::NAN);
+    let numbers = || args.iter().map(to_number);
+    Ok(number(match name {
+        // javascript rounds half values up, also on negative numbers
+        "round" => (arg(0) + 0.5).floor(),
+        "floor" => arg(0).floor(),
+        "ceil" => arg(0).ceil(),
+        "abs" => arg(0).abs(),
+        "trunc" => arg(0).trunc(),
+        "sqrt" => arg(0).sqrt(),
+        "sign" => match arg(0) {
+            n if n == 0.0 || n.is_nan() => n,
+            n => n.signum(),
+        },
+        "pow" => arg(0).powf(arg(1)),
+        "min" => numbers().fold(f64::INFINITY, f64::min),
+        "max" => numbers().fold(f64::NEG_INFINITY, f64::max),
+        _ => return Err(format!("`Math.{name}` is not supported by the evaluator").into()),
+    }))
+}
+
+struct Evaluator<'a> {
+    scope: &'a TemplateScope,
+    locals: Map<String, Value>,
+}
+
+impl Evaluator<'_> {
+    fn is_defined(&self, name: &str) -> bool {
+        self.locals.contains_key(name) || self.scope.get(name).is_some()
+    }
+
+    fn lookup(&self, name: &str) -> Result<Value> {
+        if let Some(value) = self.locals.get(name).or_else(|| self.scope.get(name)) {
+            return Ok(value.clone());
+        }
+        Ok(match name {
+            "undefined" => Value::Null,
+            "NaN" => number(f64::NAN),
+            "Infinity" => number(f64::INFINITY),
+            _ if TEMPLATE_GLOBALS.contains(&name) => {
+                return Err(format!("`{name}` is not supported by the evaluator").into());
+            }
+            _ => return Err(format!("`{name}` is not defined").into()),
+        })
+    }
+
+    fn run(&mut self, statements: &[Statement]) -> Result<Value> {
+        for statement in statements {
+            match statement {
+                Statement::Expr(expr) => {
+                    self.eval(expr)?;
+                }
+                Statement::Assign(name, expr) => {
+                    let value = self.eval(expr)?;
+                    self.locals.insert(name.clone(), value);
+                }
+                Statement::Return(expr) => return self.eval(expr),
+            }
+        }
+        Ok(Value::Null)
+    }
+
+    /// Calls an arrow function, its variables are dropped after the call.
+    fn apply(&mut self, params: &[String], args: &[Value], body: &[Statement]) -> Result<Value> {
+        let saved = self.locals.clone();
+        for (i, param) in params.iter().enumerate() {
+            let value = args.get(i).cloned().unwrap_or(Value::Null);
+            self.locals.insert(param.clone(), value);
+        }
+        let result = self.run(body);
+        self.locals = saved;
+        result
+    }
+
+    /// Array methods receiving a callback, `None` if the method is not one of them.
+    fn iterate(
+        &mut self,
+        items: &[Value],
+        method: &str,
+        params: &[String],
+        body: &[Statement],
+    ) -> Result<Option<Value>> {
+        let mut results = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let args = [item.clone(), Value::from(i), Value::Array(items.to_vec())];
+            let result = self.apply(params, &args, body)?;
+            match method {
+                "find" if is_truthy(&result) => return Ok(Some(item.clone())),
+                "findIndex" if is_truthy(&result) => return Ok(Some(Value::from(i))),
+                "some" if is_truthy(&result) => return Ok(Some(Value::Bool(true))),
+                "every" if !is_truthy(&result) => return Ok(Some(Value::Bool(false))),
+                "filter" if is_truthy(&result) => results.push(item.clone()),
+                "map" => results.push(result),
+                _ => {}
+            }
+        }
+        Ok(Some(match method {
+            "map" | "filter" => Value::Array(results),
+            "find" => Value::Null,
+            "findIndex" => Value::from(-1),
+            "some" => Value::Bool(false),
+            "every" => Value::Bool(true),
+            _ => return Ok(None),
+        }))
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        Ok(match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Ident(name) => self.lookup(name)?,
+            Expr::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.eval(item))
+                    .collect::<Result<_>>()?,
+            ),
+            Expr::Object(entries) => {
+                let mut object = Map::new();
+                for (key, value) in entries {
+                    object.insert(key.clone(), self.eval(value)?);
+                }
+                Value::Object(object)
+            }
+            Expr::TemplateLiteral(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&display(&self.eval(part)?));
+                }
+                Value::String(text)
+            }
+            Expr::Member {
+                object,
+                property,
+                optional,
+            } => {
+                if matches!(object.as_ref(), Expr::Ident(name) if name == "Math" && !self.is_defined(name))
+                {
+                    return match display(&self.eval(property)?).as_str() {
+                        "PI" => Ok(number(std::f64::consts::PI)),
+                        "E" => Ok(number(std::f64::consts::E)),
+                        name => {
+                            Err(format!("`Math.{name}` is not supported by the evaluator").into())
+                        }
+                    };
+                }
+                let object = self.eval(object)?;
+                if object.is_null() && *optional {
+                    return Ok(Value::Null);
+                }
+                let property = self.eval(property)?;
+                member(&object, &property)?
+            }
+            Expr::Call { callee, args } => match callee.as_ref() {
+                Expr::Ident(name) => {
+                    if self.is_defined(name) {
+                        return Err(format!("`{name}` is not a function").into());
+                    }
+                    let args = self.eval_args(args)?;
+                    self.call(name, args)?
+                }
+                Expr::Member {
+                    object,
+                    property,
+                    optional,
+                } => {
+                    let method = display(&self.eval(property)?);
+                    if matches!(object.as_ref(), Expr::Ident(name) if name == "Math" && !self.is_defined(name))
+                    {
+                        return math(&method, &self.eval_args(args)?);
+                    }
+                    let object = self.eval(object)?;
+                    if object.is_null() && *optional {
+                        return Ok(Value::Null);
+                    }
+                    if let (Value::Array(items), Some(Expr::Arrow { params, body })) =
+                        (&object, args.first())
+                    {
+                        if let Some(result) = self.iterate(items, &method, params, body)? {
+                            return Ok(result);
+                        }
+                    }
+                    call_method(&object, &method, &self.eval_args(args)?)?
+                }
+                _ => return Err("expression is not a function".into()),
+            },
+            Expr::Unary { op, expr } => {
+                let value = self.eval(expr)?;
+                match *op {
+                    "-" => number(-to_number(&value)),
+                    "+" => number(to_number(&value)),
+                    _ => Value::Bool(!is_truthy(&value)),
+                }
+            }
+            Expr::Binary { op, left, right } => {
+                let left = self.eval(left)?;
+                // short circuit
+                match *op {
+                    "&&" if !is_truthy(&left) => return Ok(left),
+                    "||" if is_truthy(&left) => return Ok(left),
+                    "??" if !left.is_null() => return Ok(left),
+                    "&&" | "||" | "??" => return self.eval(right),
+                    _ => {}
+                }
+                let right = self.eval(right)?;
+                binary(op, &left, &right)
+            }
+            Expr::Conditional {
+                test,
+                then,
+                otherwise,
+            } => match is_truthy(&self.eval(test)?) {
+                true => self.eval(then)?,
+                false => self.eval(otherwise)?,
+            },
+            Expr::Arrow { .. } => {
+                return Err("functions can only be passed to array methods".into());
+            }
+        })
+    }
+
+    fn eval_args(&mut self, args: &[Expr]) -> Result<Vec<Value>> {
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+
+    fn call(&self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+        Ok(match name {
+            "t" => Value::String(self.scope.translate(&display(&arg(0)))),
+            // side effect handled by the ui, there is nothing to render
+            "trigger" => Value::Null,
+            "icon" => {
+                let mut props = Map::new();
+                props.insert("name".to_owned(), arg(0));
+                if let Some(size) = args.get(1) {
+                    props.insert("size".to_owned(), size.clone());
+                }
+                component("Icon", Value::Object(props))?
+            }
+            "Icon" | "AppIcon" | "Image" | "Button" | "Group" => component(name, arg(0))?,
+            "String" => Value::String(display(&arg(0))),
+            "Number" => number(to_number(&arg(0))),
+            "Boolean" => Value::Bool(is_truthy(&arg(0))),
+            "isNaN" => Value::Bool(to_number(&arg(0)).is_nan()),
+            _ if ACTION_HELPERS.contains(&name) => {
+                return Err(format!("`{name}` can only be used on actions").into());
+            }
+            _ if TEMPLATE_GLOBALS.contains(&name) => {
+                return Err(format!("`{name}` is not supported by the evaluator").into());
+            }
+            _ => return Err(format!("`{name}` is not defined").into()),
+        })
+    }
+}
+
+/// Position on an array or string, only finite non-negative integers are valid.
+fn to_index(value: &Value) -> Option<usize> {
+    let n = to_number(value);
+    (n.is_finite() && n >= 0.0 && n.fract() == 0.0).then_some(n as usize)
+}
+
+fn member(object: &Value, property: &Value) -> Result<Value> {
+    Ok(match (object, property) {
+        (Value::Null, _) => {
+            return Err(format!("cannot read `{}` of null", display(property)).into());
+        }
+        (Value::Object(map), key) => map.get(&display(key)).cloned().unwrap_or(Value::Null),
+        (Value::Array(items), Value::String(key)) if key == "length" => Value::from(items.len()),
+        (Value::Array(items), index) => to_index(index)
+            .and_then(|i| items.get(i))
+            .cloned()
+            .unwrap_or(Value::Null),
+        (Value::String(s), Value::String(key)) if key == "length" => Value::from(s.chars().count()),
+        (Value::String(s), index) => to_index(index)
+            .and_then(|i| s.chars().nth(i))
+            .map(|c| Value::String(c.to_string()))
+            .unwrap_or(Value::Null),
+        _ => Value::Null,
+    })
+}
+
+fn call_method(object: &Value, method: &str, args: &[Value]) -> Result<Value> {
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Value::Null);
+    Ok(match (object, method) {
+        (Value::Array(items), "join") => {
+            let separator = match arg(0) {
+                Value::Null => ",".to_owned(),
+                sep => display(&sep),
+            };
+            Value::String(
+                items
+                    .iter()
+                    .map(display)
+                    .collect::<Vec<_>>()
+                    .join(&separator),
+            )
+        }
+        (Value::Array(items), "includes") => {
+            Value::Bool(items.iter().any(|i| loose_equals(i, &arg(0))))
+        }
+        (Value::String(s), "includes") => Value::Bool(s.contains(&display(&arg(0)))),
+        (Value::String(s), "toUpperCase") => Value::String(s.to_uppercase()),
+        (Value::String(s), "toLowerCase") => Value::String(s.to_lowercase()),
+        (Value::String(s), "trim") => Value::String(s.trim().to_owned()),
+        (Value::Number(n), "toFixed") => {
+            let digits = to_number(&arg(0));
+            if !(0.0..=100.0).contains(&digits) {
+                return Err("toFixed() digits argument must be between 0 and 100".into());
+            }
+            let digits = digits as usize;
+            Value::String(format!("{:.*}", digits, n.as_f64().unwrap_or_default()))
+        }
+        (value, "toString") => Value::String(display(value)),
+        _ => return Err(format!("`{method}` is not a function").into()),
+    })
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Value {
+    let (a, b) = (to_number(left), to_number(right));
+    match op {
+        "+" if left.is_string() || right.is_string() => {
+            Value::String(format!("{}{}", display(left), display(right)))
+        }
+        "+" => number(a + b),
+        "-" => number(a - b),
+        "*" => number(a * b),
+        "/" => number(a / b),
+        "%" => number(a % b),
+        "**" => number(a.powf(b)),
+        "^" => number((to_int32(left) ^ to_int32(right)) as f64),
+        "&" => number((to_int32(left) & to_int32(right)) as f64),
+        "|" => number((to_int32(left) | to_int32(right)) as f64),
+        "==" | "===" => Value::Bool(loose_equals(left, right)),
+        "!=" | "!==" => Value::Bool(!loose_equals(left, right)),
+        _ => {
+            let ordering = match (left, right) {
+                (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+                _ => a.partial_cmp(&b),
+            };
+            Value::Bool(match (op, ordering) {
+                (_, None) => false,
+                ("<", Some(o)) => o.is_lt(),
+                (">", Some(o)) => o.is_gt(),
+                ("<=", Some(o)) => o.is_le(),
+                (_, Some(o)) => o.is_ge(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_evaluate_templates_over_the_scope() -> Result<()> {
+        let scope = TemplateScope::new()
+            .with("window", json!({ "title": "Seelen", "exe": null }))
+            .with("power", json!({ "batteryLifePercent": 42.5 }))
+            .with("items", json!([1, 2, 3]))
+            .with_translations(json!({ "power": { "charging": "Charging" } }));
+
+        let eval = |source: &str| Template::parse(source)?.evaluate(&scope);
+        assert_eq!(
+            eval("return window.title + ' - ' + (window.exe ?? 'none')")?,
+            json!("Seelen - none")
+        );
+        assert_eq!(
+            eval("return Math.round(power.batteryLifePercent) + '%'")?,
+            json!("43%")
+        );
+        assert_eq!(
+            eval("return power.batteryLifePercent > 20 && !false ? t('power.charging') : 'low'")?,
+            json!("Charging")
+        );
+        assert_eq!(eval("return t('missing.key')")?, json!("missing.key"));
+        assert_eq!(eval("const x = 2 ** 3; return x * 2")?, json!(16));
+        assert_eq!(eval("return [6 ^ 3, 6 & 3, 6 | 3]")?, json!([5, 2, 7]));
+        assert_eq!(eval("return window?.nothing?.deep")?, json!(null));
+        assert_eq!(
+            eval("return `${window.title}: ${items.map((n) => n * 2).join(', ')}`")?,
+            json!("Seelen: 2, 4, 6")
+        );
+        assert_eq!(
+            eval("return items.filter(n => {\n  const odd = n % 2\n  return odd\n})")?,
+            json!([1, 3])
+        );
+        assert_eq!(
+            eval("return [icon('PiBattery'), ['a', 'b'].join('-')]")?,
+            json!([{ "@component": "Icon", "name": "PiBattery" }, "a-b"])
+        );
+        // like the sandbox, nothing is rendered without a return
+        assert_eq!(eval("window.title")?, json!(null));
+        assert!(eval("return window.nothing.deep").is_err());
+        // only finite non-negative integers are indexes
+        assert_eq!(
+            eval("return [items[-1], items[1.5], items['foo'], items['1'], window.title[-1]]")?,
+            json!([null, null, null, 2, null])
+        );
+        assert_eq!(eval("return (2.5).toFixed(2)")?, json!("2.50"));
+        assert!(eval("return (1).toFixed(101)").is_err());
+        assert!(eval("invoke('x')").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_collect_free_identifiers() -> Result<()> {
+        let template = Template::parse(
+            "const count = items.length\nreturn count > 0 ? t(label) : items.map((i) => i + icon)",
+        )?;
+        let identifiers: Vec<_> = template.identifiers().into_iter().collect();
+        assert_eq!(identifiers, ["icon", "items", "label", "t"]);
+
+        assert!(Template::parse("a +").is_err());
+        assert!(Template::parse("(a").is_err());
+        assert!(Template::parse("a b").is_err());
+        assert!(Template::parse("a and b").is_err());
+
+        // deep nesting is rejected instead of overflowing the stack
+        let nested = |open: &str, close: &str| {
+            Template::parse(&format!("{}1{}", open.repeat(10_000), close.repeat(10_000)))
+        };
+        let err = nested("(", ")").unwrap_err();
+        assert!(err.to_string().contains("nested too deeply"), "{err}");
+        assert!(nested("[", "]").is_err());
+        assert!(nested("-", "").is_err());
+        assert!(nested("2 ** ", "").is_err());
+        assert!(Template::parse(&format!("{}1{}", "(".repeat(20), ")".repeat(20))).is_ok());
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+mod patch;
+
+pub use patch::*;
+
 use std::collections::HashMap;
 
 use url::Url;
@@ -33,38 +37,136 @@ impl Default for SluPopupConfig {
 )]
 pub enum SluPopupContent {
     Text {
+        /// Optional id used to patch the element, should be unique on the popup.
+        id: Option<String>,
         value: String,
         styles: Option<CssStyles>,
     },
     Icon {
+        id: Option<String>,
         /// react icon name. ex: `FaGithub`
         name: String,
         styles: Option<CssStyles>,
     },
     Image {
+        id: Option<String>,
         href: Url,
         styles: Option<CssStyles>,
     },
     Button {
+        id: Option<String>,
         inner: Vec<SluPopupContent>,
         styles: Option<CssStyles>,
-        /// event name to be emitted on click ex: `test::clicked`
+        /// event name to be emitted on click ex: `test::clicked`,
+        /// the event payload will be a [`SluPopupResult`].
         on_click: String,
     },
     Group {
+        id: Option<String>,
         items: Vec<SluPopupContent>,
         styles: Option<CssStyles>,
     },
+    InputText {
+        id: Option<String>,
+        /// Key of the value on the popup result, should be unique on the popup.
+        key: String,
+        #[serde(default)]
+        value: String,
+        placeholder: Option<String>,
+        styles: Option<CssStyles>,
+    },
+    Checkbox {
+        id: Option<String>,
+        key: String,
+        #[serde(default)]
+        checked: bool,
+        label: Option<String>,
+        styles: Option<CssStyles>,
+    },
+    Select {
+        id: Option<String>,
+        key: String,
+        /// Should be the value of one of the options
+        value: Option<String>,
+        options: Vec<SluPopupSelectOption>,
+        styles: Option<CssStyles>,
+    },
+    ProgressBar {
+        id: Option<String>,
+        /// From 0 to 1, if not set the progress is indeterminate.
+        value: Option<f64>,
+        styles: Option<CssStyles>,
+    },
+    Divider {
+        id: Option<String>,
+        styles: Option<CssStyles>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+pub struct SluPopupSelectOption {
+    pub value: String,
+    pub label: String,
 }
 
 impl SluPopupContent {
     pub fn set_styles(&mut self, new_styles: CssStyles) {
+        *self.styles_mut() = Some(new_styles);
+    }
+
+    fn styles_mut(&mut self) -> &mut Option<CssStyles> {
+        match self {
+            SluPopupContent::Text { styles, .. }
+            | SluPopupContent::Icon { styles, .. }
+            | SluPopupContent::Image { styles, .. }
+            | SluPopupContent::Button { styles, .. }
+            | SluPopupContent::Group { styles, .. }
+            | SluPopupContent::InputText { styles, .. }
+            | SluPopupContent::Checkbox { styles, .. }
+            | SluPopupContent::Select { styles, .. }
+            | SluPopupContent::ProgressBar { styles, .. }
+            | SluPopupContent::Divider { styles, .. } => styles,
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            SluPopupContent::Text { id, .. }
+            | SluPopupContent::Icon { id, .. }
+            | SluPopupContent::Image { id, .. }
+            | SluPopupContent::Button { id, .. }
+            | SluPopupContent::Group { id, .. }
+            | SluPopupContent::InputText { id, .. }
+            | SluPopupContent::Checkbox { id, .. }
+            | SluPopupContent::Select { id, .. }
+            | SluPopupContent::ProgressBar { id, .. }
+            | SluPopupContent::Divider { id, .. } => id.as_deref(),
+        }
+    }
+
+    /// Key of the value hold by the element, only on inputs.
+    pub fn value_key(&self) -> Option<&str> {
+        match self {
+            SluPopupContent::InputText { key, .. }
+            | SluPopupContent::Checkbox { key, .. }
+            | SluPopupContent::Select { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    pub fn children(&self) -> &[SluPopupContent] {
+        match self {
+            SluPopupContent::Button { inner, .. } => inner,
+            SluPopupContent::Group { items, .. } => items,
+            _ => &[],
+        }
+    }
+
+    fn children_mut(&mut self) -> Option<&mut Vec<SluPopupContent>> {
         match self {
-            SluPopupContent::Text { styles, .. } => *styles = Some(new_styles),
-            SluPopupContent::Icon { styles, .. } => *styles = Some(new_styles),
-            SluPopupContent::Image { styles, .. } => *styles = Some(new_styles),
-            SluPopupContent::Button { styles, .. } => *styles = Some(new_styles),
-            SluPopupContent::Group { styles, .. } => *styles = Some(new_styles),
+            SluPopupContent::Button { inner, .. } => Some(inner),
+            SluPopupContent::Group { items, .. } => Some(items),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,378 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use ts_rs::TS;
+
+use crate::error::Result;
+
+use super::{CssStyles, SluPopupConfig, SluPopupContent};
+
+/// Value of an input of the popup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(untagged)]
+pub enum SluPopupValue {
+    Bool(bool),
+    Text(String),
+}
+
+/// Payload of the event emitted by the buttons
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct SluPopupResult {
+    /// Id of the clicked button, or its event name if the button has no id.
+    pub button: String,
+    /// Values of the inputs by key, selects without value are not included.
+    pub values: HashMap<String, SluPopupValue>,
+}
+
+/// Partial change of a popup, elements are found by id on any section.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SluPopupPatch {
+    Replace {
+        id: String,
+        content: SluPopupContent,
+    },
+    Remove {
+        id: String,
+    },
+    /// Inserts the content on a group or button, at the end if no index is specified.
+    Insert {
+        parent: String,
+        index: Option<usize>,
+        content: SluPopupContent,
+    },
+    SetText {
+        id: String,
+        value: String,
+    },
+    SetValue {
+        key: String,
+        value: SluPopupValue,
+    },
+    SetProgress {
+        id: String,
+        value: Option<f64>,
+    },
+    SetStyles {
+        id: String,
+        styles: CssStyles,
+    },
+    Resize {
+        width: f64,
+        height: f64,
+    },
+}
+
+/// Full replacement of the popup config or a list of patches applied in order.\
+/// Untagged so the `config` argument of `UpdatePopup` keeps accepting a full config.
+#[derive(Debug, Clone, Serialize, TS)]
+#[serde(untagged)]
+pub enum SluPopupUpdate {
+    Patches(Vec<SluPopupPatch>),
+    Config(SluPopupConfig),
+}
+
+impl<'de> Deserialize<'de> for SluPopupUpdate {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value =
+            serde_json::Value::deserialize(deserializer).map_err(serde::de::Error::custom)?;
+        if value.is_array() {
+            return serde_json::from_value(value)
+                .map(SluPopupUpdate::Patches)
+                .map_err(serde::de::Error::custom);
+        }
+        // any object is a valid config as all its fields have defaults
+        if value.get("op").is_some() {
+            return Err(serde::de::Error::custom(
+                "expected a list of patches or a popup config, found a single patch",
+            ));
+        }
+        serde_json::from_value(value)
+            .map(SluPopupUpdate::Config)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn walk<'a>(items: &'a [SluPopupContent], f: &mut impl FnMut(&'a SluPopupContent)) {
+    for item in items {
+        f(item);
+        walk(item.children(), f);
+    }
+}
+
+fn find_mut<'a>(
+    items: &'a mut [SluPopupContent],
+    predicate: &impl Fn(&SluPopupContent) -> bool,
+) -> Option<&'a mut SluPopupContent> {
+    for item in items {
+        if predicate(item) {
+            return Some(item);
+        }
+        if let Some(children) = item.children_mut() {
+            if let Some(found) = find_mut(children, predicate) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+fn remove_by_id(items: &mut Vec<SluPopupContent>, id: &str) -> Option<SluPopupContent> {
+    if let Some(index) = items.iter().position(|item| item.id() == Some(id)) {
+        return Some(items.remove(index));
+    }
+    items
+        .iter_mut()
+        .filter_map(|item| item.children_mut())
+        .find_map(|children| remove_by_id(children, id))
+}
+
+impl SluPopupConfig {
+    fn sections(&self) -> [&Vec<SluPopupContent>; 3] {
+        [&self.title, &self.content, &self.footer]
+    }
+
+    fn sections_mut(&mut self) -> [&mut Vec<SluPopupContent>; 3] {
+        [&mut self.title, &mut self.content, &mut self.footer]
+    }
+
+    fn walk<'a>(&'a self, mut f: impl FnMut(&'a SluPopupContent)) {
+        for section in self.sections() {
+            walk(section, &mut f);
+        }
+    }
+
+    fn find_mut(
+        &mut self,
+        predicate: impl Fn(&SluPopupContent) -> bool,
+    ) -> Option<&mut SluPopupContent> {
+        self.sections_mut()
+            .into_iter()
+            .find_map(|section| find_mut(section, &predicate))
+    }
+
+    fn element_mut(&mut self, id: &str) -> Result<&mut SluPopupContent> {
+        self.find_mut(|item| item.id() == Some(id))
+            .ok_or_else(|| format!("element `{id}` not found").into())
+    }
+
+    /// Ids and value keys should be unique, and the values valid for its element.
+    pub fn validate(&self) -> Result<()> {
+        let mut ids = HashSet::new();
+        let mut keys = HashSet::new();
+        let mut error = None;
+        self.walk(|item| {
+            if let Some(id) = item.id() {
+                if !ids.insert(id) {
+                    error.get_or_insert(format!("duplicated element id `{id}`"));
+                }
+            }
+            if let Some(key) = item.value_key() {
+                if !keys.insert(key) {
+                    error.get_or_insert(format!("duplicated value key `{key}`"));
+                }
+            }
+            match item {
+                SluPopupContent::Select {
+                    key,
+                    value: Some(value),
+                    options,
+                    ..
+                } if !options.iter().any(|o| &o.value == value) => {
+                    error.get_or_insert(format!("`{value}` is not an option of `{key}`"));
+                }
+                SluPopupContent::ProgressBar { value: Some(v), .. } if !(0.0..=1.0).contains(v) => {
+                    error.get_or_insert(format!("progress {v} is out of 0..1"));
+                }
+                _ => {}
+            }
+        });
+        match error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn values(&self) -> HashMap<String, SluPopupValue> {
+        let mut values = HashMap::new();
+        self.walk(|item| {
+            let value = match item {
+                SluPopupContent::InputText { value, .. } => SluPopupValue::Text(value.clone()),
+                SluPopupContent::Checkbox { checked, .. } => SluPopupValue::Bool(*checked),
+                SluPopupContent::Select {
+                    value: Some(value), ..
+                } => SluPopupValue::Text(value.clone()),
+                _ => return,
+            };
+            if let Some(key) = item.value_key() {
+                values.insert(key.to_owned(), value);
+            }
+        });
+        values
+    }
+
+    /// Builds the result of clicking the button with the given id or event name.
+    pub fn result(&self, button: &str) -> Result<SluPopupResult> {
+        let mut exists = false;
+        self.walk(|item| {
+            if let SluPopupContent::Button { id, on_click, .. } = item {
+                exists |= id.as_deref() == Some(button) || on_click == button;
+            }
+        });
+        if !exists {
+            return Err(format!("button `{button}` not found").into());
+        }
+        Ok(SluPopupResult {
+            button: button.to_owned(),
+            values: self.values(),
+        })
+    }
+
+    fn apply_patch(&mut self, patch: SluPopupPatch) -> Result<()> {
+        match patch {
+            SluPopupPatch::Replace { id, content } => *self.element_mut(&id)? = content,
+            SluPopupPatch::Remove { id } => {
+                self.sections_mut()
+                    .into_iter()
+                    .find_map(|section| remove_by_id(section, &id))
+                    .ok_or(format!("element `{id}` not found"))?;
+            }
+            SluPopupPatch::Insert {
+                parent,
+                index,
+                content,
+            } => {
+                let children = self
+                    .element_mut(&parent)?
+                    .children_mut()
+                    .ok_or(format!("element `{parent}` can not have children"))?;
+                let index = index.unwrap_or(children.len()).min(children.len());
+                children.insert(index, content);
+            }
+            SluPopupPatch::SetText { id, value } => match self.element_mut(&id)? {
+                SluPopupContent::Text { value: text, .. } => *text = value,
+                _ => return Err(format!("element `{id}` is not a text").into()),
+            },
+            SluPopupPatch::SetValue { key, value } => {
+                let element = self
+                    .find_mut(|item| item.value_key() == Some(key.as_str()))
+                    .ok_or(format!("input `{key}` not found"))?;
+                match (element, value) {
+                    (SluPopupContent::InputText { value, .. }, SluPopupValue::Text(new)) => {
+                        *value = new
+                    }
+                    (SluPopupContent::Checkbox { checked, .. }, SluPopupValue::Bool(new)) => {
+                        *checked = new
+                    }
+                    (SluPopupContent::Select { value, .. }, SluPopupValue::Text(new)) => {
+                        *value = Some(new)
+                    }
+                    _ => return Err(format!("invalid value type for `{key}`").into()),
+                }
+            }
+            SluPopupPatch::SetProgress { id, value } => match self.element_mut(&id)? {
+                SluPopupContent::ProgressBar {
+                    value: progress, ..
+                } => *progress = value,
+                _ => return Err(format!("element `{id}` is not a progress bar").into()),
+            },
+            SluPopupPatch::SetStyles { id, styles } => self.element_mut(&id)?.set_styles(styles),
+            SluPopupPatch::Resize { width, height } => {
+                self.width = width;
+                self.height = height;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the update, on error the config is not modified.
+    pub fn apply_update(&mut self, update: SluPopupUpdate) -> Result<()> {
+        let next = match update {
+            SluPopupUpdate::Config(config) => config,
+            SluPopupUpdate::Patches(patches) => {
+                let mut next = self.clone();
+                for patch in patches {
+                    next.apply_patch(patch)?;
+                }
+                next
+            }
+        };
+        next.validate()?;
+        *self = next;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn should_patch_by_id_and_build_results() -> Result<()> {
+        let mut config: SluPopupConfig = serde_json::from_value(json!({
+            "title": [{ "type": "text", "id": "title", "value": "Delete files?" }],
+            "content": [
+                { "type": "group", "id": "form", "items": [
+                    { "type": "inputText", "key": "reason" },
+                    { "type": "checkbox", "key": "forever", "label": "Skip the recycle bin" },
+                    { "type": "select", "key": "scope", "value": "all", "options": [
+                        { "value": "all", "label": "All" },
+                        { "value": "selected", "label": "Selected" }
+                    ]}
+                ]},
+                { "type": "divider" },
+                { "type": "progressBar", "id": "progress" }
+            ],
+            "footer": [
+                { "type": "button", "id": "ok", "onClick": "plugin::confirm", "inner": [] },
+                { "type": "button", "onClick": "exit", "inner": [] }
+            ]
+        }))?;
+        config.validate()?;
+
+        let patches: SluPopupUpdate = serde_json::from_value(json!([
+            { "op": "setText", "id": "title", "value": "Delete 3 files?" },
+            { "op": "setValue", "key": "forever", "value": true },
+            { "op": "setValue", "key": "reason", "value": "cleanup" },
+            { "op": "insert", "parent": "form", "index": 0, "content": { "type": "text", "value": "Options" } },
+            { "op": "setProgress", "id": "progress", "value": 0.5 }
+        ]))?;
+        config.apply_update(patches)?;
+        assert_eq!(config.content[0].children().len(), 4);
+
+        let result = config.result("ok")?;
+        assert_eq!(result.values["forever"], SluPopupValue::Bool(true));
+        assert_eq!(
+            result.values["reason"],
+            SluPopupValue::Text("cleanup".into())
+        );
+        assert_eq!(result.values["scope"], SluPopupValue::Text("all".into()));
+        assert!(config.result("exit").is_ok());
+        assert!(config.result("missing").is_err());
+
+        // invalid patches leave the config untouched
+        let invalid = SluPopupUpdate::Patches(vec![
+            SluPopupPatch::Remove { id: "title".into() },
+            SluPopupPatch::SetValue {
+                key: "scope".into(),
+                value: SluPopupValue::Text("none".into()),
+            },
+        ]);
+        assert!(config.apply_update(invalid).is_err());
+        assert_eq!(config.title.len(), 1);
+
+        // a patch outside of a list is not taken as an empty config
+        let single = json!({ "op": "setText", "id": "title", "value": "Delete?" });
+        assert!(serde_json::from_value::<SluPopupUpdate>(single).is_err());
+        let full: SluPopupUpdate = serde_json::from_value(json!({ "width": 400.0 }))?;
+        assert!(matches!(full, SluPopupUpdate::Config(c) if c.width == 400.0));
+        Ok(())
+    }
+}
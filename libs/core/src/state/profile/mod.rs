@@ -0,0 +1,225 @@
+mod rules;
+
+pub use rules::*;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    error::Result,
+    resource::{IconPackId, PluginId, ThemeId},
+    state::{Settings, WegItems},
+};
+
+use super::{Placeholder, ToolbarTemplateIssue};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProfileSettings {
+    pub themes: Vec<ThemeId>,
+    pub icon_packs: Vec<IconPackId>,
+    /// Default layout of the window manager
+    pub wm_layout: Option<PluginId>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct Profile {
+    pub name: String,
+    pub toolbar_layout: Placeholder,
+    pub weg_items: WegItems,
+    pub settings: ProfileSettings,
+    /// The profile is activated automatically when any of these rules matches.
+    pub rules: Vec<ProfileRule>,
+}
+
+/// Changes on an ordered list of resources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileListDiff<T> {
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    /// Same items on a different order
+    pub reordered: bool,
+}
+
+impl<T: PartialEq + Clone> ProfileListDiff<T> {
+    fn between(current: &[T], next: &[T]) -> Self {
+        let added: Vec<T> = next
+            .iter()
+            .filter(|i| !current.contains(i))
+            .cloned()
+            .collect();
+        let removed: Vec<T> = current
+            .iter()
+            .filter(|i| !next.contains(i))
+            .cloned()
+            .collect();
+        let reordered = added.is_empty() && removed.is_empty() && current != next;
+        Self {
+            added,
+            removed,
+            reordered,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && !self.reordered
+    }
+}
+
+/// What changes when switching to a profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDiff {
+    pub profile: String,
+    pub toolbar_layout: bool,
+    pub weg_items: bool,
+    pub themes: ProfileListDiff<ThemeId>,
+    pub icon_packs: ProfileListDiff<IconPackId>,
+    /// Previous and new window manager layout
+    pub wm_layout: Option<(PluginId, PluginId)>,
+    /// Template issues found on the applied toolbar layout, not a change by itself.
+    pub toolbar_issues: Vec<ToolbarTemplateIssue>,
+}
+
+impl ProfileDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.toolbar_layout
+            && !self.weg_items
+            && self.themes.is_empty()
+            && self.icon_packs.is_empty()
+            && self.wm_layout.is_none()
+    }
+}
+
+impl Profile {
+    /// Creates a profile from the current state.
+    pub fn capture(
+        name: impl Into<String>,
+        settings: &Settings,
+        toolbar: &Placeholder,
+        weg: &WegItems,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            toolbar_layout: toolbar.clone(),
+            weg_items: weg.clone(),
+            settings: ProfileSettings {
+                themes: settings.active_themes.clone(),
+                icon_packs: settings.active_icon_packs.clone(),
+                wm_layout: Some(settings.by_widget.wm.default_layout.clone()),
+            },
+            rules: Vec::new(),
+        }
+    }
+
+    /// Updates the profile with the current state, keeping its name and rules.
+    pub fn update_from(&mut self, settings: &Settings, toolbar: &Placeholder, weg: &WegItems) {
+        let rules = std::mem::take(&mut self.rules);
+        *self = Self::capture(std::mem::take(&mut self.name), settings, toolbar, weg);
+        self.rules = rules;
+    }
+
+    pub fn diff(&self, settings: &Settings, toolbar: &Placeholder, weg: &WegItems) -> ProfileDiff {
+        let current_layout = &settings.by_widget.wm.default_layout;
+        ProfileDiff {
+            profile: self.name.clone(),
+            toolbar_layout: &self.toolbar_layout != toolbar,
+            weg_items: &self.weg_items != weg,
+            themes: ProfileListDiff::between(&settings.active_themes, &self.settings.themes),
+            icon_packs: ProfileListDiff::between(
+                &settings.active_icon_packs,
+                &self.settings.icon_packs,
+            ),
+            wm_layout: self
+                .settings
+                .wm_layout
+                .as_ref()
+                .filter(|layout| *layout != current_layout)
+                .map(|layout| (current_layout.clone(), layout.clone())),
+            toolbar_issues: Vec::new(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err("profile name can not be empty".into());
+        }
+        let ids = self
+            .settings
+            .themes
+            .iter()
+            .map(|id| id.validate())
+            .chain(self.settings.icon_packs.iter().map(|id| id.validate()))
+            .chain(self.settings.wm_layout.iter().map(|id| id.validate()));
+        for result in ids {
+            result.map_err(|err| format!("profile {}: {err}", self.name))?;
+        }
+        Ok(())
+    }
+
+    /// Switches the state to this profile. Everything is validated before modifying the
+    /// state so on error nothing is changed, on success the applied changes are returned.
+    pub fn apply(
+        &self,
+        settings: &mut Settings,
+        toolbar: &mut Placeholder,
+        weg: &mut WegItems,
+    ) -> Result<ProfileDiff> {
+        self.validate()?;
+        let mut diff = self.diff(settings, toolbar, weg);
+
+        let mut next_toolbar = self.toolbar_layout.clone();
+        diff.toolbar_issues = next_toolbar.sanitize();
+
+        *toolbar = next_toolbar;
+        *weg = self.weg_items.clone();
+        settings.active_themes = self.settings.themes.clone();
+        settings.active_icon_packs = self.settings.icon_packs.clone();
+        if let Some(layout) = &self.settings.wm_layout {
+            settings.by_widget.wm.default_layout = layout.clone();
+        }
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_switch_atomically_and_report_diff() -> Result<()> {
+        let mut settings = Settings::default();
+        let mut toolbar = Placeholder::default();
+        let mut weg = WegItems::default();
+
+        let mut profile = Profile::capture("work", &settings, &toolbar, &weg);
+        profile.settings.themes.push("@user/dark".into());
+        profile.settings.wm_layout = Some("@default/wm-grid".into());
+        profile.toolbar_layout.is_reorder_disabled = true;
+
+        let diff = profile.apply(&mut settings, &mut toolbar, &mut weg)?;
+        assert!(diff.toolbar_layout);
+        assert!(!diff.weg_items);
+        assert_eq!(diff.themes.added, vec![ThemeId::from("@user/dark")]);
+        assert!(diff.themes.removed.is_empty());
+        assert_eq!(
+            diff.wm_layout,
+            Some(("@default/wm-bspwm".into(), "@default/wm-grid".into()))
+        );
+        assert!(toolbar.is_reorder_disabled);
+        assert!(diff.toolbar_issues.is_empty());
+        assert!(profile.diff(&settings, &toolbar, &weg).is_empty());
+
+        let mut broken = profile.clone();
+        broken.settings.themes = vec!["not a theme".into()];
+        broken.toolbar_layout.is_reorder_disabled = false;
+        assert!(broken.apply(&mut settings, &mut toolbar, &mut weg).is_err());
+        // nothing was applied
+        assert!(toolbar.is_reorder_disabled);
+        assert!(settings.active_themes.contains(&"@user/dark".into()));
+        Ok(())
+    }
+}
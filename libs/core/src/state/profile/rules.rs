@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::system_state::{MonitorId, PowerStatus};
+
+use super::Profile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(repr(enum = name))]
+pub enum PowerSource {
+    Ac,
+    Battery,
+}
+
+impl PowerSource {
+    /// `None` if the status is unknown
+    pub fn from_status(status: &PowerStatus) -> Option<Self> {
+        match status.ac_line_status {
+            0 => Some(Self::Battery),
+            1 => Some(Self::Ac),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProfileCondition {
+    /// Exactly these monitors are connected, the order doesn't matter.
+    Monitors {
+        ids: Vec<MonitorId>,
+    },
+    PowerSource {
+        source: PowerSource,
+    },
+    /// Local time between `from` and `to`, ranges can cross midnight (ex: 22:00 to 06:00).
+    TimeOfDay {
+        from: NaiveTime,
+        to: NaiveTime,
+    },
+}
+
+/// Conditions that should all be met to activate the profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProfileRule {
+    pub conditions: Vec<ProfileCondition>,
+}
+
+/// Current state of the system used to evaluate the profile rules
+#[derive(Debug, Clone)]
+pub struct ProfileRuleContext {
+    pub monitors: Vec<MonitorId>,
+    pub power: Option<PowerStatus>,
+    pub time: NaiveTime,
+}
+
+impl ProfileCondition {
+    pub fn matches(&self, context: &ProfileRuleContext) -> bool {
+        match self {
+            ProfileCondition::Monitors { ids } => {
+                let wanted: HashSet<&MonitorId> = ids.iter().collect();
+                let connected: HashSet<&MonitorId> = context.monitors.iter().collect();
+                wanted == connected
+            }
+            ProfileCondition::PowerSource { source } => context
+                .power
+                .as_ref()
+                .and_then(PowerSource::from_status)
+                .is_some_and(|current| current == *source),
+            ProfileCondition::TimeOfDay { from, to } => match from <= to {
+                true => *from <= context.time && context.time < *to,
+                false => *from <= context.time || context.time < *to,
+            },
+        }
+    }
+}
+
+impl ProfileRule {
+    /// Rules without conditions never match
+    pub fn matches(&self, context: &ProfileRuleContext) -> bool {
+        !self.conditions.is_empty() && self.conditions.iter().all(|c| c.matches(context))
+    }
+}
+
+impl Profile {
+    /// Number of conditions of the most specific matching rule
+    fn rule_specificity(&self, context: &ProfileRuleContext) -> Option<usize> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(context))
+            .map(|rule| rule.conditions.len())
+            .max()
+    }
+}
+
+/// Picks the profile whose matching rule has more conditions, on ties the first one wins.
+pub fn select_profile<'a>(
+    profiles: &'a [Profile],
+    context: &ProfileRuleContext,
+) -> Option<&'a Profile> {
+    let mut selected: Option<(&Profile, usize)> = None;
+    for profile in profiles {
+        if let Some(specificity) = profile.rule_specificity(context) {
+            if selected.is_none_or(|(_, best)| specificity > best) {
+                selected = Some((profile, specificity));
+            }
+        }
+    }
+    selected.map(|(profile, _)| profile)
+}
+
+/// Evaluates the rules on each system change, only reporting a profile when the
+/// selection changes so manual switches are respected until the context changes again.
+#[derive(Debug, Default)]
+pub struct ProfileAutoSwitcher {
+    last_selected: Option<String>,
+}
+
+impl ProfileAutoSwitcher {
+    pub fn evaluate<'a>(
+        &mut self,
+        profiles: &'a [Profile],
+        context: &ProfileRuleContext,
+    ) -> Option<&'a Profile> {
+        let selected = select_profile(profiles, context);
+        let name = selected.map(|p| p.name.clone());
+        if name == self.last_selected {
+            return None;
+        }
+        self.last_selected = name;
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(ac_line_status: u8) -> PowerStatus {
+        PowerStatus {
+            ac_line_status,
+            battery_flag: 0,
+            battery_life_percent: 50,
+            system_status_flag: 0,
+            battery_life_time: 0,
+            battery_full_life_time: 0,
+        }
+    }
+
+    fn profile(name: &str, rules: Vec<Vec<ProfileCondition>>) -> Profile {
+        Profile {
+            name: name.to_owned(),
+            rules: rules
+                .into_iter()
+                .map(|conditions| ProfileRule { conditions })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_select_most_specific_profile_on_changes() {
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let docked = ProfileCondition::Monitors {
+            ids: vec![MonitorId("DELL-1".into()), MonitorId("LAPTOP".into())],
+        };
+        let profiles = vec![
+            profile(
+                "night",
+                vec![vec![ProfileCondition::TimeOfDay {
+                    from: at(22, 0),
+                    to: at(6, 0),
+                }]],
+            ),
+            profile(
+                "desk",
+                vec![vec![
+                    docked,
+                    ProfileCondition::PowerSource {
+                        source: PowerSource::Ac,
+                    },
+                ]],
+            ),
+        ];
+
+        let mut context = ProfileRuleContext {
+            monitors: vec![MonitorId("LAPTOP".into()), MonitorId("DELL-1".into())],
+            power: Some(power(1)),
+            time: at(23, 30),
+        };
+        let mut switcher = ProfileAutoSwitcher::default();
+        assert_eq!(switcher.evaluate(&profiles, &context).unwrap().name, "desk");
+        assert!(switcher.evaluate(&profiles, &context).is_none());
+
+        context.power = Some(power(0));
+        assert_eq!(
+            switcher.evaluate(&profiles, &context).unwrap().name,
+            "night"
+        );
+
+        context.time = at(12, 0);
+        assert!(select_profile(&profiles, &context).is_none());
+    }
+}
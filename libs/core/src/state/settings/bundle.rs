@@ -0,0 +1,852 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::{
+    error::Result,
+    resource::{app_version, ResourceId, ResourceKind},
+    state::{
+        AppConfig, Placeholder, Settings, WegItem, WegItems, APP_CONFIGS_MIGRATIONS,
+        SETTINGS_MIGRATIONS, TOOLBAR_ITEMS_MIGRATIONS, WEG_ITEMS_MIGRATIONS,
+    },
+    utils::{search_resource_entrypoint, write_file_atomically, TsUnknown},
+};
+
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[ts(repr(enum = name))]
+pub enum SettingsBundleSection {
+    Settings,
+    Shortcuts,
+    WegItems,
+    ToolbarItems,
+    AppConfigs,
+    Themes,
+    IconPacks,
+    Wallpapers,
+}
+
+impl SettingsBundleSection {
+    fn resource_kind(&self) -> Option<ResourceKind> {
+        match self {
+            Self::Themes => Some(ResourceKind::Theme),
+            Self::IconPacks => Some(ResourceKind::IconPack),
+            Self::Wallpapers => Some(ResourceKind::Wallpaper),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsBundleManifest {
+    pub version: u32,
+    /// Version of the app that created the bundle, if it was known
+    #[serde(default)]
+    pub app_version: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// User folder of the exporting machine, used to remap paths on import
+    pub user_profile: Option<PathBuf>,
+    pub sections: Vec<SettingsBundleSection>,
+}
+
+/// User installed resource, stored with all the files of its folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledResource {
+    pub kind: ResourceKind,
+    pub id: ResourceId,
+    /// Relative path of the file declaring the resource
+    pub entrypoint: String,
+    /// Resource stored as a folder instead of a single file
+    pub is_folder: bool,
+    /// Base64 contents by relative path, using `/` as separator
+    pub files: BTreeMap<String, String>,
+}
+
+fn read_folder_files(
+    root: &Path,
+    folder: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    for entry in folder.read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_folder_files(root, &path, files)?;
+            continue;
+        }
+        let relative = path
+            .strip_prefix(root)
+            .map_err(|_| "invalid resource file")?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let contents = std::fs::read(&path)?;
+        files.insert(
+            relative,
+            base64::engine::general_purpose::STANDARD.encode(contents),
+        );
+    }
+    Ok(())
+}
+
+impl BundledResource {
+    /// Reads a resource installed as a file or folder.
+    pub fn read(kind: ResourceKind, path: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let (entrypoint, is_folder) = match path.is_dir() {
+            true => {
+                read_folder_files(path, path, &mut files)?;
+                let entrypoint =
+                    search_resource_entrypoint(path).ok_or("No metadata file found")?;
+                let name = entrypoint.file_name().unwrap_or_default();
+                (name.to_string_lossy().to_string(), true)
+            }
+            false => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let contents = std::fs::read(path)?;
+                files.insert(
+                    name.to_string(),
+                    base64::engine::general_purpose::STANDARD.encode(contents),
+                );
+                (name.to_string(), false)
+            }
+        };
+
+        let mut resource = Self {
+            kind,
+            id: ResourceId::default(),
+            entrypoint,
+            is_folder,
+            files,
+        };
+        let declaration = resource.declaration()?;
+        resource.id = serde_json::from_value(declaration.get("id").cloned().unwrap_or_default())?;
+        Ok(resource)
+    }
+
+    fn file(&self, name: &str) -> Result<Vec<u8>> {
+        let encoded = self
+            .files
+            .get(name)
+            .ok_or(format!("{}: missing file {name}", self.id))?;
+        Ok(base64::engine::general_purpose::STANDARD.decode(encoded)?)
+    }
+
+    fn is_json(&self) -> bool {
+        self.entrypoint.ends_with(".json")
+    }
+
+    fn declaration(&self) -> Result<Value> {
+        if !(self.entrypoint.ends_with(".yml")
+            || self.entrypoint.ends_with(".yaml")
+            || self.is_json())
+        {
+            return Err(format!("{}: only yaml and json resources can be bundled", self.id).into());
+        }
+        let contents = self.file(&self.entrypoint)?;
+        Ok(serde_yaml::from_slice(&contents)?)
+    }
+
+    /// Changes the id declared on the entrypoint of the resource.
+    fn rename(&mut self, id: ResourceId) -> Result<()> {
+        let mut declaration = self.declaration()?;
+        let object = declaration
+            .as_object_mut()
+            .ok_or(format!("{}: invalid declaration", self.id))?;
+        object.insert("id".to_owned(), id.to_string().into());
+        let contents = match self.is_json() {
+            true => serde_json::to_vec_pretty(&declaration)?,
+            false => serde_yaml::to_string(&declaration)?.into_bytes(),
+        };
+        self.files.insert(
+            self.entrypoint.clone(),
+            base64::engine::general_purpose::STANDARD.encode(contents),
+        );
+        self.id = id;
+        Ok(())
+    }
+
+    /// Path of a bundled file inside `target`, names that could escape it are rejected:
+    /// empty, `.` or `..` segments, backslashes, drive letters and roots.
+    fn file_path(&self, target: &Path, name: &str) -> Result<PathBuf> {
+        let invalid = || format!("{}: invalid file path {name}", self.id);
+        if name.contains(['\\', ':']) {
+            return Err(invalid().into());
+        }
+        let mut path = target.to_path_buf();
+        for part in name.split('/') {
+            let mut components = Path::new(part).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(part)), None) => path.push(part),
+                _ => return Err(invalid().into()),
+            }
+        }
+        if !path.starts_with(target) || path == target {
+            return Err(invalid().into());
+        }
+        Ok(path)
+    }
+
+    /// Writes the resource inside the folder, returning the created path.
+    pub fn write(&self, folder: &Path) -> Result<PathBuf> {
+        let name = format!("{}-{}", self.id.creator(), self.id.resource_name());
+        let target = match self.is_folder {
+            true => folder.join(&name),
+            false => {
+                let extension = Path::new(&self.entrypoint)
+                    .extension()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                folder.join(format!("{name}.{extension}"))
+            }
+        };
+        // all the paths are checked before writing anything
+        let paths = self
+            .files
+            .keys()
+            .map(|relative| match self.is_folder {
+                true => Ok((relative, self.file_path(&target, relative)?)),
+                false => Ok((relative, target.clone())),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (relative, path) in paths {
+            write_file_atomically(&path, &self.file(relative)?)?;
+        }
+        Ok(target)
+    }
+}
+
+/// Current state used to create a bundle.
+pub struct SettingsExportSource<'a> {
+    pub settings: &'a Settings,
+    pub weg_items: &'a WegItems,
+    pub toolbar_items: &'a Placeholder,
+    pub app_configs: &'a [AppConfig],
+    /// Paths of the user installed resources
+    pub resources: Vec<(ResourceKind, PathBuf)>,
+    pub user_profile: Option<PathBuf>,
+}
+
+/// Current state where a bundle will be imported.
+pub struct SettingsImportTarget<'a> {
+    pub settings: &'a Settings,
+    pub weg_items: &'a WegItems,
+    pub toolbar_items: &'a Placeholder,
+    pub app_configs: &'a [AppConfig],
+    /// Ids of the installed resources
+    pub resources: &'a HashSet<ResourceId>,
+    pub user_profile: Option<PathBuf>,
+}
+
+/// Complete or partial setup to be moved between machines, sections are stored
+/// as json to be migrated on import.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct SettingsBundle {
+    pub manifest: SettingsBundleManifest,
+    /// Settings without shortcuts
+    pub settings: Option<TsUnknown>,
+    pub shortcuts: Option<TsUnknown>,
+    pub weg_items: Option<TsUnknown>,
+    pub toolbar_items: Option<TsUnknown>,
+    pub app_configs: Option<TsUnknown>,
+    pub resources: Vec<BundledResource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedResource {
+    pub kind: ResourceKind,
+    pub from: ResourceId,
+    pub to: ResourceId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RemappedPath {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Changed value on a section, by JSON pointer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsImportChange {
+    pub section: SettingsBundleSection,
+    pub pointer: String,
+    pub before: Option<TsUnknown>,
+    pub after: Option<TsUnknown>,
+}
+
+/// Result of the import dry run, nothing is written until applied.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct SettingsImportPlan {
+    pub sections: Vec<SettingsBundleSection>,
+    pub settings: Option<Settings>,
+    pub weg_items: Option<WegItems>,
+    pub toolbar_items: Option<Placeholder>,
+    pub app_configs: Option<Vec<AppConfig>>,
+    pub resources: Vec<BundledResource>,
+    /// Resources whose id was already installed
+    pub renamed: Vec<RenamedResource>,
+    pub remapped_paths: Vec<RemappedPath>,
+    pub changes: Vec<SettingsImportChange>,
+}
+
+impl SettingsImportPlan {
+    /// Writes the bundled resources, the other sections should be stored by the caller.
+    pub fn write_resources(&self, folder: impl Fn(&ResourceKind) -> PathBuf) -> Result<()> {
+        for resource in &self.resources {
+            resource.write(&folder(&resource.kind))?;
+        }
+        Ok(())
+    }
+}
+
+fn section_value<T: Serialize>(value: &T) -> Result<Option<TsUnknown>> {
+    Ok(Some(TsUnknown(serde_json::to_value(value)?)))
+}
+
+/// Path relative to the user profile, Windows paths are case insensitive.
+fn strip_user_profile(path: &Path, profile: &Path) -> Option<PathBuf> {
+    let path_str = path.to_string_lossy();
+    let profile_str = profile.to_string_lossy();
+    let profile_str = profile_str.trim_end_matches(['\\', '/']);
+    let prefix = path_str.get(..profile_str.len())?;
+    if !prefix.eq_ignore_ascii_case(profile_str) {
+        return None;
+    }
+    let rest = &path_str[profile_str.len()..];
+    match rest.is_empty() || rest.starts_with(['\\', '/']) {
+        true => Some(PathBuf::from(rest.trim_start_matches(['\\', '/']))),
+        false => None,
+    }
+}
+
+fn remap_path(path: &Path, from: &Path, to: &Path, remapped: &mut Vec<RemappedPath>) -> PathBuf {
+    let Some(relative) = strip_user_profile(path, from) else {
+        return path.to_path_buf();
+    };
+    let new = match relative.as_os_str().is_empty() {
+        true => to.to_path_buf(),
+        false => PathBuf::from(format!(
+            "{}\\{}",
+            to.to_string_lossy().trim_end_matches(['\\', '/']),
+            relative.to_string_lossy()
+        )),
+    };
+    if !remapped.iter().any(|r| r.from == path) {
+        remapped.push(RemappedPath {
+            from: path.to_path_buf(),
+            to: new.clone(),
+        });
+    }
+    new
+}
+
+/// Replaces the keys and strings equal to `from`.
+fn replace_references(value: &mut Value, from: &str, to: &str) {
+    match value {
+        Value::String(s) if s == from => *s = to.to_owned(),
+        Value::Array(list) => list
+            .iter_mut()
+            .for_each(|v| replace_references(v, from, to)),
+        Value::Object(object) => {
+            if let Some(v) = object.remove(from) {
+                object.insert(to.to_owned(), v);
+            }
+            object
+                .values_mut()
+                .for_each(|v| replace_references(v, from, to));
+        }
+        _ => {}
+    }
+}
+
+fn json_changes(
+    section: SettingsBundleSection,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    pointer: &mut String,
+    changes: &mut Vec<SettingsImportChange>,
+) {
+    if let (Some(Value::Object(before)), Some(Value::Object(after))) = (before, after) {
+        let keys: std::collections::BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        for key in keys {
+            let len = pointer.len();
+            pointer.push('/');
+            pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            json_changes(section, before.get(key), after.get(key), pointer, changes);
+            pointer.truncate(len);
+        }
+        return;
+    }
+    if before != after {
+        changes.push(SettingsImportChange {
+            section,
+            pointer: pointer.clone(),
+            before: before.cloned().map(TsUnknown),
+            after: after.cloned().map(TsUnknown),
+        });
+    }
+}
+
+impl SettingsBundle {
+    pub fn export(
+        source: &SettingsExportSource,
+        sections: &[SettingsBundleSection],
+    ) -> Result<Self> {
+        let has = |section: SettingsBundleSection| sections.contains(&section);
+        let mut bundle = Self {
+            manifest: SettingsBundleManifest {
+                version: BUNDLE_VERSION,
+                app_version: app_version().map(|version| version.to_string()),
+                created_at: Utc::now(),
+                user_profile: source.user_profile.clone(),
+                sections: sections.to_vec(),
+            },
+            settings: None,
+            shortcuts: None,
+            weg_items: None,
+            toolbar_items: None,
+            app_configs: None,
+            resources: Vec::new(),
+        };
+
+        if has(SettingsBundleSection::Settings) {
+            let mut settings = serde_json::to_value(source.settings)?;
+            if let Some(object) = settings.as_object_mut() {
+                object.remove("shortcuts");
+            }
+            bundle.settings = Some(TsUnknown(settings));
+        }
+        if has(SettingsBundleSection::Shortcuts) {
+            bundle.shortcuts = section_value(&source.settings.shortcuts)?;
+        }
+        if has(SettingsBundleSection::WegItems) {
+            bundle.weg_items = section_value(source.weg_items)?;
+        }
+        if has(SettingsBundleSection::ToolbarItems) {
+            bundle.toolbar_items = section_value(source.toolbar_items)?;
+        }
+        if has(SettingsBundleSection::AppConfigs) {
+            bundle.app_configs = section_value(&source.app_configs)?;
+        }
+
+        let kinds: Vec<ResourceKind> = sections.iter().filter_map(|s| s.resource_kind()).collect();
+        for (kind, path) in &source.resources {
+            if kinds.contains(kind) {
+                bundle
+                    .resources
+                    .push(BundledResource::read(kind.clone(), path)?);
+            }
+        }
+        Ok(bundle)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.manifest.version != BUNDLE_VERSION {
+            return Err(format!("unsupported bundle version {}", self.manifest.version).into());
+        }
+        let data = [
+            (SettingsBundleSection::Settings, self.settings.is_some()),
+            (SettingsBundleSection::Shortcuts, self.shortcuts.is_some()),
+            (SettingsBundleSection::WegItems, self.weg_items.is_some()),
+            (
+                SettingsBundleSection::ToolbarItems,
+                self.toolbar_items.is_some(),
+            ),
+            (
+                SettingsBundleSection::AppConfigs,
+                self.app_configs.is_some(),
+            ),
+        ];
+        for (section, present) in data {
+            if self.manifest.sections.contains(&section) != present {
+                return Err(format!("section {section:?} doesn't match the manifest").into());
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for resource in &self.resources {
+            let declared = self
+                .manifest
+                .sections
+                .iter()
+                .any(|s| s.resource_kind().as_ref() == Some(&resource.kind));
+            if !declared {
+                return Err(
+                    format!("{}: section not declared on the manifest", resource.id).into(),
+                );
+            }
+            resource.id.validate()?;
+            if !ids.insert(&resource.id) {
+                return Err(format!("{}: duplicated resource", resource.id).into());
+            }
+            for name in resource.files.keys() {
+                resource.file_path(Path::new("bundle"), name)?;
+                resource.file(name)?;
+            }
+            // the declared id is the one used once the resource is installed
+            let declaration = resource.declaration()?;
+            if declaration.get("id").and_then(Value::as_str) != Some(&resource.id.to_string()) {
+                return Err(format!("{}: id doesn't match its declaration", resource.id).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates and migrates the chosen sections of the bundle, returning the changes
+    /// that will be applied on the target.
+    pub fn prepare_import(
+        &self,
+        target: &SettingsImportTarget,
+        sections: &[SettingsBundleSection],
+    ) -> Result<SettingsImportPlan> {
+        self.validate()?;
+        let sections: Vec<SettingsBundleSection> = sections
+            .iter()
+            .copied()
+            .filter(|s| self.manifest.sections.contains(s))
+            .collect();
+        let has = |section: SettingsBundleSection| sections.contains(&section);
+
+        let mut plan = SettingsImportPlan {
+            sections: sections.clone(),
+            settings: None,
+            weg_items: None,
+            toolbar_items: None,
+            app_configs: None,
+            resources: Vec::new(),
+            renamed: Vec::new(),
+            remapped_paths: Vec::new(),
+            changes: Vec::new(),
+        };
+
+        // resources first, as the settings could reference the renamed ids
+        let kinds: Vec<ResourceKind> = sections.iter().filter_map(|s| s.resource_kind()).collect();
+        let mut taken: HashSet<ResourceId> = target.resources.clone();
+        for resource in self.resources.iter().filter(|r| kinds.contains(&r.kind)) {
+            let mut resource = resource.clone();
+            if taken.contains(&resource.id) {
+                let from = resource.id.clone();
+                let to = (1..)
+                    .map(|n| match n {
+                        1 => ResourceId::from(format!("{from}-imported")),
+                        n => ResourceId::from(format!("{from}-imported{n}")),
+                    })
+                    .find(|id| !taken.contains(id))
+                    .ok_or("no available id")?;
+                resource.rename(to.clone())?;
+                plan.renamed.push(RenamedResource {
+                    kind: resource.kind.clone(),
+                    from,
+                    to,
+                });
+            }
+            taken.insert(resource.id.clone());
+            plan.resources.push(resource);
+        }
+
+        if has(SettingsBundleSection::Settings) || has(SettingsBundleSection::Shortcuts) {
+            let mut settings = match (has(SettingsBundleSection::Settings), &self.settings) {
+                (true, Some(settings)) => {
+                    let mut settings = settings.0.clone();
+                    SETTINGS_MIGRATIONS.migrate(&mut settings)?;
+                    for renamed in &plan.renamed {
+                        replace_references(&mut settings, &renamed.from, &renamed.to);
+                    }
+                    settings
+                }
+                _ => serde_json::to_value(target.settings)?,
+            };
+            let shortcuts = match (has(SettingsBundleSection::Shortcuts), &self.shortcuts) {
+                (true, Some(shortcuts)) => shortcuts.0.clone(),
+                _ => serde_json::to_value(&target.settings.shortcuts)?,
+            };
+            if let Some(object) = settings.as_object_mut() {
+                object.insert("shortcuts".to_owned(), shortcuts);
+            }
+            let mut settings: Settings = serde_json::from_value(settings)?;
+            settings.migrate()?;
+            settings.sanitize()?;
+            plan.settings = Some(settings);
+        }
+
+        if let (true, Some(items)) = (has(SettingsBundleSection::WegItems), &self.weg_items) {
+            let mut items = items.0.clone();
+            WEG_ITEMS_MIGRATIONS.migrate(&mut items)?;
+            let mut items: WegItems = serde_json::from_value(items)?;
+            if let (Some(from), Some(to)) = (&self.manifest.user_profile, &target.user_profile) {
+                let remapped = &mut plan.remapped_paths;
+                for item in items
+                    .left
+                    .iter_mut()
+                    .chain(items.center.iter_mut())
+                    .chain(items.right.iter_mut())
+                {
+                    let (WegItem::Pinned(data) | WegItem::Temporal(data)) = item else {
+                        continue;
+                    };
+                    data.path = remap_path(&data.path, from, to, remapped);
+                    data.relaunch_program =
+                        remap_path(Path::new(&data.relaunch_program), from, to, remapped)
+                            .to_string_lossy()
+                            .to_string();
+                    if let Some(relaunch_in) = &data.relaunch_in {
+                        data.relaunch_in = Some(remap_path(relaunch_in, from, to, remapped));
+                    }
+                }
+            }
+            plan.weg_items = Some(items);
+        }
+
+        if let (true, Some(items)) = (
+            has(SettingsBundleSection::ToolbarItems),
+            &self.toolbar_items,
+        ) {
+            let mut items = items.0.clone();
+            TOOLBAR_ITEMS_MIGRATIONS.migrate(&mut items)?;
+            plan.toolbar_items = Some(serde_json::from_value(items)?);
+        }
+
+        if let (true, Some(configs)) = (has(SettingsBundleSection::AppConfigs), &self.app_configs) {
+            let mut configs = configs.0.clone();
+            APP_CONFIGS_MIGRATIONS.migrate(&mut configs)?;
+            plan.app_configs = Some(serde_json::from_value(configs)?);
+        }
+
+        let sections = [
+            (
+                SettingsBundleSection::Settings,
+                serde_json::to_value(target.settings)?,
+                plan.settings
+                    .as_ref()
+                    .map(serde_json::to_value)
+                    .transpose()?,
+            ),
+            (
+                SettingsBundleSection::WegItems,
+                serde_json::to_value(target.weg_items)?,
+                plan.weg_items
+                    .as_ref()
+                    .map(serde_json::to_value)
+                    .transpose()?,
+            ),
+            (
+                SettingsBundleSection::ToolbarItems,
+                serde_json::to_value(target.toolbar_items)?,
+                plan.toolbar_items
+                    .as_ref()
+                    .map(serde_json::to_value)
+                    .transpose()?,
+            ),
+            (
+                SettingsBundleSection::AppConfigs,
+                serde_json::to_value(target.app_configs)?,
+                plan.app_configs
+                    .as_ref()
+                    .map(serde_json::to_value)
+                    .transpose()?,
+            ),
+        ];
+        for (section, before, after) in sections {
+            if let Some(after) = after {
+                json_changes(
+                    section,
+                    Some(&before),
+                    Some(&after),
+                    &mut String::new(),
+                    &mut plan.changes,
+                );
+            }
+        }
+        Ok(plan)
+    }
+
+    pub fn decode<R: Read + Seek>(mut reader: R) -> Result<Self> {
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != 1 {
+            return Err("unsupported bundle file version".into());
+        }
+        reader.seek(SeekFrom::Current(3))?; // SLB mime type
+        reader.seek(SeekFrom::Current(4))?; // 32 bits reserved
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer)?;
+        let decoded = base64::engine::general_purpose::STANDARD.decode(&buffer)?;
+        Ok(serde_json::from_slice(&decoded)?)
+    }
+
+    pub fn encode<W: Write>(&self, mut writer: W) -> Result<()> {
+        let data = serde_json::to_vec(self)?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+
+        writer.write_all(&[1])?; // version
+        writer.write_all("SLB".as_bytes())?; // SLB mime type
+        writer.write_all(&[0u8; 4])?; // 32 bits reserved
+        writer.write_all(encoded.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let bundle = Self::decode(&file)?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    pub fn store(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        self.encode(&mut file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::state::WegItemSubtype;
+
+    #[test]
+    fn should_export_and_import_selected_sections() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("slu-bundle-{}", uuid::Uuid::new_v4()));
+        let theme = dir.join("themes").join("dark");
+        std::fs::create_dir_all(&theme)?;
+        std::fs::write(
+            theme.join("metadata.yml"),
+            "id: \"@user/dark\"\nmetadata:\n  displayName: Dark\n",
+        )?;
+        std::fs::write(theme.join("styles.css"), ".weg { color: red; }")?;
+
+        let mut settings = Settings::default();
+        settings.active_themes.push("@user/dark".into());
+        settings.date_format = "YYYY".into();
+        let weg_items: WegItems = serde_json::from_value(json!({
+            "center": [{
+                "type": "Pinned",
+                "subtype": "File",
+                "path": "C:\\Users\\old\\Documents\\notes.txt",
+                "relaunchProgram": "C:\\Users\\old\\Documents\\notes.txt"
+            }]
+        }))?;
+
+        let bundle = SettingsBundle::export(
+            &SettingsExportSource {
+                settings: &settings,
+                weg_items: &weg_items,
+                toolbar_items: &Placeholder::default(),
+                app_configs: &[],
+                resources: vec![(ResourceKind::Theme, theme.clone())],
+                user_profile: Some("C:\\Users\\old".into()),
+            },
+            &[
+                SettingsBundleSection::Settings,
+                SettingsBundleSection::WegItems,
+                SettingsBundleSection::Themes,
+            ],
+        )?;
+        let file = dir.join("setup.slb");
+        bundle.store(&file)?;
+        let bundle = SettingsBundle::load(&file)?;
+        assert_eq!(bundle.resources[0].files.len(), 2);
+        assert_eq!(
+            bundle.manifest.app_version,
+            app_version().map(|v| v.to_string())
+        );
+
+        // files escaping the resource folder are rejected
+        for name in [
+            "../evil.css",
+            "a/../../b",
+            "..\\evil.css",
+            "C:/evil.css",
+            "/evil.css",
+            "a//b",
+        ] {
+            let mut malicious = bundle.clone();
+            let contents = malicious.resources[0].files["styles.css"].clone();
+            malicious.resources[0]
+                .files
+                .insert(name.to_owned(), contents);
+            assert!(malicious.validate().is_err(), "{name}");
+            assert!(malicious.resources[0].write(&dir).is_err(), "{name}");
+        }
+
+        // the manifest id can't differ from the declared one
+        let mut renamed = bundle.clone();
+        renamed.resources[0].id = "@user/light".into();
+        assert!(renamed.validate().is_err());
+
+        let installed = HashSet::from(["@user/dark".into()]);
+        let current = Settings::default();
+        let plan = bundle.prepare_import(
+            &SettingsImportTarget {
+                settings: &current,
+                weg_items: &WegItems::default(),
+                toolbar_items: &Placeholder::default(),
+                app_configs: &[],
+                resources: &installed,
+                user_profile: Some("C:\\Users\\New".into()),
+            },
+            &[
+                SettingsBundleSection::Settings,
+                SettingsBundleSection::WegItems,
+                SettingsBundleSection::Themes,
+                // not present on the bundle
+                SettingsBundleSection::AppConfigs,
+            ],
+        )?;
+
+        // conflicting theme renamed and referenced with the new id
+        assert_eq!(plan.renamed[0].to, "@user/dark-imported".into());
+        let imported = plan.settings.as_ref().unwrap();
+        assert!(imported
+            .active_themes
+            .contains(&"@user/dark-imported".into()));
+        assert!(plan.app_configs.is_none());
+
+        let WegItem::Pinned(data) = &plan.weg_items.as_ref().unwrap().center[0] else {
+            panic!("expected a pinned item");
+        };
+        assert_eq!(
+            data.path,
+            PathBuf::from("C:\\Users\\New\\Documents\\notes.txt")
+        );
+        assert_eq!(data.subtype, WegItemSubtype::File);
+        assert_eq!(plan.remapped_paths.len(), 1);
+
+        // dry run reports the changes without touching the current state
+        assert!(plan
+            .changes
+            .iter()
+            .any(|c| c.section == SettingsBundleSection::Settings && c.pointer == "/dateFormat"));
+        assert_eq!(current.date_format, Settings::default().date_format);
+
+        let resources = dir.join("installed");
+        plan.write_resources(|_| resources.clone())?;
+        let written = std::fs::read_to_string(resources.join("user-dark-imported/metadata.yml"))?;
+        assert!(written.contains("@user/dark-imported"));
+        assert!(resources.join("user-dark-imported/styles.css").exists());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
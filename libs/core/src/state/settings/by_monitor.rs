@@ -14,6 +14,10 @@ impl MonitorSettingsByWidget {
             .get(widget_id)
             .is_none_or(|settings| settings.enabled)
     }
+
+    pub fn get(&self, widget_id: &WidgetId) -> Option<&ThirdPartyWidgetSettings> {
+        self.0.get(widget_id)
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, TS)]
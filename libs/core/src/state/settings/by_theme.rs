@@ -4,3 +4,21 @@ use crate::state::config::CssVariableName;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
 pub struct ThemeSettings(HashMap<CssVariableName, String>);
+
+impl ThemeSettings {
+    pub fn get(&self, name: &CssVariableName) -> Option<&String> {
+        self.0.get(name)
+    }
+
+    pub fn set(&mut self, name: CssVariableName, value: String) {
+        self.0.insert(name, value);
+    }
+
+    pub fn remove(&mut self, name: &CssVariableName) -> Option<String> {
+        self.0.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&CssVariableName, &String)> {
+        self.0.iter()
+    }
+}
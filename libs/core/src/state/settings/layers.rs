@@ -0,0 +1,418 @@
+use std::{collections::BTreeMap, path::Path};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use ts_rs::TS;
+
+use crate::{
+    error::Result,
+    state::{Settings, SETTINGS_MIGRATIONS},
+    utils::TsUnknown,
+};
+
+/// Origin of a settings value, sorted from lower to higher priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum SettingsLayer {
+    Default,
+    Policy,
+    User,
+    Monitor,
+}
+
+/// Machine wide settings managed by the system administrators.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct SettingsPolicy {
+    /// Partial settings applied over the defaults
+    pub settings: TsUnknown,
+    /// JSON pointers (e.g. `/updater/channel`) that can't be changed by the user or by monitor.
+    pub locked: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedSettingsOverride {
+    pub pointer: String,
+    pub layer: SettingsLayer,
+    /// Locked pointer that rejected the value
+    pub locked_by: String,
+}
+
+/// Effective settings and where each value came from.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct LayeredSettings {
+    pub settings: Settings,
+    /// Layer of each value not coming from the defaults, by JSON pointer
+    pub provenance: BTreeMap<String, SettingsLayer>,
+    pub locked: Vec<String>,
+    /// User or monitor values ignored by the policy locks
+    pub rejected: Vec<RejectedSettingsOverride>,
+}
+
+impl LayeredSettings {
+    /// Layer of the value on the pointer, inherited from its nearest parent.
+    pub fn source_of(&self, pointer: &str) -> SettingsLayer {
+        let mut pointer = pointer;
+        loop {
+            if let Some(layer) = self.provenance.get(pointer) {
+                return *layer;
+            }
+            match pointer.rfind('/') {
+                Some(index) => pointer = &pointer[..index],
+                None => return SettingsLayer::Default,
+            }
+        }
+    }
+
+    pub fn is_locked(&self, pointer: &str) -> bool {
+        self.locked
+            .iter()
+            .any(|lock| is_same_or_under(pointer, lock))
+    }
+}
+
+/// Raw layers, in order: built-in defaults, machine policy, user and monitor.
+#[derive(Debug, Clone, Default)]
+pub struct SettingsLayers {
+    pub policy: SettingsPolicy,
+    /// User settings file, only contains the values that differ from the defaults and policy.
+    pub user: Value,
+}
+
+impl SettingsLayers {
+    /// Missing files are treated as empty layers.
+    pub fn load(policy_path: Option<&Path>, user_path: &Path) -> Result<Self> {
+        let mut layers = Self::default();
+        if let Some(path) = policy_path.filter(|p| p.exists()) {
+            layers.policy = serde_json::from_slice(&std::fs::read(path)?)?;
+            if !layers.policy.settings.0.is_null() {
+                SETTINGS_MIGRATIONS.migrate(&mut layers.policy.settings.0)?;
+            }
+        }
+        if user_path.exists() {
+            layers.user = Settings::read_value(user_path)?;
+        }
+        Ok(layers)
+    }
+
+    /// Defaults merged with the policy, locks are not applied.
+    fn base(&self) -> Result<Value> {
+        let mut base = without_record(serde_json::to_value(Settings::default())?);
+        let policy = without_record(self.policy.settings.0.clone());
+        merge(&mut base, &policy, &mut String::new(), &mut |_, _| true);
+        Ok(base)
+    }
+
+    /// Resolves the effective settings, if a monitor is given its widget settings
+    /// (`monitorsV3.{id}.byWidget`) are applied over `byWidget`.
+    pub fn resolve(&self, monitor_id: Option<&str>) -> Result<LayeredSettings> {
+        let locked = self.policy.locked.clone();
+        let mut provenance = BTreeMap::new();
+        let mut rejected = Vec::new();
+
+        let mut value = without_record(serde_json::to_value(Settings::default())?);
+        let mut apply = |value: &mut Value, layer_value: &Value, layer: SettingsLayer| {
+            merge(value, layer_value, &mut String::new(), &mut |pointer, _| {
+                if layer != SettingsLayer::Policy {
+                    if let Some(lock) = locked.iter().find(|l| is_related(pointer, l)) {
+                        rejected.push(RejectedSettingsOverride {
+                            pointer: pointer.to_owned(),
+                            layer,
+                            locked_by: lock.clone(),
+                        });
+                        return false;
+                    }
+                }
+                provenance.retain(|p: &String, _| !is_same_or_under(p, pointer));
+                provenance.insert(pointer.to_owned(), layer);
+                true
+            });
+        };
+
+        let policy = without_record(self.policy.settings.0.clone());
+        apply(&mut value, &policy, SettingsLayer::Policy);
+        let user = without_record(self.user.clone());
+        apply(&mut value, &user, SettingsLayer::User);
+
+        if let Some(monitor_id) = monitor_id {
+            let by_widget = value
+                .get("monitorsV3")
+                .and_then(|monitors| monitors.get(monitor_id))
+                .and_then(|config| config.get("byWidget"))
+                .and_then(Value::as_object)
+                .map(|by_widget| {
+                    let mut by_widget = by_widget.clone();
+                    for config in by_widget.values_mut().filter_map(Value::as_object_mut) {
+                        config.remove("$instances");
+                    }
+                    by_widget
+                });
+            if let Some(by_widget) = by_widget {
+                let mut layer = Map::new();
+                layer.insert("byWidget".to_owned(), Value::Object(by_widget));
+                apply(&mut value, &Value::Object(layer), SettingsLayer::Monitor);
+            }
+        }
+
+        let mut settings: Settings = serde_json::from_value(value)?;
+        settings.migrate()?;
+        settings.sanitize()?;
+        Ok(LayeredSettings {
+            settings,
+            provenance,
+            locked: self.policy.locked.clone(),
+            rejected,
+        })
+    }
+
+    /// Sets the user layer from settings resolved without monitor, keeping only the
+    /// values that differ from the defaults and policy. Returns the locked pointers
+    /// whose changes were dropped.
+    pub fn set_user(&mut self, settings: &Settings) -> Result<Vec<String>> {
+        let mut next = without_record(serde_json::to_value(settings)?);
+        let shortcuts = next
+            .as_object_mut()
+            .and_then(|object| object.remove("shortcuts"));
+
+        let mut dropped = Vec::new();
+        let mut user = diff(
+            &self.base()?,
+            &next,
+            &mut String::new(),
+            &mut |pointer| match self.policy.locked.iter().any(|l| is_related(pointer, l)) {
+                true => {
+                    dropped.push(pointer.to_owned());
+                    false
+                }
+                false => true,
+            },
+        )
+        .unwrap_or_else(|| Value::Object(Map::new()));
+
+        if let Some(object) = user.as_object_mut() {
+            object.insert(
+                "$schemaVersion".to_owned(),
+                SETTINGS_MIGRATIONS.latest_version().into(),
+            );
+            if let Some(shortcuts) = shortcuts {
+                object.insert("shortcuts".to_owned(), shortcuts);
+            }
+        }
+        self.user = user;
+        Ok(dropped)
+    }
+
+    pub fn save_user(&self, path: &Path) -> Result<()> {
+        Settings::write_value(path, self.user.clone())
+    }
+}
+
+/// Removes the schema version and migrations record, they are not settings values.
+fn without_record(mut value: Value) -> Value {
+    if let Some(object) = value.as_object_mut() {
+        object.remove("$schemaVersion");
+        object.remove("$migrations");
+    }
+    value
+}
+
+fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn is_same_or_under(pointer: &str, parent: &str) -> bool {
+    pointer
+        .strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// One of the pointers contains the other.
+fn is_related(a: &str, b: &str) -> bool {
+    is_same_or_under(a, b) || is_same_or_under(b, a)
+}
+
+/// Deep merges objects, any other value replaces the target if `accept` allows it.\
+/// Layers that are not objects on the root (as a missing layer) are ignored.
+fn merge(
+    target: &mut Value,
+    layer: &Value,
+    pointer: &mut String,
+    accept: &mut impl FnMut(&str, &Value) -> bool,
+) {
+    let Value::Object(layer) = layer else {
+        if !pointer.is_empty() && accept(pointer, layer) {
+            *target = layer.clone();
+        }
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+    for (key, value) in layer {
+        let len = pointer.len();
+        pointer.push('/');
+        pointer.push_str(&escape_pointer_token(key));
+        match target.get_mut(key) {
+            Some(existing) => merge(existing, value, pointer, accept),
+            None => {
+                let mut new = Value::Null;
+                merge(&mut new, value, pointer, accept);
+                if !new.is_null() {
+                    target.insert(key.clone(), new);
+                }
+            }
+        }
+        pointer.truncate(len);
+    }
+}
+
+/// Values of `next` that differ from `base`, objects are compared by key.
+fn diff(
+    base: &Value,
+    next: &Value,
+    pointer: &mut String,
+    accept: &mut impl FnMut(&str) -> bool,
+) -> Option<Value> {
+    match (base, next) {
+        (Value::Object(base), Value::Object(next)) => {
+            let mut changes = Map::new();
+            for (key, value) in next {
+                let len = pointer.len();
+                pointer.push('/');
+                pointer.push_str(&escape_pointer_token(key));
+                let change = match base.get(key) {
+                    Some(base) => diff(base, value, pointer, accept),
+                    None => diff(&Value::Null, value, pointer, accept),
+                };
+                pointer.truncate(len);
+                if let Some(change) = change {
+                    changes.insert(key.clone(), change);
+                }
+            }
+            (!changes.is_empty()).then_some(Value::Object(changes))
+        }
+        (Value::Null, Value::Object(next)) if next.is_empty() => None,
+        (base, next) if base == next => None,
+        (_, next) => accept(pointer).then(|| next.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::state::{SeelenWegMode, UpdateChannel};
+
+    #[test]
+    fn should_layer_values_with_provenance_and_locks() -> Result<()> {
+        let mut layers = SettingsLayers {
+            policy: serde_json::from_value(json!({
+                "settings": {
+                    "updater": { "channel": "Release" },
+                    "devTools": false,
+                    "dateFormat": "YYYY-MM-DD"
+                },
+                "locked": ["/updater/channel", "/devTools"]
+            }))?,
+            user: json!({
+                "$schemaVersion": 4,
+                "updater": { "channel": "Nightly" },
+                "devTools": true,
+                "dateFormat": "DD/MM/YYYY",
+                "byWidget": { "@seelen/weg": { "size": 50 } },
+                "monitorsV3": {
+                    "M1": { "byWidget": { "@seelen/weg": { "mode": "FullWidth", "$instances": {} } } }
+                }
+            }),
+        };
+
+        let resolved = layers.resolve(None)?;
+        assert!(matches!(
+            resolved.settings.updater.channel,
+            UpdateChannel::Release
+        ));
+        assert!(!resolved.settings.dev_tools);
+        assert_eq!(resolved.settings.date_format, "DD/MM/YYYY");
+        assert_eq!(
+            resolved.source_of("/updater/channel"),
+            SettingsLayer::Policy
+        );
+        assert_eq!(resolved.source_of("/dateFormat"), SettingsLayer::User);
+        assert_eq!(
+            resolved.source_of("/byWidget/@seelen~1weg/size"),
+            SettingsLayer::User
+        );
+        assert_eq!(resolved.source_of("/drpc"), SettingsLayer::Default);
+        assert!(resolved.is_locked("/updater/channel"));
+        let rejected: Vec<&str> = resolved
+            .rejected
+            .iter()
+            .map(|r| r.pointer.as_str())
+            .collect();
+        assert_eq!(rejected, ["/devTools", "/updater/channel"]);
+
+        let on_monitor = layers.resolve(Some("M1"))?;
+        assert_eq!(
+            on_monitor.settings.by_widget.weg.mode,
+            SeelenWegMode::FullWidth
+        );
+        assert_eq!(
+            on_monitor.source_of("/byWidget/@seelen~1weg/mode"),
+            SettingsLayer::Monitor
+        );
+
+        // only user changes are stored, locked values are dropped
+        let mut settings = resolved.settings;
+        settings.dev_tools = true;
+        settings.date_format = "YYYY-MM-DD".into();
+        let dropped = layers.set_user(&settings)?;
+        assert_eq!(dropped, ["/devTools"]);
+        assert_eq!(layers.user.get("dateFormat"), None);
+        assert_eq!(
+            layers.user["byWidget"],
+            json!({ "@seelen/weg": { "size": 50 } })
+        );
+        assert_eq!(layers.user["$schemaVersion"], json!(4));
+        Ok(())
+    }
+
+    #[test]
+    fn should_resolve_without_policy_or_user_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("slu-layers-{}", uuid::Uuid::new_v4()));
+        let user_path = dir.join("settings.json");
+
+        let resolved = SettingsLayers::default().resolve(None)?;
+        assert!(resolved.provenance.is_empty());
+
+        let layers = SettingsLayers::load(None, &user_path)?;
+        let resolved = layers.resolve(None)?;
+        assert_eq!(
+            resolved.settings.date_format,
+            Settings::default().date_format
+        );
+
+        // policy without settings, only locks
+        let policy_path = dir.join("policy.json");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&policy_path, r#"{ "locked": ["/devTools"] }"#)?;
+        let mut layers = SettingsLayers::load(Some(&policy_path), &user_path)?;
+        let resolved = layers.resolve(None)?;
+        assert!(resolved.rejected.is_empty());
+        assert!(resolved.is_locked("/devTools"));
+
+        assert!(layers.set_user(&resolved.settings)?.is_empty());
+        assert!(layers.resolve(None)?.rejected.is_empty());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,14 +1,16 @@
 /* In this file we use #[serde_alias(SnakeCase)] as backward compatibility from versions below v1.9.8 */
+pub mod bundle;
 pub mod by_monitor;
 pub mod by_theme;
 pub mod by_wallpaper;
 pub mod by_widget;
+pub mod layers;
 pub mod shortcuts;
 
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -23,7 +25,8 @@ use crate::{
     state::{
         by_monitor::MonitorConfiguration, by_theme::ThemeSettings,
         by_wallpaper::WallpaperInstanceSettings, by_widget::SettingsByWidget,
-        shortcuts::SluShortcutsSettings,
+        shortcuts::SluShortcutsSettings, write_backup, MigrationRecord, PerformanceModeRule,
+        SETTINGS_MIGRATIONS,
     },
 };
 
@@ -470,6 +473,10 @@ pub struct Settings {
     pub by_wallpaper: HashMap<WallpaperId, WallpaperInstanceSettings>,
     /// Performance options
     pub performance_mode: PerformanceModeSettings,
+    /// Notification rules, do not disturb and history retention
+    pub notifications: NotificationSettings,
+    #[serde(flatten)]
+    pub migrations: MigrationRecord,
 }
 
 impl Default for Settings {
@@ -482,6 +489,7 @@ impl Default for Settings {
             launcher: None,
             // ---
             performance_mode: PerformanceModeSettings::default(),
+            notifications: NotificationSettings::default(),
             shortcuts: SluShortcutsSettings::default(),
             drpc: false,
             old_active_themes: Vec::new(),
@@ -495,6 +503,7 @@ impl Default for Settings {
             by_widget: SettingsByWidget::default(),
             by_theme: HashMap::new(),
             by_wallpaper: HashMap::new(),
+            migrations: SETTINGS_MIGRATIONS.record(),
         }
     }
 }
@@ -511,24 +520,42 @@ impl Settings {
         }
     }
 
-    /// Migrate old settings (before v2.1.0) (will be removed in v3.0.0)
+    /// Migrate old settings (before v2.1.0) (will be removed in v3.0.0)\
+    /// The legacy sections are moved by [`SETTINGS_MIGRATIONS`] and replace the current ones,
+    /// as on a parsed struct these can't be told apart from the defaults.
     pub fn migrate(&mut self) -> Result<()> {
-        let dict = &mut self.by_widget;
-        if let Some(tb) = self.fancy_toolbar.take() {
-            dict.fancy_toolbar = tb;
+        let legacy = [
+            (
+                "fancyToolbar",
+                serde_json::to_value(self.fancy_toolbar.take())?,
+            ),
+            ("seelenweg", serde_json::to_value(self.seelenweg.take())?),
+            (
+                "windowManager",
+                serde_json::to_value(self.window_manager.take())?,
+            ),
+            ("wall", serde_json::to_value(self.wall.take())?),
+            ("launcher", serde_json::to_value(self.launcher.take())?),
+        ];
+        let sections: serde_json::Map<String, serde_json::Value> = legacy
+            .into_iter()
+            .filter(|(_, section)| !section.is_null())
+            .map(|(key, section)| (key.to_owned(), section))
+            .collect();
+        if sections.is_empty() {
+            return Ok(());
         }
-        if let Some(weg) = self.seelenweg.take() {
-            dict.weg = weg;
-        }
-        if let Some(wm) = self.window_manager.take() {
-            dict.wm = wm;
-        }
-        if let Some(wall) = self.wall.take() {
-            dict.wall = wall;
-        }
-        if let Some(launcher) = self.launcher.take() {
-            dict.launcher = launcher;
+
+        let mut document = serde_json::Value::Object(sections);
+        SETTINGS_MIGRATIONS.migrate(&mut document)?;
+        let Some(migrated) = document.get("byWidget").and_then(|v| v.as_object()) else {
+            return Ok(());
+        };
+        let mut by_widget = serde_json::to_value(&self.by_widget)?;
+        if let Some(by_widget) = by_widget.as_object_mut() {
+            by_widget.extend(migrated.clone());
         }
+        self.by_widget = serde_json::from_value(by_widget)?;
         Ok(())
     }
 
@@ -560,56 +587,73 @@ impl Settings {
         Ok(())
     }
 
-    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let path = path.as_ref();
+    fn shortcuts_path(path: &Path) -> Option<PathBuf> {
+        let (parent, stem) = (path.parent()?, path.file_stem()?);
+        Some(parent.join(format!("{}_shortcuts.json", stem.to_string_lossy())))
+    }
 
-        let mut settings: Self = {
-            let file = File::open(path)?;
+    /// Reads the settings file as json, migrated to the latest schema version and
+    /// with the shortcuts of the sibling file.
+    pub(crate) fn read_value(path: &Path) -> Result<serde_json::Value> {
+        let contents = {
+            let mut file = File::open(path)?;
             file.lock_shared()?;
-            serde_json::from_reader(&file)?
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents)?;
+            contents
         };
+        let mut value: serde_json::Value = serde_json::from_slice(&contents)?;
+        let report = SETTINGS_MIGRATIONS.migrate(&mut value)?;
+        if !report.is_empty() {
+            write_backup(path, &contents, report.from)?;
+        }
 
         // Load shortcuts from sibling file if it exists
-        if let (Some(parent), Some(stem)) = (path.parent(), path.file_stem()) {
-            let shortcuts_path = parent.join(format!("{}_shortcuts.json", stem.to_string_lossy()));
-            if shortcuts_path.exists() {
-                let file = File::open(&shortcuts_path)?;
-                file.lock_shared()?;
-                settings.shortcuts = serde_json::from_reader(&file)?;
+        if let Some(shortcuts_path) = Self::shortcuts_path(path).filter(|p| p.exists()) {
+            let file = File::open(&shortcuts_path)?;
+            file.lock_shared()?;
+            let shortcuts: serde_json::Value = serde_json::from_reader(&file)?;
+            if let Some(object) = value.as_object_mut() {
+                object.insert("shortcuts".to_owned(), shortcuts);
             }
         }
-
-        settings.migrate()?;
-        settings.sanitize()?;
-        Ok(settings)
+        Ok(value)
     }
 
-    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
-        let path = path.as_ref();
+    /// Writes the json settings, the shortcuts are stored on a sibling file.
+    pub(crate) fn write_value(path: &Path, mut value: serde_json::Value) -> Result<()> {
+        let shortcuts = value
+            .as_object_mut()
+            .and_then(|object| object.remove("shortcuts"));
 
         {
-            // Create a copy without shortcuts for main settings file
-            let mut settings_copy = serde_json::to_value(self)?;
-            settings_copy.as_object_mut().unwrap().remove("shortcuts");
-
             let mut file = File::create(path)?;
             file.lock()?;
-            serde_json::to_writer_pretty(&file, &settings_copy)?;
+            serde_json::to_writer_pretty(&file, &value)?;
             file.flush()?;
         }
 
         // Save shortcuts to sibling file
-        if let (Some(parent), Some(stem)) = (path.parent(), path.file_stem()) {
-            let shortcuts_path = parent.join(format!("{}_shortcuts.json", stem.to_string_lossy()));
+        if let (Some(shortcuts_path), Some(shortcuts)) = (Self::shortcuts_path(path), shortcuts) {
             let mut shortcuts_file = File::create(&shortcuts_path)?;
             shortcuts_file.lock()?;
-            serde_json::to_writer_pretty(&shortcuts_file, &self.shortcuts)?;
+            serde_json::to_writer_pretty(&shortcuts_file, &shortcuts)?;
             shortcuts_file.flush()?;
         }
-
         Ok(())
     }
 
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let mut settings: Self = serde_json::from_value(Self::read_value(path.as_ref())?)?;
+        settings.migrate()?;
+        settings.sanitize()?;
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Self::write_value(path.as_ref(), serde_json::to_value(self)?)
+    }
+
     // This indicates if the widget is enabled on general, doesn't take in care
     // by monitor or by instance settings.
     pub fn is_widget_enabled(&self, widget_id: &WidgetId) -> bool {
@@ -627,6 +671,12 @@ pub struct PerformanceModeSettings {
     pub default: PerformanceMode,
     pub on_battery: PerformanceMode,
     pub on_energy_saver: PerformanceMode,
+    /// Evaluated in order before the modes above, the first matching rule is used.
+    pub rules: Vec<PerformanceModeRule>,
+    /// Percentage that the battery should move back over a threshold to stop matching it.
+    pub battery_margin: f32,
+    /// Time that a new mode should be stable before switching to it.
+    pub switch_delay_ms: u64,
 }
 
 impl Default for PerformanceModeSettings {
@@ -635,6 +685,9 @@ impl Default for PerformanceModeSettings {
             default: PerformanceMode::Disabled,
             on_battery: PerformanceMode::Minimal,
             on_energy_saver: PerformanceMode::Extreme,
+            rules: Vec::new(),
+            battery_margin: 3.0,
+            switch_delay_ms: 5000,
         }
     }
 }
@@ -649,3 +702,94 @@ pub enum PerformanceMode {
     /// Disables all the animations.
     Extreme,
 }
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NotificationSettings {
+    /// Rules by app, the first rule matching the app is used.
+    pub rules: Vec<NotificationAppRule>,
+    pub do_not_disturb: DoNotDisturbSettings,
+    pub retention: NotificationRetention,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationAppRule {
+    /// App user model id of the app
+    pub app_umid: String,
+    pub action: NotificationRuleAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum NotificationRuleAction {
+    /// Notifications are saved on the history but never shown.
+    Mute,
+    /// Notifications are shown first and bypass do not disturb.
+    Prioritize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DoNotDisturbSettings {
+    /// Manually enabled, independently of the schedules
+    pub enabled: bool,
+    pub schedules: Vec<DoNotDisturbSchedule>,
+    /// App user model ids allowed to show notifications while do not disturb is active
+    pub bypass: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DoNotDisturbSchedule {
+    /// Days when the schedule starts, empty means every day.
+    #[serde(default)]
+    pub days: Vec<ScheduleDay>,
+    /// Local time, ranges can cross midnight (ex: 22:00 to 07:00).
+    pub from: chrono::NaiveTime,
+    pub to: chrono::NaiveTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum ScheduleDay {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<chrono::Weekday> for ScheduleDay {
+    fn from(day: chrono::Weekday) -> Self {
+        match day {
+            chrono::Weekday::Mon => Self::Monday,
+            chrono::Weekday::Tue => Self::Tuesday,
+            chrono::Weekday::Wed => Self::Wednesday,
+            chrono::Weekday::Thu => Self::Thursday,
+            chrono::Weekday::Fri => Self::Friday,
+            chrono::Weekday::Sat => Self::Saturday,
+            chrono::Weekday::Sun => Self::Sunday,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NotificationRetention {
+    /// Max number of notifications stored on the history
+    pub max_entries: usize,
+    /// Notifications older than this are removed, 0 keeps them forever.
+    pub max_age_days: u32,
+}
+
+impl Default for NotificationRetention {
+    fn default() -> Self {
+        Self {
+            max_entries: 500,
+            max_age_days: 30,
+        }
+    }
+}
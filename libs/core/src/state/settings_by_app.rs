@@ -1,9 +1,13 @@
+use std::path::Path;
+
 use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_alias::serde_alias;
 use ts_rs::TS;
 
+use crate::{error::Result, state::APP_CONFIGS_MIGRATIONS};
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
 #[ts(repr(enum = name))]
 pub enum AppExtraFlag {
@@ -191,6 +195,13 @@ impl AppConfig {
 pub struct AppsConfigurationList(Vec<AppConfig>);
 
 impl AppsConfigurationList {
+    /// Loads a list of configs (json or yaml), migrating it to the latest version.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut list = Self(APP_CONFIGS_MIGRATIONS.load_file(path)?);
+        list.prepare();
+        Ok(list)
+    }
+
     pub fn prepare(&mut self) {
         self.0.iter_mut().for_each(|config| config.prepare());
     }
@@ -0,0 +1,208 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    resource::{ThemeId, WidgetId},
+    state::{by_theme::ThemeSettings, CompiledThemeVariables, Settings, Theme},
+    utils::sha256_hex,
+};
+
+static CSS_COMMENT_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(?s)/\*.*?\*/").unwrap());
+
+static CSS_CUSTOM_PROPERTY_REGEX: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(?:^|[\s;{])(--[a-zA-Z_][\w-]*)\s*:").unwrap());
+
+/// Css contributed by a single active theme
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeLayer {
+    pub theme: ThemeId,
+    /// Css layer name, shared styles use the same name with the `-shared` suffix.
+    pub name: String,
+    pub variables: CompiledThemeVariables,
+    pub shared_styles: String,
+    pub styles: HashMap<WidgetId, String>,
+}
+
+impl ThemeLayer {
+    fn new(theme: &Theme, user: Option<&ThemeSettings>) -> Self {
+        Self {
+            theme: theme.id.clone(),
+            name: Self::layer_name(&theme.id),
+            variables: theme.compile_variables(user),
+            shared_styles: theme.shared_styles.clone(),
+            styles: theme.styles.clone(),
+        }
+    }
+
+    /// ex: `@user/my-theme` -> `theme-_user_my_theme-1a2b3c4d`\
+    /// The sanitized id is only for readability, the hash of the id keeps the names unique.
+    pub fn layer_name(id: &ThemeId) -> String {
+        let sanitized: String = id
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let hash = sha256_hex(id.as_bytes());
+        format!("theme-{sanitized}-{}", &hash[..8])
+    }
+
+    fn shared_layer_name(&self) -> String {
+        format!("{}-shared", self.name)
+    }
+
+    /// Custom properties declared by the theme, via settings or directly on the styles.
+    fn custom_properties(&self) -> HashSet<String> {
+        let mut properties: HashSet<String> = self
+            .variables
+            .variables
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for css in std::iter::once(&self.shared_styles).chain(self.styles.values()) {
+            let css = CSS_COMMENT_REGEX.replace_all(css, "");
+            for captures in CSS_CUSTOM_PROPERTY_REGEX.captures_iter(&css) {
+                properties.insert(captures[1].to_owned());
+            }
+        }
+        properties
+    }
+}
+
+/// Custom property defined by more than one active theme
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeVariableConflict {
+    pub name: String,
+    /// Themes defining the property in activation order
+    pub defined_by: Vec<ThemeId>,
+    /// Theme whose value wins the cascade, the last activated one.
+    pub winner: ThemeId,
+}
+
+/// Result of cascading the active themes in activation order, later themes
+/// are placed on later css layers so they override the previous ones.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeComposition {
+    pub layers: Vec<ThemeLayer>,
+    /// Active themes that are not installed
+    pub missing: Vec<ThemeId>,
+    /// Active themes skipped because they target an incompatible app version
+    pub quarantined: Vec<ThemeId>,
+    /// Sorted by property name
+    pub conflicts: Vec<ThemeVariableConflict>,
+}
+
+impl ThemeComposition {
+    pub fn compose(
+        themes: &[Theme],
+        active: &[ThemeId],
+        by_theme: &HashMap<ThemeId, ThemeSettings>,
+    ) -> Self {
+        let mut composition = Self::default();
+
+        let mut seen = HashSet::new();
+        for id in active {
+            if !seen.insert(id) {
+                continue;
+            }
+            match themes.iter().find(|t| &t.id == id) {
+                Some(theme) if theme.metadata.is_quarantined() => {
+                    composition.quarantined.push(id.clone())
+                }
+                Some(theme) => composition
+                    .layers
+                    .push(ThemeLayer::new(theme, by_theme.get(id))),
+                None => composition.missing.push(id.clone()),
+            }
+        }
+
+        let mut defined_by: HashMap<String, Vec<ThemeId>> = HashMap::new();
+        for layer in &composition.layers {
+            for property in layer.custom_properties() {
+                defined_by
+                    .entry(property)
+                    .or_default()
+                    .push(layer.theme.clone());
+            }
+        }
+        composition.conflicts = defined_by
+            .into_iter()
+            .filter(|(_, themes)| themes.len() > 1)
+            .map(|(name, themes)| ThemeVariableConflict {
+                name,
+                winner: themes.last().cloned().unwrap_or_default(),
+                defined_by: themes,
+            })
+            .collect();
+        composition.conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+        composition
+    }
+
+    pub fn from_settings(themes: &[Theme], settings: &Settings) -> Self {
+        Self::compose(themes, &settings.active_themes, &settings.by_theme)
+    }
+
+    /// Widgets with specific styles on any of the active themes, sorted by id.
+    pub fn widgets(&self) -> Vec<&WidgetId> {
+        let mut widgets: Vec<&WidgetId> = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.styles.keys())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        widgets.sort_by_key(|id| id.as_str());
+        widgets
+    }
+
+    /// Final stylesheet for the widget, widgets without specific styles still
+    /// receive the shared styles and variables of each theme.
+    pub fn css_for(&self, widget: &WidgetId) -> String {
+        let order: Vec<String> = self
+            .layers
+            .iter()
+            .flat_map(|layer| [layer.shared_layer_name(), layer.name.clone()])
+            .collect();
+
+        let mut css = String::new();
+        if !order.is_empty() {
+            css.push_str(&format!("@layer {};\n", order.join(", ")));
+        }
+
+        for layer in &self.layers {
+            css.push_str(&format!(
+                "@layer {} {{\n{}\n}}\n",
+                layer.shared_layer_name(),
+                layer.shared_styles
+            ));
+            css.push_str(&format!(
+                "@layer {} {{\n{}\n{}\n}}\n",
+                layer.name,
+                layer.variables.to_css(),
+                layer
+                    .styles
+                    .get(widget)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            ));
+        }
+        css
+    }
+
+    /// Stylesheets of all the widgets with specific styles, see [`ThemeComposition::css_for`]
+    pub fn css_by_widget(&self) -> HashMap<WidgetId, String> {
+        self.widgets()
+            .into_iter()
+            .map(|id| (id.clone(), self.css_for(id)))
+            .collect()
+    }
+}
@@ -8,6 +8,27 @@ use crate::{error::Result, resource::ResourceText};
 #[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, TS)]
 pub struct ThemeSettingsDefinition(Vec<ThemeConfigDefinition>);
 
+impl ThemeSettingsDefinition {
+    /// All the variable definitions, including the ones inside groups, in declaration order.
+    pub fn variables(&self) -> Vec<&ThemeVariableDefinition> {
+        fn collect<'a>(
+            items: &'a [ThemeConfigDefinition],
+            result: &mut Vec<&'a ThemeVariableDefinition>,
+        ) {
+            for item in items {
+                match item {
+                    ThemeConfigDefinition::Group(group) => collect(&group.items, result),
+                    ThemeConfigDefinition::Item(def) => result.push(def),
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        collect(&self.0, &mut result);
+        result
+    }
+}
+
 #[derive(Debug, Clone, Serialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
 pub enum ThemeConfigDefinition {
@@ -1,7 +1,12 @@
 #[cfg(test)]
 mod tests;
 
+mod composer;
 pub mod config;
+mod variables;
+
+pub use composer::*;
+pub use variables::*;
 
 use std::{collections::HashMap, path::Path};
 
@@ -12,7 +17,10 @@ use ts_rs::TS;
 
 use crate::{
     error::Result,
-    resource::{ResourceKind, ResourceMetadata, SluResource, ThemeId, WidgetId},
+    resource::{
+        collect_style_diagnostics, compile_scss, load_resource_file, ResourceKind,
+        ResourceMetadata, SluResource, StyleDiagnostic, ThemeId, WidgetId,
+    },
     utils::search_resource_entrypoint,
 };
 
@@ -32,6 +40,10 @@ pub struct Theme {
     /// Shared css styles for all widgets, commonly used to set styles
     /// for the components library
     pub shared_styles: String,
+    /// Errors found while compiling the styles, the broken styles are not loaded.\
+    /// Not stored on the theme file, exposed via `StateGetThemeDiagnostics`.
+    #[serde(skip)]
+    pub diagnostics: Vec<StyleDiagnostic>,
 }
 
 impl SluResource for Theme {
@@ -45,6 +57,13 @@ impl SluResource for Theme {
         &mut self.metadata
     }
 
+    fn load_from_file(path: &Path) -> Result<Theme> {
+        let (theme, diagnostics) = collect_style_diagnostics(|| load_resource_file::<Theme>(path));
+        let mut theme = theme?;
+        theme.diagnostics.extend(diagnostics);
+        Ok(theme)
+    }
+
     fn load_from_folder(path: &Path) -> Result<Theme> {
         let mut theme = Self::load_old_folder_schema(path)?;
 
@@ -57,12 +76,9 @@ impl SluResource for Theme {
                 };
 
                 if file_stem == "shared" && ALLOWED_STYLE_EXTENSIONS.iter().any(|e| *e == ext) {
-                    let css = if ext == "scss" || ext == "sass" {
-                        grass::from_path(&outer_path, &grass::Options::default())?
-                    } else {
-                        std::fs::read_to_string(&outer_path)?
-                    };
-                    theme.shared_styles = css;
+                    if let Some(css) = theme.read_style_file(&outer_path)? {
+                        theme.shared_styles = css;
+                    }
                 }
                 continue 'outer;
             }
@@ -79,10 +95,8 @@ impl SluResource for Theme {
                 };
 
                 if ALLOWED_STYLE_EXTENSIONS.iter().any(|e| *e == ext) {
-                    let css = if ext == "scss" || ext == "sass" {
-                        grass::from_path(&path, &grass::Options::default())?
-                    } else {
-                        std::fs::read_to_string(&path)?
+                    let Some(css) = theme.read_style_file(&path)? else {
+                        continue 'inner;
                     };
                     theme.styles.insert(
                         WidgetId::from(
@@ -103,6 +117,24 @@ impl SluResource for Theme {
 }
 
 impl Theme {
+    /// Reads a css file or compiles a scss/sass one, on compilation errors the
+    /// diagnostic is stored on the theme and `None` is returned.
+    fn read_style_file(&mut self, path: &Path) -> Result<Option<String>> {
+        if !path
+            .extension()
+            .is_some_and(|ext| ext == "scss" || ext == "sass")
+        {
+            return Ok(Some(std::fs::read_to_string(path)?));
+        }
+        match compile_scss(path) {
+            Ok(css) => Ok(Some(css)),
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                Ok(None)
+            }
+        }
+    }
+
     /// Load theme from a folder using old deprecated paths since v2.1.0 will be removed in v3
     fn load_old_folder_schema(path: &Path) -> Result<Theme> {
         let file = search_resource_entrypoint(path).unwrap_or_else(|| {
@@ -1,6 +1,13 @@
 use std::path::PathBuf;
 
-use crate::{error::Result, resource::SluResource, state::Theme};
+use crate::{
+    error::Result,
+    resource::{ResourceCompatibility, SluResource, ThemeId, WidgetId},
+    state::{
+        by_theme::ThemeSettings, config::CssVariableName, validate_css_color, Theme,
+        ThemeComposition, ThemeLayer, ThemeVariableValueSource,
+    },
+};
 
 #[test]
 fn test_compatibility_with_older_schemas() -> Result<()> {
@@ -9,3 +16,295 @@ fn test_compatibility_with_older_schemas() -> Result<()> {
         .map_err(|e| format!("v2.3.12: {e}"))?;
     Ok(())
 }
+
+fn variables_theme() -> Theme {
+    serde_json::from_value(serde_json::json!({
+        "id": "@test/variables",
+        "settings": [
+            { "syntax": "<color>", "name": "--color", "label": "Color", "initialValue": "#f00" },
+            { "syntax": "<length>", "name": "--size", "label": "Size", "initialValue": 20, "initialValueUnit": "px" },
+            { "syntax": "<number>", "name": "--opacity", "label": "Opacity", "initialValue": 0.5, "options": [0.5, 1] },
+            { "syntax": "<string>", "name": "--text", "label": "Text", "initialValue": "hello \"world\"" },
+            { "group": { "header": "Group", "items": [
+                { "syntax": "<url>", "name": "--image", "label": "Image", "initialValue": "https://example.com/a.png" },
+            ]}},
+        ],
+    }))
+    .unwrap()
+}
+
+fn var(name: &str) -> CssVariableName {
+    CssVariableName::from_string(name).unwrap()
+}
+
+#[test]
+fn should_compile_initial_values() {
+    let compiled = variables_theme().compile_variables(None);
+    assert!(compiled.rejected.is_empty(), "{:?}", compiled.rejected);
+    assert_eq!(
+        compiled.to_css(),
+        "/* @test/variables */\n:root {\n  --color: #f00;\n  --size: 20px;\n  --opacity: 0.5;\n  --text: \"hello \\\"world\\\"\";\n  --image: url(\"https://example.com/a.png\");\n}"
+    );
+}
+
+#[test]
+fn should_apply_valid_user_overrides() {
+    let mut user = ThemeSettings::default();
+    user.set(var("--color"), "rgba(0, 0, 0, 0.5)".to_owned());
+    user.set(var("--size"), "0".to_owned());
+    user.set(var("--opacity"), "1".to_owned());
+    user.set(
+        var("--image"),
+        "url('https://example.com/b.png')".to_owned(),
+    );
+
+    let compiled = variables_theme().compile_variables(Some(&user));
+    assert!(compiled.rejected.is_empty(), "{:?}", compiled.rejected);
+    let values: Vec<&str> = compiled.variables.iter().map(|(_, v)| v.as_str()).collect();
+    assert_eq!(
+        values,
+        [
+            "rgba(0, 0, 0, 0.5)",
+            "0",
+            "1",
+            "\"hello \\\"world\\\"\"",
+            "url(\"https://example.com/b.png\")"
+        ]
+    );
+}
+
+#[test]
+fn should_reject_invalid_and_unknown_overrides() {
+    let mut user = ThemeSettings::default();
+    user.set(var("--color"), "red; } body { display: none".to_owned());
+    user.set(var("--size"), "20".to_owned());
+    user.set(var("--opacity"), "0.7".to_owned());
+    user.set(var("--image"), "javascript:alert(1)".to_owned());
+    user.set(var("--unknown-b"), "1".to_owned());
+    user.set(var("--unknown-a"), "1".to_owned());
+
+    let compiled = variables_theme().compile_variables(Some(&user));
+    let rejected: Vec<&str> = compiled.rejected.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(
+        rejected,
+        [
+            "--color",
+            "--size",
+            "--opacity",
+            "--image",
+            "--unknown-a",
+            "--unknown-b"
+        ]
+    );
+    assert!(compiled
+        .rejected
+        .iter()
+        .all(|r| r.source == ThemeVariableValueSource::User));
+
+    // initial values are used as fallback
+    assert_eq!(
+        compiled.to_css(),
+        variables_theme().compile_variables(None).to_css()
+    );
+}
+
+#[test]
+fn should_accept_named_colors_and_var_references() {
+    for valid in [
+        "red",
+        "RebeccaPurple",
+        "transparent",
+        "var(--accent)",
+        "var(--accent, #fff)",
+        "var(--accent, var(--fallback, navy))",
+    ] {
+        assert_eq!(validate_css_color(valid).as_deref(), Ok(valid), "{valid}");
+    }
+    for invalid in [
+        "reddish",
+        "var(accent)",
+        "var(--accent, not-a-color)",
+        "var(--a); } body { display: none",
+    ] {
+        assert!(validate_css_color(invalid).is_err(), "{invalid}");
+    }
+
+    let theme: Theme = serde_json::from_value(serde_json::json!({
+        "settings": [
+            { "syntax": "<color>", "name": "--color", "label": "Color", "initialValue": "rebeccapurple" },
+        ],
+    }))
+    .unwrap();
+    let mut user = ThemeSettings::default();
+    user.set(var("--color"), "var(--system-accent-color)".to_owned());
+    assert!(theme.compile_variables(None).rejected.is_empty());
+    let compiled = theme.compile_variables(Some(&user));
+    assert!(compiled.rejected.is_empty());
+    assert_eq!(compiled.variables[0].1, "var(--system-accent-color)");
+}
+
+#[test]
+fn should_reject_invalid_initial_values() {
+    let theme: Theme = serde_json::from_value(serde_json::json!({
+        "settings": [
+            { "syntax": "<color>", "name": "--color", "label": "Color", "initialValue": "not-a-color" },
+            { "syntax": "<length>", "name": "--size", "label": "Size", "initialValue": 2, "initialValueUnit": "parsecs" },
+        ],
+    }))
+    .unwrap();
+
+    let compiled = theme.compile_variables(None);
+    assert!(compiled.variables.is_empty());
+    assert_eq!(compiled.rejected.len(), 2);
+    assert!(compiled
+        .rejected
+        .iter()
+        .all(|r| r.source == ThemeVariableValueSource::Initial));
+}
+
+fn composable_theme(id: &str, settings: serde_json::Value, styles: serde_json::Value) -> Theme {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "settings": settings,
+        "styles": styles,
+        "sharedStyles": format!(".{} {{}}", id.replace(['@', '/'], "")),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn should_compose_themes_by_activation_order() {
+    let base = composable_theme(
+        "@test/base",
+        serde_json::json!([{ "syntax": "<color>", "name": "--accent", "label": "Accent", "initialValue": "#f00" }]),
+        serde_json::json!({ "@seelen/weg": ":root { --weg-size: 40px; }" }),
+    );
+    let custom = composable_theme(
+        "@test/custom",
+        serde_json::json!([]),
+        serde_json::json!({
+            "@seelen/weg": "/* --ignored: 1; */ .item { --accent: blue; --weg-size: 32px; }",
+            "@seelen/fancy-toolbar": ".bar { color: red; }",
+        }),
+    );
+    let themes = vec![custom, base];
+    let active: Vec<ThemeId> = vec![
+        "@test/base".into(),
+        "@test/custom".into(),
+        "@test/missing".into(),
+    ];
+
+    let composition = ThemeComposition::compose(&themes, &active, &Default::default());
+    assert_eq!(composition.missing, vec![ThemeId::from("@test/missing")]);
+    assert_eq!(
+        composition.widgets(),
+        vec![&WidgetId::known_toolbar(), &WidgetId::known_weg()]
+    );
+
+    let base_layer = ThemeLayer::layer_name(&"@test/base".into());
+    let custom_layer = ThemeLayer::layer_name(&"@test/custom".into());
+    let css = composition.css_for(&WidgetId::known_weg());
+    assert!(css.starts_with(&format!(
+        "@layer {base_layer}-shared, {base_layer}, {custom_layer}-shared, {custom_layer};\n"
+    )));
+    let base_pos = css.find("--weg-size: 40px").unwrap();
+    let custom_pos = css.find("--weg-size: 32px").unwrap();
+    assert!(base_pos < custom_pos);
+
+    // widgets without specific styles still get shared styles
+    let css = composition.css_for(&WidgetId::known_wm());
+    assert!(css.contains(".testbase {}") && css.contains(".testcustom {}"));
+
+    let conflicts: Vec<&str> = composition
+        .conflicts
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(conflicts, ["--accent", "--weg-size"]);
+    assert!(composition
+        .conflicts
+        .iter()
+        .all(|c| c.winner == ThemeId::from("@test/custom")));
+}
+
+#[test]
+fn should_use_unique_layer_names() {
+    let a = ThemeLayer::layer_name(&"@a/b-c".into());
+    let b = ThemeLayer::layer_name(&"@a/b_c".into());
+    assert_ne!(a, b);
+    assert!(a.starts_with("theme-_a_b_c-"));
+}
+
+#[test]
+fn should_skip_quarantined_themes() {
+    let base = composable_theme("@test/base", serde_json::json!([]), serde_json::json!({}));
+    let mut old = composable_theme("@test/old", serde_json::json!([]), serde_json::json!({}));
+    old.metadata.internal.compatibility = ResourceCompatibility::Incompatible;
+    let themes = vec![base, old];
+    let active: Vec<ThemeId> = vec!["@test/base".into(), "@test/old".into()];
+
+    let composition = ThemeComposition::compose(&themes, &active, &Default::default());
+    assert_eq!(composition.quarantined, vec![ThemeId::from("@test/old")]);
+    assert_eq!(composition.layers.len(), 1);
+    assert!(!composition
+        .css_for(&WidgetId::known_weg())
+        .contains(".testold"));
+}
+
+#[test]
+fn should_load_resources_targeting_the_library_version() -> Result<()> {
+    crate::resource::init_app_version()?;
+    let dir = std::env::temp_dir().join(format!("slu-theme-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("metadata.yml"),
+        "id: '@test/current'\nmetadata:\n  appTargetVersion: '>=2.3, <3'\n",
+    )?;
+
+    let theme = Theme::load(&dir)?;
+    assert_eq!(
+        theme.metadata.internal.compatibility,
+        ResourceCompatibility::Compatible
+    );
+    assert!(!theme.metadata.is_quarantined());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn should_load_theme_partially_on_scss_errors() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("slu-theme-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("user"))?;
+    std::fs::write(dir.join("metadata.yml"), "id: '@test/partial'\n")?;
+    std::fs::write(dir.join("user/ok.scss"), "a { b { color: red; } }")?;
+    std::fs::write(dir.join("user/broken.scss"), "a { color: $missing; }")?;
+
+    let theme = Theme::load(&dir)?;
+    assert!(theme.styles.contains_key(&WidgetId::from("@user/ok")));
+    assert!(!theme.styles.contains_key(&WidgetId::from("@user/broken")));
+    assert_eq!(theme.diagnostics.len(), 1);
+    assert_eq!(theme.diagnostics[0].file, dir.join("user/broken.scss"));
+
+    // diagnostics are not written back to the theme file
+    theme.save()?;
+    let saved = std::fs::read_to_string(dir.join("metadata.yml"))?;
+    assert!(!saved.contains("diagnostics"), "{saved}");
+
+    // broken styles included from the metadata file are dropped too
+    let included = dir.join("included");
+    std::fs::create_dir_all(&included)?;
+    std::fs::write(
+        included.join("metadata.yml"),
+        "id: '@test/included'\nstyles:\n  '@user/fine': !include ok.scss\n  '@user/broken': !include broken.scss\n",
+    )?;
+    std::fs::write(included.join("ok.scss"), "a { b { color: red; } }")?;
+    std::fs::write(included.join("broken.scss"), "a { color: $missing; }")?;
+    let theme = Theme::load(&included)?;
+    assert!(theme.styles.contains_key(&WidgetId::from("@user/fine")));
+    assert!(!theme.styles.contains_key(&WidgetId::from("@user/broken")));
+    assert_eq!(theme.diagnostics.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,482 @@
+use std::sync::LazyLock;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    resource::ThemeId,
+    state::{
+        by_theme::ThemeSettings,
+        config::{CssVariableName, ThemeVariableDefinition},
+        Theme,
+    },
+};
+
+/// Length units allowed on `<length>` variables.
+pub static CSS_LENGTH_UNITS: &[&str] = &[
+    "px", "%", "em", "rem", "ex", "ch", "cap", "ic", "lh", "rlh", "vw", "vh", "vi", "vb", "vmin",
+    "vmax", "svw", "svh", "lvw", "lvh", "dvw", "dvh", "cqw", "cqh", "cqi", "cqb", "cqmin", "cqmax",
+    "cm", "mm", "q", "in", "pt", "pc",
+];
+
+/// Named colors of CSS Color Level 4.
+pub static CSS_NAMED_COLORS: &[&str] = &[
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peachpuff",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+static COLOR_HEX_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^#(?:[0-9a-fA-F]{3}|[0-9a-fA-F]{4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$")
+        .unwrap()
+});
+
+static COLOR_FN_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^(?i:rgba?|hsla?|hwb|lab|lch|oklab|oklch|color)\(\s*[\w\s.,%/+\-]*\)$")
+        .unwrap()
+});
+
+/// `var(--name)` or `var(--name, fallback)`
+static CSS_VAR_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^(?i:var)\(\s*--[a-zA-Z_][\w-]*\s*(?:,(.*))?\)$").unwrap()
+});
+
+static LENGTH_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^([+-]?(?:\d+\.?\d*|\.\d+)(?:[eE][+-]?\d+)?)([a-zA-Z%]*)$").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum ThemeVariableValueSource {
+    /// value defined by the theme as `initialValue`
+    Initial,
+    /// value set by the user on settings
+    User,
+}
+
+/// Value rejected while compiling the theme variables
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedThemeVariable {
+    pub name: String,
+    pub value: String,
+    pub source: ThemeVariableValueSource,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct CompiledThemeVariables {
+    pub theme: ThemeId,
+    /// Validated variables as `(name, css value)` in declaration order
+    pub variables: Vec<(CssVariableName, String)>,
+    /// Invalid values, these were dropped from the output
+    pub rejected: Vec<RejectedThemeVariable>,
+}
+
+impl CompiledThemeVariables {
+    /// Css block to be added to the document, ex: `:root { --var: 10px; }`
+    pub fn to_css(&self) -> String {
+        let mut css = format!("/* {} */\n:root {{\n", self.theme);
+        for (name, value) in &self.variables {
+            css.push_str(&format!("  {name}: {value};\n"));
+        }
+        css.push('}');
+        css
+    }
+}
+
+fn escape_css_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\a "),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Removes the wrapping quotes of a css string if present.
+fn unquote(value: &str) -> &str {
+    let bytes = value.as_bytes();
+    if value.len() >= 2
+        && (bytes[0] == b'"' || bytes[0] == b'\'')
+        && bytes[value.len() - 1] == bytes[0]
+    {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+fn number_to_css(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    let n: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{value}' is not a number"))?;
+    if !n.is_finite() {
+        return Err(format!("'{value}' is not a finite number"));
+    }
+    Ok(n)
+}
+
+fn check_options<T: PartialEq>(value: &T, options: &Option<Vec<T>>) -> Result<(), String> {
+    match options {
+        Some(options) if !options.is_empty() && !options.contains(value) => {
+            Err("value is not one of the allowed options".to_owned())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Validates a `<color>` value and returns it as css, `var()` references are
+/// accepted if their fallback is also a valid color.
+pub fn validate_css_color(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if COLOR_HEX_REGEX.is_match(value) || COLOR_FN_REGEX.is_match(value) {
+        return Ok(value.to_owned());
+    }
+    if let Some(captures) = CSS_VAR_REGEX.captures(value) {
+        if let Some(fallback) = captures.get(1) {
+            validate_css_color(fallback.as_str())?;
+        }
+        return Ok(value.to_owned());
+    }
+    let lowercase = value.to_ascii_lowercase();
+    match lowercase.as_str() {
+        "transparent" | "currentcolor" => Ok(value.to_owned()),
+        name if CSS_NAMED_COLORS.contains(&name) => Ok(value.to_owned()),
+        _ => Err(format!("'{value}' is not a valid color")),
+    }
+}
+
+/// Validates a `<length>` value and returns it as css
+pub fn validate_css_length(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let captures = LENGTH_REGEX
+        .captures(value)
+        .ok_or_else(|| format!("'{value}' is not a valid length"))?;
+
+    let number = parse_number(&captures[1])?;
+    let unit = captures[2].to_ascii_lowercase();
+    if unit.is_empty() {
+        // unitless lengths are only valid for zero
+        return match number == 0.0 {
+            true => Ok("0".to_owned()),
+            false => Err(format!("'{value}' is missing the length unit")),
+        };
+    }
+    if !CSS_LENGTH_UNITS.contains(&unit.as_str()) {
+        return Err(format!("'{unit}' is not a valid length unit"));
+    }
+    Ok(format!("{}{unit}", number_to_css(number)))
+}
+
+/// Validates a `<url>` value and returns it as css, ex: `url("https://example.com")`
+pub fn validate_css_url(value: &str) -> Result<String, String> {
+    let mut value = value.trim();
+    if let Some(inner) = value
+        .strip_prefix("url(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        value = inner.trim();
+    }
+    let value = unquote(value);
+    let url = url::Url::parse(value).map_err(|e| format!("'{value}' is not a valid url: {e}"))?;
+    if matches!(url.scheme(), "javascript" | "vbscript") {
+        return Err(format!("'{}' urls are not allowed", url.scheme()));
+    }
+    Ok(format!("url({})", escape_css_string(url.as_str())))
+}
+
+impl ThemeVariableDefinition {
+    pub fn name(&self) -> &CssVariableName {
+        match self {
+            ThemeVariableDefinition::String(def) => &def.name,
+            ThemeVariableDefinition::Color(def) => &def.name,
+            ThemeVariableDefinition::Length(def) => &def.name,
+            ThemeVariableDefinition::Number(def) => &def.name,
+            ThemeVariableDefinition::Url(def) => &def.name,
+        }
+    }
+
+    /// Initial value as it would be stored on user settings
+    pub fn initial_value(&self) -> String {
+        match self {
+            ThemeVariableDefinition::String(def) => def.initial_value.clone(),
+            ThemeVariableDefinition::Color(def) => def.initial_value.clone(),
+            ThemeVariableDefinition::Length(def) => {
+                format!(
+                    "{}{}",
+                    number_to_css(def.initial_value),
+                    def.initial_value_unit
+                )
+            }
+            ThemeVariableDefinition::Number(def) => number_to_css(def.initial_value),
+            ThemeVariableDefinition::Url(def) => def.initial_value.clone(),
+        }
+    }
+
+    /// Validates a raw value against the variable syntax and options,
+    /// returning the value ready to be used on css.
+    pub fn validate_value(&self, value: &str) -> Result<String, String> {
+        match self {
+            ThemeVariableDefinition::String(def) => {
+                let value = unquote(value);
+                check_options(&value.to_owned(), &def.options)?;
+                Ok(escape_css_string(value))
+            }
+            ThemeVariableDefinition::Color(def) => {
+                let css = validate_css_color(value)?;
+                if let Some(options) = &def.options {
+                    let matches = options.iter().any(|o| o.eq_ignore_ascii_case(&css));
+                    if !options.is_empty() && !matches {
+                        return Err("value is not one of the allowed options".to_owned());
+                    }
+                }
+                Ok(css)
+            }
+            ThemeVariableDefinition::Length(_) => validate_css_length(value),
+            ThemeVariableDefinition::Number(def) => {
+                let n = parse_number(value)?;
+                check_options(&n, &def.options)?;
+                Ok(number_to_css(n))
+            }
+            ThemeVariableDefinition::Url(def) => {
+                let css = validate_css_url(value)?;
+                if let Some(options) = &def.options {
+                    let allowed = options
+                        .iter()
+                        .filter_map(|o| validate_css_url(o).ok())
+                        .any(|o| o == css);
+                    if !options.is_empty() && !allowed {
+                        return Err("value is not one of the allowed options".to_owned());
+                    }
+                }
+                Ok(css)
+            }
+        }
+    }
+}
+
+impl Theme {
+    /// Merges the initial values of the theme settings declaration with the user overrides,
+    /// validating each value against its syntax. Invalid values are reported and dropped,
+    /// for invalid user overrides the initial value is used instead.
+    pub fn compile_variables(&self, user: Option<&ThemeSettings>) -> CompiledThemeVariables {
+        let mut compiled = CompiledThemeVariables {
+            theme: self.id.clone(),
+            ..Default::default()
+        };
+
+        let definitions = self.settings.variables();
+        for def in &definitions {
+            let name = def.name();
+            let initial = def.initial_value();
+
+            let user_value = user.and_then(|u| u.get(name));
+            if let Some(value) = user_value {
+                match def.validate_value(value) {
+                    Ok(css) => {
+                        compiled.variables.push((name.clone(), css));
+                        continue;
+                    }
+                    Err(reason) => compiled.rejected.push(RejectedThemeVariable {
+                        name: name.to_string(),
+                        value: value.clone(),
+                        source: ThemeVariableValueSource::User,
+                        reason,
+                    }),
+                }
+            }
+
+            match def.validate_value(&initial) {
+                Ok(css) => compiled.variables.push((name.clone(), css)),
+                Err(reason) => compiled.rejected.push(RejectedThemeVariable {
+                    name: name.to_string(),
+                    value: initial,
+                    source: ThemeVariableValueSource::Initial,
+                    reason,
+                }),
+            }
+        }
+
+        if let Some(user) = user {
+            let mut unknown: Vec<_> = user
+                .iter()
+                .filter(|(name, _)| !definitions.iter().any(|def| def.name() == *name))
+                .collect();
+            unknown.sort_by_key(|(name, _)| name.to_string());
+            for (name, value) in unknown {
+                compiled.rejected.push(RejectedThemeVariable {
+                    name: name.to_string(),
+                    value: value.clone(),
+                    source: ThemeVariableValueSource::User,
+                    reason: "variable is not declared by the theme".to_owned(),
+                });
+            }
+        }
+
+        compiled
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    state::{
+        PinnedWegItemData, WegAppGroupItem, WegItem, WegItemSubtype, WegItems,
+        WegPinnedItemsVisibility, WegTemporalItemsVisibility,
+    },
+    system_state::{MonitorId, UserAppWindow},
+};
+
+/// Order of the windows inside a weg item
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum WegWindowOrder {
+    /// Last active window first
+    #[default]
+    MostRecentlyUsed,
+    /// Same order as the windows were received, oldest first.
+    Creation,
+    Title,
+}
+
+#[derive(Debug, Clone)]
+pub struct WegGroupingOptions {
+    /// Monitor where the weg is displayed
+    pub monitor: MonitorId,
+    pub is_primary_monitor: bool,
+    /// With `OnMonitor` only the windows on `monitor` are grouped on temporal items,
+    /// pinned items keep the windows of all the monitors.
+    pub temporal_visibility: WegTemporalItemsVisibility,
+    /// With `WhenPrimary` pinned items without windows are hidden outside the primary monitor.
+    pub pinned_visibility: WegPinnedItemsVisibility,
+    pub order: WegWindowOrder,
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+}
+
+impl PinnedWegItemData {
+    fn can_group_windows(&self) -> bool {
+        matches!(
+            self.subtype,
+            WegItemSubtype::App | WegItemSubtype::UnknownV2_1_6
+        )
+    }
+
+    fn matches_umid(&self, window: &UserAppWindow) -> bool {
+        self.umid.is_some() && self.umid == window.umid
+    }
+
+    /// The stored path is only trusted when it doesn't need to be updated by umid.
+    fn matches_path(&self, exe: &Path) -> bool {
+        self.should_ensure_path() && same_path(&self.path, exe)
+    }
+
+    fn matches_exe(&self, exe: &Path) -> bool {
+        !self.relaunch_program.is_empty() && same_path(Path::new(&self.relaunch_program), exe)
+    }
+}
+
+fn window_exe(window: &UserAppWindow) -> Option<&PathBuf> {
+    window.process.path.as_ref()
+}
+
+fn group_item(window: &UserAppWindow, last_active: &HashMap<isize, u64>) -> WegAppGroupItem {
+    WegAppGroupItem {
+        handle: window.hwnd,
+        title: window.title.clone(),
+        is_iconic: window.is_iconic,
+        is_zoomed: window.is_zoomed,
+        last_active: last_active.get(&window.hwnd).copied().unwrap_or_default(),
+    }
+}
+
+fn sort_windows(windows: &mut [(usize, WegAppGroupItem)], order: WegWindowOrder) {
+    match order {
+        WegWindowOrder::MostRecentlyUsed => windows.sort_by(|(a_idx, a), (b_idx, b)| {
+            b.last_active.cmp(&a.last_active).then(a_idx.cmp(b_idx))
+        }),
+        WegWindowOrder::Creation => windows.sort_by_key(|(idx, _)| *idx),
+        WegWindowOrder::Title => windows.sort_by(|(a_idx, a), (b_idx, b)| {
+            a.title
+                .to_lowercase()
+                .cmp(&b.title.to_lowercase())
+                .then(a_idx.cmp(b_idx))
+        }),
+    }
+}
+
+impl WegItems {
+    /// Maps the open windows onto the weg items. Windows are attached by umid first, then by
+    /// the pinned path (when it is trustworthy, see [`PinnedWegItemData::should_ensure_path`])
+    /// and finally by the executable. Windows without item are grouped on temporal items.\
+    /// `last_active` contains the last activation timestamp by window handle.\
+    /// The result is meant to be displayed, it should not be saved.
+    pub fn group_windows(
+        &self,
+        windows: &[UserAppWindow],
+        last_active: &HashMap<isize, u64>,
+        options: &WegGroupingOptions,
+    ) -> WegItems {
+        let mut result = self.clone();
+        let mut pending: Vec<(usize, &UserAppWindow)> = windows.iter().enumerate().collect();
+
+        let mut grouped: HashMap<String, Vec<(usize, WegAppGroupItem)>> = HashMap::new();
+        let stages: [fn(&PinnedWegItemData, &UserAppWindow) -> bool; 3] = [
+            |item, w| item.matches_umid(w),
+            |item, w| window_exe(w).is_some_and(|exe| item.matches_path(exe)),
+            |item, w| window_exe(w).is_some_and(|exe| item.matches_exe(exe)),
+        ];
+        for matches in stages {
+            pending.retain(|(idx, window)| {
+                let owner = result
+                    .iter_data()
+                    .find(|item| item.can_group_windows() && matches(item, window));
+                match owner {
+                    Some(item) => {
+                        grouped
+                            .entry(item.id.clone())
+                            .or_default()
+                            .push((*idx, group_item(window, last_active)));
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+
+        // remaining windows are grouped on new temporal items, by umid or executable.
+        pending.retain(|(_, w)| {
+            options.temporal_visibility == WegTemporalItemsVisibility::All
+                || w.monitor == options.monitor
+        });
+        for (idx, window) in pending {
+            let key = window
+                .umid
+                .clone()
+                .or_else(|| window_exe(window).map(|p| p.to_string_lossy().to_lowercase()));
+            let Some(key) = key else {
+                continue;
+            };
+            let id = format!("temporal:{key}");
+            if !grouped.contains_key(&id) {
+                let path = window_exe(window).cloned().unwrap_or_default();
+                result.center.push(WegItem::Temporal(PinnedWegItemData {
+                    id: id.clone(),
+                    subtype: WegItemSubtype::App,
+                    umid: window.umid.clone(),
+                    relaunch_program: path.to_string_lossy().to_string(),
+                    path,
+                    display_name: window.app_name.clone(),
+                    ..Default::default()
+                }));
+            }
+            grouped
+                .entry(id)
+                .or_default()
+                .push((idx, group_item(window, last_active)));
+        }
+
+        let hide_pinned = options.pinned_visibility == WegPinnedItemsVisibility::WhenPrimary
+            && !options.is_primary_monitor;
+        for items in [&mut result.left, &mut result.center, &mut result.right] {
+            items.retain_mut(|item| {
+                let (data, pinned) = match item {
+                    WegItem::Pinned(data) => (data, true),
+                    WegItem::Temporal(data) => (data, false),
+                    _ => return true,
+                };
+                let mut windows = grouped.remove(&data.id).unwrap_or_default();
+                sort_windows(&mut windows, options.order);
+                data.windows = windows.into_iter().map(|(_, w)| w).collect();
+                match pinned {
+                    true => !hide_pinned || !data.windows.is_empty(),
+                    false => !data.windows.is_empty(),
+                }
+            });
+        }
+        result
+    }
+
+    fn iter_data(&self) -> impl Iterator<Item = &PinnedWegItemData> {
+        self.left
+            .iter()
+            .chain(&self.center)
+            .chain(&self.right)
+            .filter_map(|item| match item {
+                WegItem::Pinned(data) | WegItem::Temporal(data) => Some(data),
+                _ => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_state::ProcessInformation;
+
+    fn window(
+        hwnd: isize,
+        title: &str,
+        exe: &str,
+        umid: Option<&str>,
+        monitor: &str,
+    ) -> UserAppWindow {
+        UserAppWindow {
+            hwnd,
+            monitor: MonitorId(monitor.into()),
+            title: title.into(),
+            app_name: title.into(),
+            is_zoomed: false,
+            is_iconic: false,
+            is_fullscreen: false,
+            umid: umid.map(Into::into),
+            process: ProcessInformation {
+                id: hwnd as u32,
+                path: Some(exe.into()),
+            },
+        }
+    }
+
+    fn pinned(id: &str, path: &str, umid: Option<&str>) -> WegItem {
+        WegItem::Pinned(PinnedWegItemData {
+            id: id.into(),
+            subtype: WegItemSubtype::App,
+            umid: umid.map(Into::into),
+            path: path.into(),
+            relaunch_program: path.into(),
+            ..Default::default()
+        })
+    }
+
+    fn options(order: WegWindowOrder) -> WegGroupingOptions {
+        WegGroupingOptions {
+            monitor: MonitorId("main".into()),
+            is_primary_monitor: false,
+            temporal_visibility: WegTemporalItemsVisibility::OnMonitor,
+            pinned_visibility: WegPinnedItemsVisibility::WhenPrimary,
+            order,
+        }
+    }
+
+    fn windows_of(items: &WegItems, id: &str) -> Vec<isize> {
+        items
+            .iter_data()
+            .find(|d| d.id == id)
+            .map(|d| d.windows.iter().map(|w| w.handle).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn should_group_by_umid_then_path_and_apply_filters() {
+        let items = WegItems {
+            is_reorder_disabled: false,
+            left: vec![],
+            center: vec![
+                // path is outdated, but the umid still matches
+                pinned(
+                    "terminal",
+                    "C:\\old\\wt.exe",
+                    Some("Microsoft.WindowsTerminal"),
+                ),
+                pinned("code", "C:\\Apps\\Code.exe", None),
+                pinned("unused", "C:\\Apps\\unused.exe", None),
+            ],
+            right: vec![],
+            ..Default::default()
+        };
+        let windows = vec![
+            window(
+                1,
+                "b",
+                "C:\\new\\wt.exe",
+                Some("Microsoft.WindowsTerminal"),
+                "main",
+            ),
+            window(2, "a", "c:\\apps\\code.exe", None, "main"),
+            window(3, "c", "C:\\Apps\\Code.exe", None, "main"),
+            window(4, "notes", "C:\\Apps\\notes.exe", None, "main"),
+            window(5, "other", "C:\\Apps\\other.exe", None, "second"),
+            window(6, "d", "C:\\Apps\\Code.exe", None, "second"),
+        ];
+        let last_active = HashMap::from([(2, 10), (3, 30)]);
+
+        let grouped = items.group_windows(
+            &windows,
+            &last_active,
+            &options(WegWindowOrder::MostRecentlyUsed),
+        );
+        assert_eq!(windows_of(&grouped, "terminal"), [1]);
+        // pinned items keep the windows of other monitors
+        assert_eq!(windows_of(&grouped, "code"), [3, 2, 6]);
+        // not primary, so pinned items without windows are hidden
+        assert!(grouped.iter_data().all(|d| d.id != "unused"));
+        // temporal item for notes, the window on the other monitor is filtered
+        assert_eq!(grouped.center.len(), 3);
+        assert!(matches!(&grouped.center[2], WegItem::Temporal(d) if d.windows.len() == 1));
+
+        let grouped = items.group_windows(&windows, &last_active, &options(WegWindowOrder::Title));
+        assert_eq!(windows_of(&grouped, "code"), [2, 3, 6]);
+    }
+}
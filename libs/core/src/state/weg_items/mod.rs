@@ -1,9 +1,21 @@
-use std::{collections::HashSet, path::PathBuf};
+mod grouping;
+
+pub use grouping::*;
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::{
+    error::Result,
+    state::{keep_unique, ItemZone, MigrationRecord, ZonedItem, ZonedList, WEG_ITEMS_MIGRATIONS},
+};
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(default, rename_all = "camelCase")]
 pub struct WegAppGroupItem {
@@ -132,6 +144,40 @@ impl WegItem {
     }
 }
 
+impl ZonedItem for WegItem {
+    fn item_id(&self) -> String {
+        self.id().clone()
+    }
+
+    fn set_item_id(&mut self, id: String) {
+        self.set_id(id);
+    }
+
+    fn new_separator(id: String) -> Option<Self> {
+        Some(WegItem::Separator { id })
+    }
+
+    /// Unpinned items without open windows are removed.
+    fn set_pinned(&mut self, pinned: bool) -> Result<bool> {
+        let data = match self {
+            WegItem::Pinned(data) | WegItem::Temporal(data) => data,
+            _ => return Err("only apps, files and folders can be pinned".into()),
+        };
+        if data.pin_disabled {
+            return Err("the pinned state of this item can not be changed".into());
+        }
+        let data = std::mem::take(data);
+        if !pinned && data.windows.is_empty() {
+            return Ok(false);
+        }
+        *self = match pinned {
+            true => WegItem::Pinned(data),
+            false => WegItem::Temporal(data),
+        };
+        Ok(true)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(default, rename_all = "camelCase")]
 #[cfg_attr(feature = "gen-binds", ts(export))]
@@ -141,6 +187,8 @@ pub struct WegItems {
     pub left: Vec<WegItem>,
     pub center: Vec<WegItem>,
     pub right: Vec<WegItem>,
+    #[serde(flatten)]
+    pub migrations: MigrationRecord,
 }
 
 #[allow(deprecated)]
@@ -164,13 +212,14 @@ impl Default for WegItems {
                 pin_disabled: false,
             })],
             right: vec![WegItem::Media { id: String::new() }],
+            migrations: WEG_ITEMS_MIGRATIONS.record(),
         }
     }
 }
 
 #[allow(deprecated)]
 impl WegItems {
-    fn get_parts_of_deprecated_inline_command(cmd: &str) -> (String, String) {
+    pub(crate) fn get_parts_of_deprecated_inline_command(cmd: &str) -> (String, String) {
         let start_double_quoted = cmd.starts_with("\"");
         if start_double_quoted || cmd.starts_with("'") {
             let delimiter = if start_double_quoted { '"' } else { '\'' };
@@ -243,18 +292,20 @@ impl WegItems {
                 _ => {}
             }
 
-            if item.id().is_empty() {
-                item.set_id(uuid::Uuid::new_v4().to_string());
-            }
-
-            if !dict.contains(item.id()) {
-                dict.insert(item.id().clone());
+            if let Some(item) = keep_unique(dict, item) {
                 result.push(item);
             }
         }
         result
     }
 
+    /// Loads the items file (json or yaml), migrating it to the latest version.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut items: Self = WEG_ITEMS_MIGRATIONS.load_file(path)?;
+        items.sanitize();
+        Ok(items)
+    }
+
     pub fn sanitize(&mut self) {
         let mut dict = HashSet::new();
         self.left = Self::sanitize_items(&mut dict, std::mem::take(&mut self.left));
@@ -263,9 +314,33 @@ impl WegItems {
     }
 }
 
+impl ZonedList for WegItems {
+    type Item = WegItem;
+
+    fn is_reorder_disabled(&self) -> bool {
+        self.is_reorder_disabled
+    }
+
+    fn zone(&self, zone: ItemZone) -> &Vec<WegItem> {
+        match zone {
+            ItemZone::Left => &self.left,
+            ItemZone::Center => &self.center,
+            ItemZone::Right => &self.right,
+        }
+    }
+
+    fn zone_mut(&mut self, zone: ItemZone) -> &mut Vec<WegItem> {
+        match zone {
+            ItemZone::Left => &mut self.left,
+            ItemZone::Center => &mut self.center,
+            ItemZone::Right => &mut self.right,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::state::WegItems;
+    use crate::state::{ItemZone, WegItem, WegItems, ZonedListEditor};
 
     #[test]
     fn should_return_empty_response_for_empty_command() {
@@ -350,4 +425,33 @@ mod tests {
         assert_eq!(program, "node");
         assert_eq!(args, "\"arg with spaces\" 'another arg' --flag=\"value\"");
     }
+
+    #[test]
+    fn should_move_pin_and_undo_keeping_unique_ids() {
+        let mut items = WegItems::default();
+        items.left[0] = WegItem::StartMenu { id: "start".into() };
+        items.right.push(items.left[0].clone());
+        let mut editor = ZonedListEditor::new(items);
+        // duplicated start menu removed
+        assert_eq!(editor.list().right.len(), 1);
+
+        let explorer = editor.list().center[0].id().clone();
+        editor.move_item(&explorer, ItemZone::Left, 0).unwrap();
+        assert_eq!(editor.list().left[0].id(), &explorer);
+        assert!(editor.list().center.is_empty());
+
+        let separator = editor.insert_separator(ItemZone::Left, 1).unwrap();
+        assert!(matches!(&editor.list().left[1], WegItem::Separator { id } if *id == separator));
+
+        // pinned without windows is dropped when unpinned
+        editor.unpin(&explorer).unwrap();
+        assert_eq!(editor.list().left.len(), 2);
+        assert!(editor.move_item("unknown", ItemZone::Right, 0).is_err());
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(!editor.undo());
+        assert_eq!(editor.list().center[0].id(), &explorer);
+    }
 }
@@ -6,6 +6,8 @@ use ts_rs::TS;
 
 use crate::resource::ResourceText;
 
+use super::WidgetSettingValue;
+
 /// Wsd = Widget Settings Declaration
 macro_rules! wsd_item {
     (
@@ -94,6 +96,22 @@ macro_rules! wsd_item {
                         )*
                     }
                 }
+
+                pub fn allow_set_by_monitor(&self) -> bool {
+                    match self {
+                        $(
+                            WsdItem::$name(item) => item.allow_set_by_monitor,
+                        )*
+                    }
+                }
+
+                pub fn dependencies(&self) -> &[String] {
+                    match self {
+                        $(
+                            WsdItem::$name(item) => &item.dependencies,
+                        )*
+                    }
+                }
             }
         }
     };
@@ -149,6 +167,73 @@ wsd_item! {
     }
 }
 
+fn is_color(value: &str, allow_alpha: bool) -> bool {
+    let Some(hex) = value.strip_prefix('#') else {
+        return false;
+    };
+    let valid_len = match allow_alpha {
+        true => matches!(hex.len(), 3 | 4 | 6 | 8),
+        false => matches!(hex.len(), 3 | 6),
+    };
+    valid_len && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl WsdItem {
+    pub fn default_value(&self) -> WidgetSettingValue {
+        match self {
+            WsdItem::Switch(item) => WidgetSettingValue::Bool(item.default_value),
+            WsdItem::Select(item) => WidgetSettingValue::Text(item.default_value.clone()),
+            WsdItem::InputText(item) => WidgetSettingValue::Text(item.default_value.clone()),
+            WsdItem::InputNumber(item) => WidgetSettingValue::Number(item.default_value),
+            WsdItem::Range(item) => WidgetSettingValue::Number(item.default_value),
+            WsdItem::Color(item) => WidgetSettingValue::Text(item.default_value.clone()),
+        }
+    }
+
+    /// Type checks the stored value against the declaration.
+    pub fn check(
+        &self,
+        value: &serde_json::Value,
+    ) -> std::result::Result<WidgetSettingValue, String> {
+        use serde_json::Value;
+        match (self, value) {
+            (WsdItem::Switch(_), Value::Bool(b)) => Ok(WidgetSettingValue::Bool(*b)),
+            (WsdItem::Select(item), Value::String(s)) => {
+                match item.options.iter().any(|o| &o.value == s) {
+                    true => Ok(WidgetSettingValue::Text(s.clone())),
+                    false => Err(format!("`{s}` is not an option")),
+                }
+            }
+            (WsdItem::InputText(_), Value::String(s)) => Ok(WidgetSettingValue::Text(s.clone())),
+            (WsdItem::InputNumber(item), Value::Number(n)) => {
+                let n = n.as_f64().unwrap_or_default();
+                if item.min.is_some_and(|min| n < min) || item.max.is_some_and(|max| n > max) {
+                    return Err(format!("{n} is out of bounds"));
+                }
+                Ok(WidgetSettingValue::Number(n))
+            }
+            (WsdItem::Range(item), Value::Number(n)) => {
+                let n = n.as_f64().unwrap_or_default();
+                if n < item.from || n > item.to {
+                    return Err(format!("{n} is out of {}..{}", item.from, item.to));
+                }
+                if let Some(step) = item.step.filter(|s| *s > 0.0) {
+                    let steps = (n - item.from) / step;
+                    if (steps - steps.round()).abs() > 1e-9 {
+                        return Err(format!("{n} is not a multiple of the step {step}"));
+                    }
+                }
+                Ok(WidgetSettingValue::Number(n))
+            }
+            (WsdItem::Color(item), Value::String(s)) => match is_color(s, item.allow_alpha) {
+                true => Ok(WidgetSettingValue::Text(s.clone())),
+                false => Err(format!("`{s}` is not a valid color")),
+            },
+            (_, value) => Err(format!("unexpected value {value}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, TS)]
 #[serde(rename_all = "camelCase")]
 pub struct WsdGroupEntry {
@@ -200,6 +285,25 @@ impl WsdGroup {
 pub struct WidgetSettingsDeclarationList(Vec<WsdGroup>);
 
 impl WidgetSettingsDeclarationList {
+    /// All the declared items, on duplicated keys the first one is kept.
+    pub fn items(&self) -> Vec<&WsdItem> {
+        fn collect<'a>(entry: &'a WsdGroupEntry, items: &mut Vec<&'a WsdItem>) {
+            items.push(&entry.config);
+            for child in &entry.children {
+                collect(child, items);
+            }
+        }
+        let mut items = Vec::new();
+        for group in &self.0 {
+            for entry in &group.group {
+                collect(entry, &mut items);
+            }
+        }
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item.get_key()));
+        items
+    }
+
     pub fn there_are_duplicates(&self) -> bool {
         let mut seen: HashSet<&str> = HashSet::new();
 
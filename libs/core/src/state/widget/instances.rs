@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    resource::WidgetId,
+    state::{by_widget::ThirdPartyWidgetSettings, Settings},
+    system_state::{MonitorId, PhysicalMonitor},
+};
+
+use super::{Widget, WidgetInstanceMode, WidgetPreset, WidgetSettingValue};
+
+/// Identifies a running instance of a widget
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetInstanceKey {
+    pub widget_id: WidgetId,
+    /// Only present on widgets with multiple instances
+    pub instance_id: Option<Uuid>,
+    /// Only present on widgets replicated by monitor
+    pub monitor_id: Option<MonitorId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct WidgetInstance {
+    pub key: WidgetInstanceKey,
+    pub preset: WidgetPreset,
+    /// Resolved settings of the instance, see [`super::ResolvedWidgetSettings`]
+    pub settings: BTreeMap<String, WidgetSettingValue>,
+}
+
+/// Changes to apply on the running instances
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct WidgetInstancesDiff {
+    pub create: Vec<WidgetInstance>,
+    /// Instances whose preset or settings changed
+    pub update: Vec<WidgetInstance>,
+    pub destroy: Vec<WidgetInstanceKey>,
+}
+
+impl WidgetInstancesDiff {
+    pub fn between(current: &[WidgetInstance], desired: &[WidgetInstance]) -> Self {
+        let current_by_key: HashMap<&WidgetInstanceKey, &WidgetInstance> =
+            current.iter().map(|i| (&i.key, i)).collect();
+        let desired_by_key: HashMap<&WidgetInstanceKey, &WidgetInstance> =
+            desired.iter().map(|i| (&i.key, i)).collect();
+
+        let mut diff = Self::default();
+        for instance in desired {
+            match current_by_key.get(&instance.key) {
+                None => diff.create.push(instance.clone()),
+                Some(current) if *current != instance => diff.update.push(instance.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.destroy = current
+            .iter()
+            .filter(|i| !desired_by_key.contains_key(&i.key))
+            .map(|i| i.key.clone())
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.destroy.is_empty()
+    }
+}
+
+/// Computes the instances that should exist for each widget:
+/// - disabled and quarantined widgets have no instances.
+/// - `Single` (and popups) have one instance.
+/// - `Multiple` have one instance per stored `$instances` entry, or a default one if there is none.
+/// - `ReplicaByMonitor` have one instance per monitor where the widget is enabled.
+pub fn desired_widget_instances(
+    widgets: &[Widget],
+    settings: &Settings,
+    monitors: &[PhysicalMonitor],
+) -> Vec<WidgetInstance> {
+    let default_settings = ThirdPartyWidgetSettings::default();
+    let mut desired = Vec::new();
+
+    for widget in widgets {
+        if !settings.is_widget_enabled(&widget.id) || widget.metadata.is_quarantined() {
+            continue;
+        }
+        let root = settings
+            .by_widget
+            .others
+            .get(&widget.id)
+            .unwrap_or(&default_settings);
+
+        let instance = |instance_id: Option<Uuid>, monitor_id: Option<&MonitorId>| {
+            let by_monitor = monitor_id
+                .and_then(|id| settings.monitors_v3.get(&id.0))
+                .and_then(|config| config.by_widget.get(&widget.id));
+            WidgetInstance {
+                key: WidgetInstanceKey {
+                    widget_id: widget.id.clone(),
+                    instance_id,
+                    monitor_id: monitor_id.cloned(),
+                },
+                preset: widget.preset,
+                settings: widget
+                    .settings
+                    .resolve(root, instance_id.as_ref(), by_monitor)
+                    .values,
+            }
+        };
+
+        match widget.instance_mode() {
+            WidgetInstanceMode::Single => desired.push(instance(None, None)),
+            WidgetInstanceMode::Multiple => {
+                let mut ids: Vec<Uuid> = root
+                    .instances
+                    .iter()
+                    .flat_map(|instances| instances.keys().copied())
+                    .collect();
+                ids.sort();
+                match ids.is_empty() {
+                    true => desired.push(instance(None, None)),
+                    false => desired.extend(ids.into_iter().map(|id| instance(Some(id), None))),
+                }
+            }
+            WidgetInstanceMode::ReplicaByMonitor => {
+                for monitor in monitors {
+                    let enabled_on_monitor = settings
+                        .monitors_v3
+                        .get(&monitor.id.0)
+                        .is_none_or(|config| config.by_widget.is_widget_enabled(&widget.id));
+                    if enabled_on_monitor {
+                        desired.push(instance(None, Some(&monitor.id)));
+                    }
+                }
+            }
+        }
+    }
+    desired
+}
+
+/// Keeps the last applied instances to report only the changes on each reconciliation.
+#[derive(Debug, Default)]
+pub struct WidgetInstanceReconciler {
+    current: Vec<WidgetInstance>,
+}
+
+impl WidgetInstanceReconciler {
+    pub fn current(&self) -> &[WidgetInstance] {
+        &self.current
+    }
+
+    pub fn reconcile(
+        &mut self,
+        widgets: &[Widget],
+        settings: &Settings,
+        monitors: &[PhysicalMonitor],
+    ) -> WidgetInstancesDiff {
+        let desired = desired_widget_instances(widgets, settings, monitors);
+        let diff = WidgetInstancesDiff::between(&self.current, &desired);
+        self.current = desired;
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{error::Result, rect::Rect};
+
+    fn monitor(id: &str) -> PhysicalMonitor {
+        PhysicalMonitor {
+            id: MonitorId(id.into()),
+            name: id.into(),
+            rect: Rect::default(),
+            dpi: 1.0,
+            is_primary: false,
+        }
+    }
+
+    fn widget(id: &str, instances: WidgetInstanceMode, preset: WidgetPreset) -> Result<Widget> {
+        Ok(Widget {
+            id: id.into(),
+            instances,
+            preset,
+            settings: serde_json::from_value(json!([{
+                "group": [{ "config": { "type": "switch", "key": "compact", "label": "Compact", "allowSetByMonitor": true } }]
+            }]))?,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn should_diff_desired_instances() -> Result<()> {
+        let first = Uuid::new_v4();
+        let widgets = vec![
+            widget(
+                "@user/clock",
+                WidgetInstanceMode::ReplicaByMonitor,
+                WidgetPreset::Desktop,
+            )?,
+            widget(
+                "@user/notes",
+                WidgetInstanceMode::Multiple,
+                WidgetPreset::Desktop,
+            )?,
+            // popups are always single instance
+            widget(
+                "@user/menu",
+                WidgetInstanceMode::Multiple,
+                WidgetPreset::Popup,
+            )?,
+            widget(
+                "@user/disabled",
+                WidgetInstanceMode::Single,
+                WidgetPreset::None,
+            )?,
+        ];
+        let mut settings: Settings = serde_json::from_value(json!({
+            "byWidget": {
+                "@user/clock": { "enabled": true },
+                "@user/notes": { "enabled": true, "$instances": { first.to_string(): {} } },
+                "@user/menu": { "enabled": true },
+            },
+            "monitorsV3": {
+                "B": { "byWidget": { "@user/clock": { "enabled": false } } }
+            }
+        }))?;
+        let monitors = vec![monitor("A"), monitor("B")];
+
+        let mut reconciler = WidgetInstanceReconciler::default();
+        let diff = reconciler.reconcile(&widgets, &settings, &monitors);
+        let created: Vec<(&str, Option<Uuid>, Option<&str>)> = diff
+            .create
+            .iter()
+            .map(|i| {
+                let k = &i.key;
+                (
+                    k.widget_id.as_str(),
+                    k.instance_id,
+                    k.monitor_id.as_ref().map(|m| m.0.as_str()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            created,
+            [
+                ("@user/clock", None, Some("A")),
+                ("@user/notes", Some(first), None),
+                ("@user/menu", None, None),
+            ]
+        );
+        assert!(reconciler
+            .reconcile(&widgets, &settings, &monitors)
+            .is_empty());
+
+        // monitor settings changed and a monitor was disconnected
+        settings.monitors_v3 = serde_json::from_value(json!({
+            "A": { "byWidget": { "@user/clock": { "enabled": true, "compact": true } } }
+        }))?;
+        let diff = reconciler.reconcile(&widgets, &settings, &monitors[..1]);
+        assert!(diff.create.is_empty());
+        assert_eq!(diff.update.len(), 1);
+        assert_eq!(
+            diff.update[0].settings["compact"],
+            WidgetSettingValue::Bool(true)
+        );
+
+        settings.set_widget_enabled(&"@user/notes".into(), false);
+        let diff = reconciler.reconcile(&widgets, &settings, &monitors[..1]);
+        assert_eq!(diff.destroy.len(), 1);
+        assert_eq!(diff.destroy[0].instance_id, Some(first));
+        Ok(())
+    }
+}
@@ -1,4 +1,11 @@
 pub mod declaration;
+mod instances;
+mod positioning;
+mod resolver;
+
+pub use instances::*;
+pub use positioning::*;
+pub use resolver::*;
 
 use std::{collections::HashMap, path::Path};
 
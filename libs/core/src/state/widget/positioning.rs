@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    rect::Rect,
+    system_state::{MonitorId, PhysicalMonitor},
+};
+
+use super::{Alignment, WidgetTriggerPayload};
+
+/// Popup to be placed, the anchor is in physical pixels and the size in logical pixels.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct PopupPlacementRequest {
+    pub anchor: (i32, i32),
+    pub width: f64,
+    pub height: f64,
+    /// Same meaning as on [`WidgetTriggerPayload`], `None` is the same as `End`.
+    pub align_x: Option<Alignment>,
+    pub align_y: Option<Alignment>,
+    pub monitor_id: Option<MonitorId>,
+}
+
+impl PopupPlacementRequest {
+    /// `None` if the payload has no desired position
+    pub fn from_payload(payload: &WidgetTriggerPayload, width: f64, height: f64) -> Option<Self> {
+        let (x, y) = payload.desired_position?;
+        Some(Self {
+            anchor: (x, y),
+            width,
+            height,
+            align_x: payload.align_x,
+            align_y: payload.align_y,
+            monitor_id: payload.monitor_id.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct PopupPlacement {
+    /// Final rect in physical pixels
+    pub rect: Rect,
+    pub monitor_id: MonitorId,
+    /// The alignment was inverted to avoid overflowing the work area
+    pub flipped_x: bool,
+    pub flipped_y: bool,
+}
+
+fn contains(rect: &Rect, (x, y): (i32, i32)) -> bool {
+    rect.left <= x && x < rect.right && rect.top <= y && y < rect.bottom
+}
+
+fn distance_to(rect: &Rect, (x, y): (i32, i32)) -> i64 {
+    let dx = (rect.left - x).max(0).max(x - rect.right) as i64;
+    let dy = (rect.top - y).max(0).max(y - rect.bottom) as i64;
+    dx * dx + dy * dy
+}
+
+fn intersects(a: &Rect, b: &Rect) -> bool {
+    a.left < b.right && b.left < a.right && a.top < b.bottom && b.top < a.bottom
+}
+
+/// The requested monitor, else the one containing the anchor or the nearest one.
+fn pick_monitor<'a>(
+    request: &PopupPlacementRequest,
+    monitors: &'a [PhysicalMonitor],
+) -> Option<&'a PhysicalMonitor> {
+    if let Some(id) = &request.monitor_id {
+        if let Some(monitor) = monitors.iter().find(|m| &m.id == id) {
+            return Some(monitor);
+        }
+    }
+    monitors
+        .iter()
+        .find(|m| contains(&m.rect, request.anchor))
+        .or_else(|| {
+            monitors
+                .iter()
+                .min_by_key(|m| distance_to(&m.rect, request.anchor))
+        })
+}
+
+/// Monitor rect minus the exclusion zones, each zone is treated as docked to the nearest
+/// monitor edge along its longer side (as the toolbar and the weg are).
+pub fn work_area(monitor: &Rect, exclusions: &[Rect]) -> Rect {
+    let mut area = monitor.clone();
+    for zone in exclusions.iter().filter(|zone| intersects(monitor, zone)) {
+        let horizontal = zone.right - zone.left >= zone.bottom - zone.top;
+        match horizontal {
+            true if zone.top - monitor.top <= monitor.bottom - zone.bottom => {
+                area.top = area.top.max(zone.bottom)
+            }
+            true => area.bottom = area.bottom.min(zone.top),
+            false if zone.left - monitor.left <= monitor.right - zone.right => {
+                area.left = area.left.max(zone.right)
+            }
+            false => area.right = area.right.min(zone.left),
+        }
+    }
+    area
+}
+
+/// Places a segment of `size` around `anchor` inside `min..max`.
+/// Flips to the opposite side if the preferred one overflows and the other fits,
+/// then shifts the segment to keep it inside the bounds.
+fn place_axis(anchor: i32, size: i32, align: Option<Alignment>, min: i32, max: i32) -> (i32, bool) {
+    let start = anchor - size;
+    let end = anchor;
+    let fits = |pos: i32| pos >= min && pos + size <= max;
+
+    let (position, flipped) = match align {
+        Some(Alignment::Center) => (anchor - size / 2, false),
+        Some(Alignment::Start) if !fits(start) && fits(end) => (end, true),
+        Some(Alignment::Start) => (start, false),
+        _ if !fits(end) && fits(start) => (start, true),
+        _ => (end, false),
+    };
+
+    let shifted = match size > max - min {
+        true => min,
+        false => position.clamp(min, max - size),
+    };
+    (shifted, flipped)
+}
+
+/// Solves the final rect of a popup, `exclusions` are the zones reserved by the
+/// toolbar, weg and others app bars, in physical pixels.\
+/// `PhysicalMonitor::dpi` is used as the scale factor of the monitor (1.0 = 96 dpi).
+pub fn solve_popup_placement(
+    request: &PopupPlacementRequest,
+    monitors: &[PhysicalMonitor],
+    exclusions: &[Rect],
+) -> Option<PopupPlacement> {
+    let monitor = pick_monitor(request, monitors)?;
+    let area = work_area(&monitor.rect, exclusions);
+    let scale = if monitor.dpi > 0.0 { monitor.dpi } else { 1.0 };
+    let width = (request.width * scale).round() as i32;
+    let height = (request.height * scale).round() as i32;
+
+    let (x, flipped_x) = place_axis(
+        request.anchor.0,
+        width,
+        request.align_x,
+        area.left,
+        area.right,
+    );
+    let (y, flipped_y) = place_axis(
+        request.anchor.1,
+        height,
+        request.align_y,
+        area.top,
+        area.bottom,
+    );
+
+    Some(PopupPlacement {
+        rect: Rect {
+            left: x,
+            top: y,
+            right: x + width,
+            bottom: y + height,
+        },
+        monitor_id: monitor.id.clone(),
+        flipped_x,
+        flipped_y,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(id: &str, left: i32, dpi: f64) -> PhysicalMonitor {
+        PhysicalMonitor {
+            id: MonitorId(id.into()),
+            name: id.into(),
+            rect: Rect {
+                left,
+                top: 0,
+                right: left + 1920,
+                bottom: 1080,
+            },
+            dpi,
+            is_primary: left == 0,
+        }
+    }
+
+    fn request(anchor: (i32, i32), align_y: Alignment) -> PopupPlacementRequest {
+        PopupPlacementRequest {
+            anchor,
+            width: 300.0,
+            height: 200.0,
+            align_x: Some(Alignment::Center),
+            align_y: Some(align_y),
+            monitor_id: None,
+        }
+    }
+
+    #[test]
+    fn should_flip_shift_and_avoid_exclusions() {
+        let monitors = vec![monitor("A", 0, 1.0), monitor("B", 1920, 1.5)];
+        // toolbar on top of A and weg at the bottom of B, not full width
+        let exclusions = vec![
+            Rect {
+                left: 0,
+                top: 0,
+                right: 1920,
+                bottom: 30,
+            },
+            Rect {
+                left: 2600,
+                top: 1020,
+                right: 3200,
+                bottom: 1080,
+            },
+        ];
+
+        // opened from the toolbar, asking to be placed over the anchor: flipped below the toolbar
+        let placement =
+            solve_popup_placement(&request((10, 30), Alignment::Start), &monitors, &exclusions)
+                .unwrap();
+        assert!(placement.flipped_y);
+        assert_eq!(
+            placement.rect,
+            Rect {
+                left: 0,
+                top: 30,
+                right: 300,
+                bottom: 230
+            }
+        );
+
+        // opened from the weg on a scaled monitor, asking to be placed below the anchor
+        let placement = solve_popup_placement(
+            &request((3830, 1020), Alignment::End),
+            &monitors,
+            &exclusions,
+        )
+        .unwrap();
+        assert_eq!(placement.monitor_id, MonitorId("B".into()));
+        assert!(placement.flipped_y);
+        assert_eq!(
+            placement.rect,
+            Rect {
+                left: 3390,
+                top: 720,
+                right: 3840,
+                bottom: 1020
+            }
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{state::by_widget::ThirdPartyWidgetSettings, utils::TsUnknown};
+
+use super::declaration::{WidgetSettingsDeclarationList, WsdItem};
+
+/// Value of a widget setting after being checked against its declaration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, TS)]
+#[serde(untagged)]
+pub enum WidgetSettingValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl WidgetSettingValue {
+    /// Same as js `!!value`
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            WidgetSettingValue::Bool(b) => *b,
+            WidgetSettingValue::Number(n) => *n != 0.0 && !n.is_nan(),
+            WidgetSettingValue::Text(s) => !s.is_empty(),
+        }
+    }
+}
+
+/// Where a setting value comes from, sorted from lower to higher priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum WidgetSettingSource {
+    Default,
+    Root,
+    Instance,
+    Monitor,
+    MonitorInstance,
+}
+
+impl WidgetSettingSource {
+    fn is_by_monitor(&self) -> bool {
+        matches!(self, Self::Monitor | Self::MonitorInstance)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedWidgetSetting {
+    pub key: String,
+    pub source: WidgetSettingSource,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct ResolvedWidgetSettings {
+    /// Value of every declared setting
+    pub values: BTreeMap<String, WidgetSettingValue>,
+    pub sources: BTreeMap<String, WidgetSettingSource>,
+    /// Keys whose dependencies are not set, the value is kept but should be ignored.
+    pub disabled: Vec<String>,
+    /// Stored values ignored because they are unknown, invalid or not allowed on its source.
+    pub rejected: Vec<RejectedWidgetSetting>,
+}
+
+impl ResolvedWidgetSettings {
+    pub fn get(&self, key: &str) -> Option<&WidgetSettingValue> {
+        self.values.get(key)
+    }
+
+    pub fn is_enabled(&self, key: &str) -> bool {
+        self.values.contains_key(key) && !self.disabled.iter().any(|k| k == key)
+    }
+}
+
+fn instance_values<'a>(
+    settings: &'a ThirdPartyWidgetSettings,
+    instance: Option<&Uuid>,
+) -> Option<&'a HashMap<String, TsUnknown>> {
+    settings.instances.as_ref()?.get(instance?)
+}
+
+impl WidgetSettingsDeclarationList {
+    /// Merges the declaration defaults with the stored values, in order: root, instance,
+    /// monitor and monitor instance. Monitor values are only used on items that allow it.
+    pub fn resolve(
+        &self,
+        root: &ThirdPartyWidgetSettings,
+        instance: Option<&Uuid>,
+        monitor: Option<&ThirdPartyWidgetSettings>,
+    ) -> ResolvedWidgetSettings {
+        let items: HashMap<&str, &WsdItem> =
+            self.items().into_iter().map(|i| (i.get_key(), i)).collect();
+
+        let mut resolved = ResolvedWidgetSettings::default();
+        for (key, item) in &items {
+            resolved
+                .values
+                .insert(key.to_string(), item.default_value());
+            resolved
+                .sources
+                .insert(key.to_string(), WidgetSettingSource::Default);
+        }
+
+        let layers = [
+            (WidgetSettingSource::Root, Some(&root.rest)),
+            (
+                WidgetSettingSource::Instance,
+                instance_values(root, instance),
+            ),
+            (WidgetSettingSource::Monitor, monitor.map(|m| &m.rest)),
+            (
+                WidgetSettingSource::MonitorInstance,
+                monitor.and_then(|m| instance_values(m, instance)),
+            ),
+        ];
+        for (source, values) in layers {
+            let Some(values) = values else {
+                continue;
+            };
+            let mut values: Vec<_> = values.iter().collect();
+            values.sort_by_key(|(key, _)| key.as_str());
+
+            for (key, value) in values {
+                let reject = |reason: String| RejectedWidgetSetting {
+                    key: key.clone(),
+                    source,
+                    reason,
+                };
+                let Some(item) = items.get(key.as_str()) else {
+                    resolved.rejected.push(reject("not declared".to_owned()));
+                    continue;
+                };
+                if source.is_by_monitor() && !item.allow_set_by_monitor() {
+                    resolved
+                        .rejected
+                        .push(reject("can not be set by monitor".to_owned()));
+                    continue;
+                }
+                match item.check(&value.0) {
+                    Ok(value) => {
+                        resolved.values.insert(key.clone(), value);
+                        resolved.sources.insert(key.clone(), source);
+                    }
+                    Err(reason) => resolved.rejected.push(reject(reason)),
+                }
+            }
+        }
+
+        let mut disabled: Vec<String> = items
+            .iter()
+            .filter(|(key, _)| {
+                !dependencies_met(key, &items, &resolved.values, &mut HashSet::new())
+            })
+            .map(|(key, _)| key.to_string())
+            .collect();
+        disabled.sort();
+        resolved.disabled = disabled;
+        resolved
+    }
+}
+
+/// Dependencies should be truthy and enabled too, cycles are never met.
+fn dependencies_met<'a>(
+    key: &'a str,
+    items: &HashMap<&'a str, &'a WsdItem>,
+    values: &BTreeMap<String, WidgetSettingValue>,
+    visiting: &mut HashSet<&'a str>,
+) -> bool {
+    let Some(item) = items.get(key) else {
+        return false;
+    };
+    if !visiting.insert(key) {
+        return false;
+    }
+    let met = item.dependencies().iter().all(|dependency| {
+        values.get(dependency).is_some_and(|v| v.is_truthy())
+            && dependencies_met(dependency, items, values, visiting)
+    });
+    visiting.remove(key);
+    met
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::error::Result;
+
+    #[test]
+    fn should_resolve_layers_and_reject_invalid_values() -> Result<()> {
+        let declaration: WidgetSettingsDeclarationList = serde_json::from_value(json!([
+            {
+                "group": [
+                    { "config": { "type": "switch", "key": "showClock", "label": "Clock", "defaultValue": true } },
+                    {
+                        "config": {
+                            "type": "select", "key": "format", "label": "Format", "subtype": "List",
+                            "defaultValue": "24h", "dependencies": ["showClock"],
+                            "options": [{ "label": "12h", "value": "12h" }, { "label": "24h", "value": "24h" }]
+                        },
+                        "children": [
+                            { "config": { "type": "switch", "key": "seconds", "label": "Seconds", "dependencies": ["format"] } }
+                        ]
+                    },
+                    {
+                        "config": {
+                            "type": "range", "key": "opacity", "label": "Opacity",
+                            "defaultValue": 1, "from": 0, "to": 1, "step": 0.25, "allowSetByMonitor": true
+                        }
+                    },
+                    { "config": { "type": "color", "key": "accent", "label": "Accent", "defaultValue": "#fff", "allowAlpha": false } }
+                ]
+            }
+        ]))?;
+
+        let instance = Uuid::new_v4();
+        let root: ThirdPartyWidgetSettings = serde_json::from_value(json!({
+            "enabled": true,
+            "format": "12h",
+            "opacity": 3,
+            "accent": "#ff000080",
+            "legacy": 1,
+            "$instances": { instance.to_string(): { "showClock": false, "opacity": 0.5 } }
+        }))?;
+        let monitor: ThirdPartyWidgetSettings = serde_json::from_value(json!({
+            "opacity": 0.75,
+            "format": "24h"
+        }))?;
+
+        let resolved = declaration.resolve(&root, Some(&instance), Some(&monitor));
+        assert_eq!(
+            resolved.get("format"),
+            Some(&WidgetSettingValue::Text("12h".into()))
+        );
+        assert_eq!(
+            resolved.get("opacity"),
+            Some(&WidgetSettingValue::Number(0.75))
+        );
+        assert_eq!(resolved.sources["opacity"], WidgetSettingSource::Monitor);
+        assert_eq!(
+            resolved.get("accent"),
+            Some(&WidgetSettingValue::Text("#fff".into()))
+        );
+        // showClock is false on the instance, so format and its child are disabled
+        assert_eq!(resolved.disabled, ["format", "seconds"]);
+        assert!(resolved.is_enabled("opacity"));
+
+        let rejected: Vec<(&str, WidgetSettingSource)> = resolved
+            .rejected
+            .iter()
+            .map(|r| (r.key.as_str(), r.source))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                ("accent", WidgetSettingSource::Root),
+                ("legacy", WidgetSettingSource::Root),
+                ("opacity", WidgetSettingSource::Root),
+                ("format", WidgetSettingSource::Monitor),
+            ]
+        );
+        Ok(())
+    }
+}
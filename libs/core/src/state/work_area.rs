@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    rect::Rect,
+    resource::WidgetId,
+    state::{FancyToolbarSide, HideMode, SeelenWegSide, Settings},
+    system_state::{MonitorId, PhysicalMonitor},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(repr(enum = name))]
+pub enum ScreenEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+impl From<FancyToolbarSide> for ScreenEdge {
+    fn from(side: FancyToolbarSide) -> Self {
+        match side {
+            FancyToolbarSide::Top => Self::Top,
+            FancyToolbarSide::Bottom => Self::Bottom,
+        }
+    }
+}
+
+impl From<SeelenWegSide> for ScreenEdge {
+    fn from(side: SeelenWegSide) -> Self {
+        match side {
+            SeelenWegSide::Left => Self::Left,
+            SeelenWegSide::Top => Self::Top,
+            SeelenWegSide::Right => Self::Right,
+            SeelenWegSide::Bottom => Self::Bottom,
+        }
+    }
+}
+
+/// Strip of the monitor reserved by a bar, in physical pixels
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedStrip {
+    pub widget_id: WidgetId,
+    pub edge: ScreenEdge,
+    pub rect: Rect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct MonitorWorkArea {
+    pub monitor_id: MonitorId,
+    pub monitor_rect: Rect,
+    /// Monitor rect minus the reserved strips
+    pub work_area: Rect,
+    /// Work area minus the window manager workspace margin
+    pub tiling_area: Rect,
+    pub reserved: Vec<ReservedStrip>,
+}
+
+impl MonitorWorkArea {
+    /// Reserved rects, to be used as exclusion zones on popup placement.
+    pub fn exclusions(&self) -> Vec<Rect> {
+        self.reserved.iter().map(|r| r.rect.clone()).collect()
+    }
+}
+
+/// Bar that may reserve space on a monitor
+struct BarReservation {
+    widget_id: WidgetId,
+    enabled: bool,
+    edge: ScreenEdge,
+    hide_mode: HideMode,
+    /// Logical pixels
+    size: u32,
+}
+
+impl BarReservation {
+    /// Applies the per monitor overrides, stored as `enabled`, `position` and `hideMode`.
+    fn on_monitor(&self, settings: &Settings, monitor: &MonitorId) -> Self {
+        let mut bar = BarReservation {
+            widget_id: self.widget_id.clone(),
+            ..*self
+        };
+        let Some(config) = settings
+            .monitors_v3
+            .get(&monitor.0)
+            .and_then(|config| config.by_widget.get(&self.widget_id))
+        else {
+            return bar;
+        };
+        bar.enabled &= config.enabled;
+        if let Some(position) = config.rest.get("position") {
+            let edge = match self.widget_id == WidgetId::known_toolbar() {
+                true => {
+                    serde_json::from_value::<FancyToolbarSide>(position.0.clone()).map(Into::into)
+                }
+                false => {
+                    serde_json::from_value::<SeelenWegSide>(position.0.clone()).map(Into::into)
+                }
+            };
+            if let Ok(edge) = edge {
+                bar.edge = edge;
+            }
+        }
+        if let Some(hide_mode) = config.rest.get("hideMode") {
+            if let Ok(hide_mode) = serde_json::from_value(hide_mode.0.clone()) {
+                bar.hide_mode = hide_mode;
+            }
+        }
+        bar
+    }
+}
+
+fn scaled(value: impl Into<f64>, dpi: f64) -> i32 {
+    let scale = if dpi > 0.0 { dpi } else { 1.0 };
+    (value.into() * scale).round() as i32
+}
+
+/// Cuts a strip of `size` from the `edge` of the area, returning the strip.
+fn cut_edge(area: &mut Rect, edge: ScreenEdge, size: i32) -> Rect {
+    let mut strip = area.clone();
+    match edge {
+        ScreenEdge::Left => {
+            strip.right = (area.left + size).min(area.right);
+            area.left = strip.right;
+        }
+        ScreenEdge::Top => {
+            strip.bottom = (area.top + size).min(area.bottom);
+            area.top = strip.bottom;
+        }
+        ScreenEdge::Right => {
+            strip.left = (area.right - size).max(area.left);
+            area.right = strip.left;
+        }
+        ScreenEdge::Bottom => {
+            strip.top = (area.bottom - size).max(area.top);
+            area.bottom = strip.top;
+        }
+    }
+    strip
+}
+
+/// Computes the effective work area of each monitor. Only bars that never hide reserve
+/// space, the toolbar is reserved first so the weg is stacked after it on the same edge.
+pub fn compute_work_areas(
+    monitors: &[PhysicalMonitor],
+    settings: &Settings,
+) -> Vec<MonitorWorkArea> {
+    let toolbar = &settings.by_widget.fancy_toolbar;
+    let weg = &settings.by_widget.weg;
+    let wm = &settings.by_widget.wm;
+    let bars = [
+        BarReservation {
+            widget_id: WidgetId::known_toolbar(),
+            enabled: toolbar.enabled,
+            edge: toolbar.position.into(),
+            hide_mode: toolbar.hide_mode,
+            size: toolbar.height,
+        },
+        BarReservation {
+            widget_id: WidgetId::known_weg(),
+            enabled: weg.enabled,
+            edge: weg.position.into(),
+            hide_mode: weg.hide_mode,
+            size: weg.total_size(),
+        },
+    ];
+
+    monitors
+        .iter()
+        .map(|monitor| {
+            let mut work_area = monitor.rect.clone();
+            let mut reserved = Vec::new();
+            for bar in &bars {
+                let bar = bar.on_monitor(settings, &monitor.id);
+                if !bar.enabled || bar.hide_mode != HideMode::Never {
+                    continue;
+                }
+                let rect = cut_edge(&mut work_area, bar.edge, scaled(bar.size, monitor.dpi));
+                reserved.push(ReservedStrip {
+                    widget_id: bar.widget_id,
+                    edge: bar.edge,
+                    rect,
+                });
+            }
+
+            let mut tiling_area = work_area.clone();
+            if wm.enabled {
+                let margin = &wm.workspace_margin;
+                tiling_area.left += scaled(margin.left, monitor.dpi);
+                tiling_area.top += scaled(margin.top, monitor.dpi);
+                tiling_area.right -= scaled(margin.right, monitor.dpi);
+                tiling_area.bottom -= scaled(margin.bottom, monitor.dpi);
+            }
+
+            MonitorWorkArea {
+                monitor_id: monitor.id.clone(),
+                monitor_rect: monitor.rect.clone(),
+                work_area,
+                tiling_area,
+                reserved,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::error::Result;
+
+    fn monitor(id: &str, left: i32, dpi: f64) -> PhysicalMonitor {
+        PhysicalMonitor {
+            id: MonitorId(id.into()),
+            name: id.into(),
+            rect: Rect {
+                left,
+                top: 0,
+                right: left + 1920,
+                bottom: 1080,
+            },
+            dpi,
+            is_primary: left == 0,
+        }
+    }
+
+    #[test]
+    fn should_reserve_strips_with_monitor_overrides() -> Result<()> {
+        let mut settings: Settings = serde_json::from_value(json!({
+            "monitorsV3": {
+                "B": { "byWidget": { "@seelen/weg": { "enabled": true, "position": "Left" } } }
+            }
+        }))?;
+        settings.by_widget.weg.hide_mode = HideMode::Never;
+        settings.by_widget.wm.enabled = true;
+        settings.by_widget.wm.workspace_margin = Rect {
+            left: 4,
+            top: 4,
+            right: 4,
+            bottom: 4,
+        };
+        let monitors = vec![monitor("A", 0, 1.0), monitor("B", 1920, 1.5)];
+        let areas = compute_work_areas(&monitors, &settings);
+
+        // toolbar 30px on top, weg 40 + 8*2 + 8*2 = 72px at the bottom
+        assert_eq!(
+            areas[0].work_area,
+            Rect {
+                left: 0,
+                top: 30,
+                right: 1920,
+                bottom: 1008
+            }
+        );
+        assert_eq!(areas[0].reserved.len(), 2);
+        assert_eq!(
+            areas[0].tiling_area,
+            Rect {
+                left: 4,
+                top: 34,
+                right: 1916,
+                bottom: 1004
+            }
+        );
+
+        // scaled and with the weg on the left
+        assert_eq!(areas[1].reserved[1].edge, ScreenEdge::Left);
+        assert_eq!(
+            areas[1].work_area,
+            Rect {
+                left: 1920 + 108,
+                top: 45,
+                right: 3840,
+                bottom: 1080
+            }
+        );
+        Ok(())
+    }
+}
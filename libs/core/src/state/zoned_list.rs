@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::Result;
+
+/// Max number of operations that can be undone
+pub const ZONED_LIST_UNDO_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, TS)]
+#[ts(repr(enum = name))]
+pub enum ItemZone {
+    Left,
+    Center,
+    Right,
+}
+
+impl ItemZone {
+    pub const ALL: [ItemZone; 3] = [ItemZone::Left, ItemZone::Center, ItemZone::Right];
+}
+
+/// Item of a [`ZonedList`], identified by an id unique across the three zones.
+pub trait ZonedItem: Clone {
+    fn item_id(&self) -> String;
+
+    fn set_item_id(&mut self, id: String);
+
+    /// `None` if the list doesn't support separators
+    fn new_separator(id: String) -> Option<Self>;
+
+    /// Changes the pinned state of the item, returns `false` if the item should be removed
+    /// as it is no longer useful unpinned.
+    fn set_pinned(&mut self, pinned: bool) -> Result<bool> {
+        let _ = pinned;
+        Err("the item can not be pinned".into())
+    }
+}
+
+/// Items splitted on left, center and right zones, as the toolbar and the weg.
+pub trait ZonedList: Clone {
+    type Item: ZonedItem;
+
+    fn is_reorder_disabled(&self) -> bool;
+
+    fn zone(&self, zone: ItemZone) -> &Vec<Self::Item>;
+
+    fn zone_mut(&mut self, zone: ItemZone) -> &mut Vec<Self::Item>;
+
+    fn position(&self, id: &str) -> Option<(ItemZone, usize)> {
+        ItemZone::ALL.into_iter().find_map(|zone| {
+            self.zone(zone)
+                .iter()
+                .position(|item| item.item_id() == id)
+                .map(|index| (zone, index))
+        })
+    }
+
+    /// Gives an id to the items without one and removes the duplicated ones.
+    fn ensure_unique_ids(&mut self) {
+        let mut dict = HashSet::new();
+        for zone in ItemZone::ALL {
+            let items = std::mem::take(self.zone_mut(zone));
+            *self.zone_mut(zone) = items
+                .into_iter()
+                .filter_map(|item| keep_unique(&mut dict, item))
+                .collect();
+        }
+    }
+}
+
+/// Gives an id to the item if it doesn't have one, returns `None` if the id was already used.
+pub(crate) fn keep_unique<T: ZonedItem>(dict: &mut HashSet<String>, mut item: T) -> Option<T> {
+    if item.item_id().is_empty() {
+        item.set_item_id(uuid::Uuid::new_v4().to_string());
+    }
+    match dict.insert(item.item_id()) {
+        true => Some(item),
+        false => None,
+    }
+}
+
+/// Edition operations over a [`ZonedList`] with undo support.
+#[derive(Debug, Clone)]
+pub struct ZonedListEditor<L: ZonedList> {
+    list: L,
+    history: Vec<L>,
+}
+
+impl<L: ZonedList> ZonedListEditor<L> {
+    pub fn new(mut list: L) -> Self {
+        list.ensure_unique_ids();
+        Self {
+            list,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn list(&self) -> &L {
+        &self.list
+    }
+
+    pub fn into_inner(self) -> L {
+        self.list
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    /// Reverts the last successful operation, returns `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(previous) => {
+                self.list = previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Runs the operation over a copy of the list, so failed operations leave no changes.
+    fn edit<T>(&mut self, f: impl FnOnce(&mut L) -> Result<T>) -> Result<T> {
+        let mut next = self.list.clone();
+        let result = f(&mut next)?;
+        let previous = std::mem::replace(&mut self.list, next);
+        self.history.push(previous);
+        if self.history.len() > ZONED_LIST_UNDO_LIMIT {
+            self.history.remove(0);
+        }
+        Ok(result)
+    }
+
+    fn ensure_reorder_enabled(&self) -> Result<()> {
+        match self.list.is_reorder_disabled() {
+            true => Err("reordering is disabled".into()),
+            false => Ok(()),
+        }
+    }
+
+    /// Moves the item to the `index` of the zone, the index is clamped to the zone length.
+    pub fn move_item(&mut self, id: &str, zone: ItemZone, index: usize) -> Result<()> {
+        self.ensure_reorder_enabled()?;
+        self.edit(|list| {
+            let (from, from_index) = list.position(id).ok_or("item not found")?;
+            let item = list.zone_mut(from).remove(from_index);
+            let target = list.zone_mut(zone);
+            target.insert(index.min(target.len()), item);
+            Ok(())
+        })
+    }
+
+    /// Returns the id of the new separator
+    pub fn insert_separator(&mut self, zone: ItemZone, index: usize) -> Result<String> {
+        self.ensure_reorder_enabled()?;
+        self.edit(|list| {
+            let id = uuid::Uuid::new_v4().to_string();
+            let separator =
+                L::Item::new_separator(id.clone()).ok_or("separators are not supported")?;
+            let target = list.zone_mut(zone);
+            target.insert(index.min(target.len()), separator);
+            Ok(id)
+        })
+    }
+
+    pub fn set_pinned(&mut self, id: &str, pinned: bool) -> Result<()> {
+        self.edit(|list| {
+            let (zone, index) = list.position(id).ok_or("item not found")?;
+            let items = list.zone_mut(zone);
+            if !items[index].set_pinned(pinned)? {
+                items.remove(index);
+            }
+            Ok(())
+        })
+    }
+
+    pub fn pin(&mut self, id: &str) -> Result<()> {
+        self.set_pinned(id, true)
+    }
+
+    pub fn unpin(&mut self, id: &str) -> Result<()> {
+        self.set_pinned(id, false)
+    }
+}
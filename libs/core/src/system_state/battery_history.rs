@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+
+use crate::{error::Result, utils::write_file_atomically};
+
+use super::Battery;
+
+/// Snapshot of a battery, energy values use the same units as [`Battery`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BatterySample {
+    pub timestamp: DateTime<Utc>,
+    pub state: String,
+    pub percentage: f32,
+    pub energy: f32,
+    pub energy_full: f32,
+    pub energy_full_design: f32,
+    pub energy_rate: f32,
+    pub cycle_count: Option<u32>,
+}
+
+impl BatterySample {
+    pub fn from_battery(battery: &Battery, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            timestamp,
+            state: battery.state.to_lowercase(),
+            percentage: battery.percentage,
+            energy: battery.energy,
+            energy_full: battery.energy_full,
+            energy_full_design: battery.energy_full_design,
+            energy_rate: battery.energy_rate,
+            cycle_count: battery.cycle_count,
+        }
+    }
+
+    /// Full capacity relative to the design capacity, 1.0 means no wear.
+    pub fn health(&self) -> Option<f32> {
+        (self.energy_full_design > 0.0 && self.energy_full > 0.0)
+            .then(|| self.energy_full / self.energy_full_design)
+    }
+
+    fn is_charging(&self) -> bool {
+        self.state == "charging"
+    }
+
+    fn is_discharging(&self) -> bool {
+        self.state == "discharging"
+    }
+}
+
+/// Smoothed estimations, times are in seconds (energy in joules and rate in watts).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryEstimate {
+    /// Exponential moving average of the energy rate while charging or discharging
+    pub smoothed_rate: Option<f32>,
+    pub time_to_empty: Option<f32>,
+    pub time_to_full: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryHealthPoint {
+    pub date: NaiveDate,
+    /// Mean health of the day
+    pub health: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryHealthTrend {
+    pub current: f32,
+    pub points: Vec<BatteryHealthPoint>,
+    /// Health change each 30 days by linear regression, `None` with less than two days.
+    pub change_per_month: Option<f32>,
+    pub cycle_count: Option<u32>,
+}
+
+/// Data shown by the power toolbar item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct BatteryInsights {
+    pub estimate: BatteryEstimate,
+    pub health: Option<BatteryHealthTrend>,
+}
+
+/// Ring buffer of battery samples, persisted as an append-only file of json lines.
+/// The file is compacted to the samples in memory once it doubles the capacity.
+#[derive(Debug)]
+pub struct BatteryHistory {
+    path: Option<PathBuf>,
+    capacity: usize,
+    /// Lines on the file, including the ones already dropped from the ring
+    stored_lines: usize,
+    /// Minimum time between stored samples
+    pub min_interval: TimeDelta,
+    /// Weight of the newest rate on the moving average
+    pub smoothing: f32,
+    samples: VecDeque<BatterySample>,
+    rate_ema: Option<f32>,
+    /// State of the last update, the average is restarted when it changes
+    last_state: Option<String>,
+}
+
+impl BatteryHistory {
+    pub const DEFAULT_CAPACITY: usize = 10_000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            path: None,
+            capacity: capacity.max(1),
+            stored_lines: 0,
+            min_interval: TimeDelta::minutes(1),
+            smoothing: 0.2,
+            samples: VecDeque::new(),
+            rate_ema: None,
+            last_state: None,
+        }
+    }
+
+    /// Loads the stored samples, a missing file is an empty history.\
+    /// Corrupted lines (ex: partially written on a crash) are skipped.
+    pub fn open(path: impl AsRef<Path>, capacity: usize) -> Result<Self> {
+        let path = path.as_ref();
+        let mut history = Self::new(capacity);
+        history.path = Some(path.to_path_buf());
+        if path.exists() {
+            for line in std::fs::read_to_string(path)?.lines() {
+                history.stored_lines += 1;
+                if let Ok(sample) = serde_json::from_str::<BatterySample>(line) {
+                    history.update_rate(&sample);
+                    history.push(sample);
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &BatterySample> {
+        self.samples.iter()
+    }
+
+    fn push(&mut self, sample: BatterySample) {
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    fn update_rate(&mut self, sample: &BatterySample) {
+        if self.last_state.as_deref() != Some(sample.state.as_str()) {
+            self.rate_ema = None;
+            self.last_state = Some(sample.state.clone());
+        }
+        if !(sample.is_charging() || sample.is_discharging()) || sample.energy_rate <= 0.0 {
+            return;
+        }
+        let rate = sample.energy_rate.abs();
+        self.rate_ema = Some(match self.rate_ema {
+            Some(ema) => self.smoothing * rate + (1.0 - self.smoothing) * ema,
+            None => rate,
+        });
+    }
+
+    /// Updates the moving average and stores the sample if `min_interval` passed since
+    /// the last stored one. Returns whether the sample was stored.
+    pub fn record(&mut self, battery: &Battery, now: DateTime<Utc>) -> Result<bool> {
+        let sample = BatterySample::from_battery(battery, now);
+        self.update_rate(&sample);
+
+        let should_store = self.samples.back().is_none_or(|last| {
+            now - last.timestamp >= self.min_interval || last.state != sample.state
+        });
+        if !should_store {
+            return Ok(false);
+        }
+        self.append(&sample)?;
+        self.push(sample);
+        Ok(true)
+    }
+
+    fn append(&mut self, sample: &BatterySample) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.stored_lines >= self.capacity * 2 {
+            return self.compact(sample);
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(sample)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(&line)?;
+        self.stored_lines += 1;
+        Ok(())
+    }
+
+    /// Rewrites the file with the samples of the ring, plus the one being stored.
+    fn compact(&mut self, next: &BatterySample) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let skip = (self.samples.len() + 1).saturating_sub(self.capacity);
+        let mut contents = Vec::new();
+        let mut lines = 0;
+        for sample in self.samples.iter().chain([next]).skip(skip) {
+            contents.extend(serde_json::to_vec(sample)?);
+            contents.push(b'\n');
+            lines += 1;
+        }
+        write_file_atomically(path, &contents)?;
+        self.stored_lines = lines;
+        Ok(())
+    }
+
+    /// Estimations for the last recorded state of the battery.
+    pub fn estimate(&self) -> BatteryEstimate {
+        let mut estimate = BatteryEstimate {
+            smoothed_rate: self.rate_ema,
+            ..Default::default()
+        };
+        let (Some(last), Some(rate)) = (self.samples.back(), self.rate_ema) else {
+            return estimate;
+        };
+        // the last stored sample could be older than the last update
+        if self.last_state.as_deref() != Some(last.state.as_str()) || rate <= 0.0 {
+            return estimate;
+        }
+        if last.is_discharging() {
+            estimate.time_to_empty = Some(last.energy / rate);
+        }
+        if last.is_charging() {
+            estimate.time_to_full = Some((last.energy_full - last.energy).max(0.0) / rate);
+        }
+        estimate
+    }
+
+    pub fn health_trend(&self) -> Option<BatteryHealthTrend> {
+        let mut by_day: BTreeMap<NaiveDate, (f32, u32)> = BTreeMap::new();
+        for sample in &self.samples {
+            if let Some(health) = sample.health() {
+                let day = by_day.entry(sample.timestamp.date_naive()).or_default();
+                day.0 += health;
+                day.1 += 1;
+            }
+        }
+        let points: Vec<BatteryHealthPoint> = by_day
+            .into_iter()
+            .map(|(date, (total, count))| BatteryHealthPoint {
+                date,
+                health: total / count as f32,
+            })
+            .collect();
+
+        let last = self.samples.iter().rev().find(|s| s.health().is_some())?;
+        Some(BatteryHealthTrend {
+            current: last.health()?,
+            change_per_month: monthly_slope(&points),
+            cycle_count: last.cycle_count,
+            points,
+        })
+    }
+
+    pub fn insights(&self) -> BatteryInsights {
+        BatteryInsights {
+            estimate: self.estimate(),
+            health: self.health_trend(),
+        }
+    }
+}
+
+/// Least squares slope of the health by day, scaled to 30 days.
+fn monthly_slope(points: &[BatteryHealthPoint]) -> Option<f32> {
+    let first = points.first()?.date;
+    let xs: Vec<f64> = points
+        .iter()
+        .map(|p| (p.date - first).num_days() as f64)
+        .collect();
+    let ys: Vec<f64> = points.iter().map(|p| p.health as f64).collect();
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        return None;
+    }
+    let covariance: f64 = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    Some((covariance / variance * 30.0) as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn approx(value: Option<f32>, expected: f32) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-2)
+    }
+
+    fn battery(state: &str, energy: f32, rate: f32, energy_full: f32) -> Battery {
+        Battery {
+            vendor: None,
+            model: None,
+            serial_number: None,
+            technology: "lithium-ion".into(),
+            state: state.into(),
+            capacity: 1.0,
+            temperature: None,
+            percentage: energy / energy_full * 100.0,
+            cycle_count: Some(120),
+            smart_charging: false,
+            energy,
+            energy_full,
+            energy_full_design: 200_000.0,
+            energy_rate: rate,
+            voltage: 12.0,
+            time_to_full: None,
+            time_to_empty: None,
+        }
+    }
+
+    #[test]
+    fn should_smooth_estimates_and_track_health() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("slu-battery-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("battery.jsonl");
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap();
+
+        let mut history = BatteryHistory::open(&path, 3)?;
+        // a spike on the rate is smoothed
+        assert!(history.record(&battery("Discharging", 100_000.0, 10.0, 180_000.0), start)?);
+        assert!(!history.record(
+            &battery("Discharging", 99_990.0, 60.0, 180_000.0),
+            start + TimeDelta::seconds(10)
+        )?);
+        let estimate = history.estimate();
+        assert!(approx(estimate.smoothed_rate, 20.0));
+        assert!(approx(estimate.time_to_empty, 100_000.0 / 20.0));
+        assert_eq!(estimate.time_to_full, None);
+
+        // health is tracked by day, with a ring of 3 samples
+        for day in 1..=3 {
+            let full = 180_000.0 - day as f32 * 1_000.0;
+            history.record(
+                &battery("Discharging", 90_000.0, 10.0, full),
+                start + TimeDelta::days(day),
+            )?;
+        }
+        assert_eq!(history.samples().count(), 3);
+        let trend = history.health_trend().unwrap();
+        assert_eq!(trend.points.len(), 3);
+        assert_eq!(trend.current, 177_000.0 / 200_000.0);
+        assert!(approx(trend.change_per_month, -0.15));
+
+        // plugging the charger restarts the average, and the state change is stored
+        let charging = battery("Charging", 90_000.0, 30.0, 177_000.0);
+        assert!(history.record(
+            &charging,
+            start + TimeDelta::days(3) + TimeDelta::seconds(5)
+        )?);
+        assert!(approx(history.estimate().time_to_full, 87_000.0 / 30.0));
+
+        // reloaded from disk
+        let reloaded = BatteryHistory::open(&path, 3)?;
+        assert_eq!(reloaded.samples().count(), 3);
+        assert_eq!(reloaded.estimate(), history.estimate());
+
+        // samples are appended and the file is compacted after doubling the capacity
+        let lines = || -> Result<usize> { Ok(std::fs::read_to_string(&path)?.lines().count()) };
+        assert_eq!(lines()?, 5);
+        history.record(&charging, start + TimeDelta::days(4))?;
+        assert_eq!(lines()?, 6);
+        history.record(&charging, start + TimeDelta::days(5))?;
+        assert_eq!(lines()?, 3);
+        history.record(&charging, start + TimeDelta::days(6))?;
+        assert_eq!(lines()?, 4);
+        let reloaded = BatteryHistory::open(&path, 3)?;
+        let last: Vec<_> = reloaded.samples().map(|s| s.timestamp).collect();
+        let expected: Vec<_> = history.samples().map(|s| s.timestamp).collect();
+        assert_eq!(last, expected);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
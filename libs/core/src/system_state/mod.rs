@@ -1,3 +1,4 @@
+mod battery_history;
 mod bluetooth;
 mod language;
 mod media;
@@ -11,6 +12,7 @@ mod user;
 mod user_apps;
 mod win_explorer;
 
+pub use battery_history::*;
 pub use bluetooth::*;
 pub use language::*;
 pub use media::*;
@@ -0,0 +1,495 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    error::Result,
+    state::{
+        DoNotDisturbSchedule, DoNotDisturbSettings, NotificationRetention, NotificationRuleAction,
+        NotificationSettings, ScheduleDay,
+    },
+    utils::write_file_atomically,
+};
+
+use super::AppNotification;
+
+/// Milliseconds between the windows file time epoch (1601) and the unix epoch
+const FILETIME_UNIX_OFFSET_MS: i64 = 11_644_473_600_000;
+
+impl AppNotification {
+    /// `date` is a windows file time, in 100 nanoseconds intervals since 1601.
+    pub fn date_utc(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.date / 10_000 - FILETIME_UNIX_OFFSET_MS)
+    }
+
+    fn is_same(&self, other: &AppNotification) -> bool {
+        self.id == other.id && self.app_umid == other.app_umid && self.date == other.date
+    }
+
+    /// Lowercased text used by the full text search
+    fn searchable_text(&self) -> String {
+        let model = self.content.to_render_model();
+        let mut text = vec![self.app_name.as_str()];
+        text.extend(model.header.as_deref());
+        text.extend(model.title.as_deref());
+        text.extend(model.body.iter().map(String::as_str));
+        text.extend(model.attribution.as_deref());
+        text.join("\n").to_lowercase()
+    }
+}
+
+/// How a notification was delivered to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(repr(enum = name))]
+pub enum NotificationDelivery {
+    Show,
+    /// Shown first, even if do not disturb is active
+    Priority,
+    /// Not shown because do not disturb was active
+    Silenced,
+    /// Not shown because the app is muted
+    Muted,
+}
+
+impl NotificationDelivery {
+    pub fn is_shown(&self) -> bool {
+        matches!(self, Self::Show | Self::Priority)
+    }
+}
+
+impl DoNotDisturbSchedule {
+    fn starts_on(&self, day: ScheduleDay) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Ranges crossing midnight belong to the day they start.
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let today = ScheduleDay::from(now.weekday());
+        let yesterday = ScheduleDay::from(now.weekday().pred());
+        match self.from <= self.to {
+            true => self.starts_on(today) && self.from <= time && time < self.to,
+            false => {
+                (self.starts_on(today) && self.from <= time)
+                    || (self.starts_on(yesterday) && time < self.to)
+            }
+        }
+    }
+}
+
+impl DoNotDisturbSettings {
+    /// `now` should be on local time
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.enabled || self.schedules.iter().any(|s| s.is_active(now))
+    }
+}
+
+impl NotificationSettings {
+    pub fn rule_for(&self, app_umid: &str) -> Option<NotificationRuleAction> {
+        self.rules
+            .iter()
+            .find(|rule| rule.app_umid.eq_ignore_ascii_case(app_umid))
+            .map(|rule| rule.action)
+    }
+
+    /// `now` should be on local time
+    pub fn delivery_for(&self, app_umid: &str, now: NaiveDateTime) -> NotificationDelivery {
+        let rule = self.rule_for(app_umid);
+        if rule == Some(NotificationRuleAction::Mute) {
+            return NotificationDelivery::Muted;
+        }
+        if rule == Some(NotificationRuleAction::Prioritize) {
+            return NotificationDelivery::Priority;
+        }
+        let dnd = &self.do_not_disturb;
+        let bypass = dnd
+            .bypass
+            .iter()
+            .any(|umid| umid.eq_ignore_ascii_case(app_umid));
+        match !bypass && dnd.is_active(now) {
+            true => NotificationDelivery::Silenced,
+            false => NotificationDelivery::Show,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct NotificationHistoryEntry {
+    pub notification: AppNotification,
+    pub delivery: NotificationDelivery,
+}
+
+impl NotificationHistoryEntry {
+    fn group_key(&self) -> (String, Option<String>) {
+        let header = self.notification.content.header.as_ref();
+        (
+            self.notification.app_umid.to_lowercase(),
+            header.map(|h| h.id.clone()),
+        )
+    }
+}
+
+/// Notifications of the same app and toast header
+#[derive(Debug, Clone)]
+pub struct NotificationGroup<'a> {
+    pub app_umid: &'a str,
+    pub header_id: Option<&'a str>,
+    pub header_title: Option<&'a str>,
+    /// Newest first
+    pub entries: Vec<&'a NotificationHistoryEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(default, rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct NotificationQuery {
+    pub app_umid: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// All the words should be present on the app name, title or body.
+    pub text: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl NotificationQuery {
+    fn matches(&self, entry: &NotificationHistoryEntry) -> bool {
+        let notification = &entry.notification;
+        if let Some(umid) = &self.app_umid {
+            if !notification.app_umid.eq_ignore_ascii_case(umid) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(date) = notification.date_utc() else {
+                return false;
+            };
+            if self.since.is_some_and(|since| date < since)
+                || self.until.is_some_and(|until| date > until)
+            {
+                return false;
+            }
+        }
+        if let Some(text) = &self.text {
+            let haystack = notification.searchable_text();
+            return text
+                .to_lowercase()
+                .split_whitespace()
+                .all(|word| haystack.contains(word));
+        }
+        true
+    }
+}
+
+/// Persistent history of notifications, stored as an append-only file of json lines.
+/// The file is only rewritten when entries are removed. Entries are kept sorted by
+/// notification date, as toasts can be reported out of order.
+#[derive(Debug)]
+pub struct NotificationHistory {
+    path: PathBuf,
+    entries: Vec<NotificationHistoryEntry>,
+}
+
+impl NotificationHistory {
+    /// Loads the history, corrupted lines (ex: partially written on a crash) are skipped.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut entries: Vec<NotificationHistoryEntry> = match path.exists() {
+            true => std::fs::read_to_string(&path)?
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            false => Vec::new(),
+        };
+        entries.sort_by_key(|entry| entry.notification.date);
+        Ok(Self { path, entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Oldest first, by notification date
+    pub fn entries(&self) -> &[NotificationHistoryEntry] {
+        &self.entries
+    }
+
+    /// Saves the notification applying the rules, returns how it should be delivered.
+    /// Notifications already recorded are not duplicated.
+    pub fn record(
+        &mut self,
+        notification: AppNotification,
+        settings: &NotificationSettings,
+        now: NaiveDateTime,
+    ) -> Result<NotificationDelivery> {
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|e| e.notification.is_same(&notification))
+        {
+            return Ok(entry.delivery);
+        }
+
+        let entry = NotificationHistoryEntry {
+            delivery: settings.delivery_for(&notification.app_umid, now),
+            notification,
+        };
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)?;
+
+        let delivery = entry.delivery;
+        let index = self
+            .entries
+            .partition_point(|e| e.notification.date <= entry.notification.date);
+        self.entries.insert(index, entry);
+        Ok(delivery)
+    }
+
+    fn rewrite(&self) -> Result<()> {
+        let mut contents = Vec::new();
+        for entry in &self.entries {
+            contents.extend(serde_json::to_vec(entry)?);
+            contents.push(b'\n');
+        }
+        write_file_atomically(&self.path, &contents)?;
+        Ok(())
+    }
+
+    /// Removes the entries matching the predicate, returns the removed count.
+    pub fn remove_where(
+        &mut self,
+        predicate: impl Fn(&NotificationHistoryEntry) -> bool,
+    ) -> Result<usize> {
+        let before = self.entries.len();
+        self.entries.retain(|entry| !predicate(entry));
+        let removed = before - self.entries.len();
+        if removed > 0 {
+            self.rewrite()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn remove(&mut self, app_umid: &str, id: u32) -> Result<bool> {
+        let removed = self.remove_where(|e| {
+            e.notification.id == id && e.notification.app_umid.eq_ignore_ascii_case(app_umid)
+        })?;
+        Ok(removed > 0)
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.rewrite()
+    }
+
+    /// Removes the expired entries and then the oldest ones over the limit.
+    pub fn apply_retention(
+        &mut self,
+        retention: &NotificationRetention,
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let before = self.entries.len();
+        if retention.max_age_days > 0 {
+            let limit = now - chrono::Duration::days(retention.max_age_days.into());
+            self.entries
+                .retain(|e| e.notification.date_utc().is_none_or(|date| date >= limit));
+        }
+        if self.entries.len() > retention.max_entries {
+            let excess = self.entries.len() - retention.max_entries;
+            self.entries.drain(..excess);
+        }
+        let removed = before - self.entries.len();
+        if removed > 0 {
+            self.rewrite()?;
+        }
+        Ok(removed)
+    }
+
+    /// Matching entries, newest first
+    pub fn query(&self, query: &NotificationQuery) -> Vec<&NotificationHistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Entries grouped by app and toast header, the group with the newest entry first.
+    pub fn groups(&self) -> Vec<NotificationGroup<'_>> {
+        let mut groups: Vec<NotificationGroup<'_>> = Vec::new();
+        let mut index_by_key = HashMap::new();
+        for entry in self.entries.iter().rev() {
+            let index = *index_by_key.entry(entry.group_key()).or_insert_with(|| {
+                let header = entry.notification.content.header.as_ref();
+                groups.push(NotificationGroup {
+                    app_umid: &entry.notification.app_umid,
+                    header_id: header.map(|h| h.id.as_str()),
+                    header_title: header.map(|h| h.title.as_str()),
+                    entries: Vec::new(),
+                });
+                groups.len() - 1
+            });
+            groups[index].entries.push(entry);
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::{state::NotificationAppRule, system_state::Toast};
+
+    fn notification(id: u32, app: &str, unix_secs: i64, xml: &str) -> Result<AppNotification> {
+        Ok(AppNotification {
+            id,
+            app_umid: app.to_owned(),
+            app_name: app.to_owned(),
+            app_description: String::new(),
+            date: (unix_secs * 1000 + FILETIME_UNIX_OFFSET_MS) * 10_000,
+            content: Toast::from_xml(xml)?,
+        })
+    }
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        // 2026-06-01 is a monday
+        NaiveDate::from_ymd_opt(2026, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn should_apply_rules_and_do_not_disturb() {
+        let hm = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let settings = NotificationSettings {
+            rules: vec![
+                NotificationAppRule {
+                    app_umid: "Spam".into(),
+                    action: NotificationRuleAction::Mute,
+                },
+                NotificationAppRule {
+                    app_umid: "Pager".into(),
+                    action: NotificationRuleAction::Prioritize,
+                },
+            ],
+            do_not_disturb: DoNotDisturbSettings {
+                enabled: false,
+                schedules: vec![DoNotDisturbSchedule {
+                    days: vec![ScheduleDay::Friday],
+                    from: hm(22),
+                    to: hm(7),
+                }],
+                bypass: vec!["Calls".into()],
+            },
+            retention: NotificationRetention::default(),
+        };
+
+        use NotificationDelivery::*;
+        assert_eq!(settings.delivery_for("spam", at(1, 12)), Muted);
+        assert_eq!(settings.delivery_for("Chat", at(1, 12)), Show);
+        // friday night and saturday morning
+        assert_eq!(settings.delivery_for("Chat", at(5, 23)), Silenced);
+        assert_eq!(settings.delivery_for("Chat", at(6, 6)), Silenced);
+        assert_eq!(settings.delivery_for("Calls", at(6, 6)), Show);
+        assert_eq!(settings.delivery_for("Pager", at(6, 6)), Priority);
+        // the schedule doesn't start on saturdays
+        assert_eq!(settings.delivery_for("Chat", at(6, 23)), Show);
+    }
+
+    #[test]
+    fn should_persist_group_query_and_retain() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("slu-notifications-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("history.jsonl");
+        let settings = NotificationSettings::default();
+        let now = at(1, 12);
+        let base = now.and_utc().timestamp();
+
+        let chat = std::fs::read_to_string("./mocks/toasts/chat_message.xml")?;
+        let weather = std::fs::read_to_string("./mocks/toasts/weather_groups.xml")?;
+        let backup = std::fs::read_to_string("./mocks/toasts/legacy_text02.xml")?;
+
+        let mut history = NotificationHistory::open(&path)?;
+        history.record(notification(1, "Chat", base - 3600, &chat)?, &settings, now)?;
+        history.record(
+            notification(2, "Weather", base - 60, &weather)?,
+            &settings,
+            now,
+        )?;
+        history.record(notification(3, "Chat", base - 30, &chat)?, &settings, now)?;
+        history.record(
+            notification(4, "Backup", base - 86400 * 40, &backup)?,
+            &settings,
+            now,
+        )?;
+        // reported late
+        history.record(notification(5, "Chat", base - 7200, &chat)?, &settings, now)?;
+        // duplicated reports are ignored
+        history.record(notification(3, "Chat", base - 30, &chat)?, &settings, now)?;
+        assert_eq!(history.entries().len(), 5);
+
+        let mut history = NotificationHistory::open(&path)?;
+        let ids: Vec<u32> = history
+            .entries()
+            .iter()
+            .map(|e| e.notification.id)
+            .collect();
+        assert_eq!(ids, [4, 5, 1, 2, 3]);
+
+        let groups = history.groups();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].app_umid, "Chat");
+        assert_eq!(groups[0].entries.len(), 3);
+        assert_eq!(groups[0].entries[0].notification.id, 3);
+        assert_eq!(groups[1].header_id, Some("weather"));
+        assert_eq!(groups[2].app_umid, "Backup");
+
+        let found = history.query(&NotificationQuery {
+            text: Some("SUNNY today".into()),
+            ..Default::default()
+        });
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].notification.id, 2);
+
+        let found = history.query(&NotificationQuery {
+            app_umid: Some("chat".into()),
+            since: DateTime::from_timestamp(base - 120, 0),
+            ..Default::default()
+        });
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].notification.id, 3);
+
+        let retention = NotificationRetention {
+            max_entries: 2,
+            max_age_days: 30,
+        };
+        assert_eq!(history.apply_retention(&retention, now.and_utc())?, 3);
+        let history = NotificationHistory::open(&path)?;
+        let ids: Vec<u32> = history
+            .entries()
+            .iter()
+            .map(|e| e.notification.id)
+            .collect();
+        assert_eq!(ids, [2, 3]);
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
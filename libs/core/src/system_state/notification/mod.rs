@@ -1,9 +1,17 @@
 // All this structs/interfaces are taken from https://learn.microsoft.com/en-us/uwp/schemas/tiles/toastschema/schema-root
 
+mod history;
+mod render;
+#[cfg(test)]
+mod tests;
+
+pub use history::*;
+pub use render::*;
+
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-#[derive(Debug, Clone, Serialize, TS)]
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "gen-binds", ts(export))]
 pub struct AppNotification {
@@ -124,6 +132,28 @@ pub struct ToastText {
     pub id: Option<u32>,
     #[serde(rename = "$value")]
     pub content: String,
+    #[serde(rename = "@placement")]
+    pub placement: Option<ToastTextPlacement>,
+    /// https://learn.microsoft.com/en-us/windows/apps/design/shell/tiles-and-notifications/toast-schema#adaptivetextstyle
+    #[serde(rename = "@hint-style")]
+    pub hint_style: Option<String>,
+    #[serde(rename = "@hint-align")]
+    pub hint_align: Option<String>,
+    #[serde(rename = "@hint-wrap")]
+    pub hint_wrap: Option<bool>,
+    #[serde(rename = "@hint-maxLines")]
+    pub hint_max_lines: Option<u32>,
+    #[serde(rename = "@hint-minLines")]
+    pub hint_min_lines: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(repr(enum = name))]
+pub enum ToastTextPlacement {
+    #[serde(alias = "attribution")]
+    Attribution,
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -167,6 +197,7 @@ pub enum ToastImagePlacement {
 /// https://learn.microsoft.com/en-us/uwp/schemas/tiles/toastschema/element-group
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 pub struct ToastGroup {
+    #[serde(default)]
     pub subgroup: Vec<ToastSubGroup>,
 }
 
@@ -178,6 +209,11 @@ pub struct ToastGroup {
 pub struct ToastSubGroup {
     #[serde(rename = "$value")]
     pub children: Vec<ToastSubGroupChild>,
+    /// Relative width of the column
+    #[serde(rename = "@hint-weight")]
+    pub hint_weight: Option<u32>,
+    #[serde(rename = "@hint-textStacking")]
+    pub hint_text_stacking: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -236,6 +272,9 @@ pub struct ToastInput {
     /// Options for the input if it is of type selection.
     #[serde(default)]
     pub selection: Vec<ToastInputSelection>,
+    /// Initial value, the selection id for selection inputs.
+    #[serde(rename = "@defaultInput")]
+    pub default_input: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
@@ -279,7 +318,7 @@ pub struct ToastAction {
     /// this is used as button icon
     #[serde(rename = "@imageUri")]
     pub image_uri: Option<String>,
-    #[serde(rename = "@hint-inputid")]
+    #[serde(rename = "@hint-inputId", alias = "@hint-inputid")]
     pub hint_inputid: Option<String>,
     #[serde(rename = "@hint-buttonStyle")]
     pub hint_button_style: Option<ToastActionButtonStyle>,
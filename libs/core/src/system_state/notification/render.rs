@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::Result;
+
+use super::{
+    Toast, ToastActionActivationType, ToastActionButtonStyle, ToastActionPlacement,
+    ToastActionsChild, ToastBindingChild, ToastImage, ToastImageCropType, ToastImagePlacement,
+    ToastInput, ToastInputType, ToastProgress, ToastSubGroupChild, ToastTemplateType, ToastText,
+    ToastTextPlacement,
+};
+
+/// Max number of buttons and inputs displayed on a toast
+pub const TOAST_MAX_ACTIONS: usize = 5;
+/// Max number of top level texts displayed on generic toasts
+pub const TOAST_MAX_TEXTS: usize = 3;
+
+static ADAPTIVE_TEXT_STYLES: &[&str] = &[
+    "default",
+    "caption",
+    "captionSubtle",
+    "body",
+    "bodySubtle",
+    "base",
+    "baseSubtle",
+    "subtitle",
+    "subtitleSubtle",
+    "title",
+    "titleSubtle",
+    "titleNumeral",
+    "subheader",
+    "subheaderSubtle",
+    "subheaderNumeral",
+    "header",
+    "headerSubtle",
+    "headerNumeral",
+];
+
+static ADAPTIVE_TEXT_ALIGNS: &[&str] = &["default", "auto", "left", "center", "right"];
+
+static ADAPTIVE_TEXT_STACKING: &[&str] = &["default", "top", "center", "bottom"];
+
+fn is_known(values: &[&str], value: &str) -> bool {
+    values.iter().any(|v| v.eq_ignore_ascii_case(value))
+}
+
+fn check_text(text: &ToastText, issues: &mut Vec<String>) {
+    if let Some(style) = &text.hint_style {
+        if !is_known(ADAPTIVE_TEXT_STYLES, style) {
+            issues.push(format!("unknown text style `{style}`"));
+        }
+    }
+    if let Some(align) = &text.hint_align {
+        if !is_known(ADAPTIVE_TEXT_ALIGNS, align) {
+            issues.push(format!("unknown text align `{align}`"));
+        }
+    }
+    if text.hint_max_lines == Some(0) {
+        issues.push("hint-maxLines should be greater than zero".to_owned());
+    }
+    if let (Some(min), Some(max)) = (text.hint_min_lines, text.hint_max_lines) {
+        if min > max {
+            issues.push("hint-minLines is greater than hint-maxLines".to_owned());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ToastRenderImage {
+    pub src: String,
+    pub alt: Option<String>,
+    pub circle: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ToastRenderButton {
+    /// Empty on system actions, widgets should use a localized label
+    pub content: String,
+    pub arguments: String,
+    pub activation_type: ToastActionActivationType,
+    pub image: Option<String>,
+    pub style: Option<ToastActionButtonStyle>,
+    pub tooltip: Option<String>,
+    /// Input placed next to the button, like a reply box
+    pub input_id: Option<String>,
+}
+
+/// Flattened toast ready to be displayed, following the rules of the toast schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, TS)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "gen-binds", ts(export))]
+pub struct ToastRenderModel {
+    pub title: Option<String>,
+    pub body: Vec<String>,
+    pub attribution: Option<String>,
+    pub header: Option<String>,
+    pub app_logo: Option<ToastRenderImage>,
+    pub hero: Option<ToastRenderImage>,
+    pub inline_images: Vec<ToastRenderImage>,
+    pub progress: Vec<ToastProgress>,
+    pub inputs: Vec<ToastInput>,
+    pub buttons: Vec<ToastRenderButton>,
+    pub context_menu: Vec<ToastRenderButton>,
+}
+
+impl Toast {
+    /// Parses the toast xml as delivered by the system notifications.
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        Ok(quick_xml::de::from_str(xml)?)
+    }
+
+    /// Relative image sources are resolved against the `baseUri` of the visual.
+    fn resolve_src(&self, src: &str) -> String {
+        if src.contains(':') {
+            return src.to_owned();
+        }
+        let base = &self.visual.base_uri;
+        match base.ends_with('/') {
+            true => format!("{base}{src}"),
+            false => format!("{base}/{src}"),
+        }
+    }
+
+    fn render_image(&self, image: &ToastImage) -> ToastRenderImage {
+        ToastRenderImage {
+            src: self.resolve_src(&image.src),
+            alt: image.alt.clone(),
+            circle: matches!(image.hint_crop, Some(ToastImageCropType::Circle)),
+        }
+    }
+
+    fn inputs(&self) -> impl Iterator<Item = &ToastInput> {
+        self.actions
+            .iter()
+            .flat_map(|a| &a.children)
+            .filter_map(|child| match child {
+                ToastActionsChild::Input(input) => Some(input),
+                ToastActionsChild::Action(_) => None,
+            })
+    }
+
+    /// Problems on the toast that would make it render differently than expected.
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        let binding = &self.visual.binding;
+
+        let mut texts = 0;
+        let mut heroes = 0;
+        for child in &binding.children {
+            match child {
+                ToastBindingChild::Text(text) => {
+                    if !matches!(text.placement, Some(ToastTextPlacement::Attribution)) {
+                        texts += 1;
+                    }
+                    check_text(text, &mut issues);
+                }
+                ToastBindingChild::Image(image) => {
+                    if matches!(image.placement, Some(ToastImagePlacement::Hero)) {
+                        heroes += 1;
+                    }
+                }
+                ToastBindingChild::Group(group) => {
+                    if group.subgroup.is_empty() {
+                        issues.push("groups should contain at least one subgroup".to_owned());
+                    }
+                    for subgroup in &group.subgroup {
+                        if let Some(stacking) = &subgroup.hint_text_stacking {
+                            if !is_known(ADAPTIVE_TEXT_STACKING, stacking) {
+                                issues.push(format!("unknown text stacking `{stacking}`"));
+                            }
+                        }
+                        for child in &subgroup.children {
+                            if let ToastSubGroupChild::Text(text) = child {
+                                check_text(text, &mut issues);
+                            }
+                        }
+                    }
+                }
+                ToastBindingChild::Progress(_) => {}
+            }
+        }
+
+        if matches!(binding.template, ToastTemplateType::ToastGeneric) && texts > TOAST_MAX_TEXTS {
+            issues.push(format!(
+                "only the first {TOAST_MAX_TEXTS} texts are displayed, found {texts}"
+            ));
+        }
+        if heroes > 1 {
+            issues.push("only one hero image is displayed".to_owned());
+        }
+
+        let inputs: Vec<&ToastInput> = self.inputs().collect();
+        if inputs.len() > TOAST_MAX_ACTIONS {
+            issues.push(format!("toasts allow up to {TOAST_MAX_ACTIONS} inputs"));
+        }
+        for input in &inputs {
+            if matches!(input.r#type, ToastInputType::Selection) && input.selection.is_empty() {
+                issues.push(format!("selection input `{}` has no options", input.id));
+            }
+        }
+        let input_ids: HashSet<&str> = inputs.iter().map(|i| i.id.as_str()).collect();
+
+        let mut buttons = 0;
+        for child in self.actions.iter().flat_map(|a| &a.children) {
+            let ToastActionsChild::Action(action) = child else {
+                continue;
+            };
+            if !matches!(action.placement, Some(ToastActionPlacement::ContextMenu)) {
+                buttons += 1;
+            }
+            if let Some(input) = &action.hint_inputid {
+                if !input_ids.contains(input.as_str()) {
+                    issues.push(format!(
+                        "action `{}` references unknown input `{input}`",
+                        action.content
+                    ));
+                }
+            }
+        }
+        if buttons > TOAST_MAX_ACTIONS {
+            issues.push(format!("toasts allow up to {TOAST_MAX_ACTIONS} buttons"));
+        }
+        issues
+    }
+
+    /// Flattens the toast, dropping the content that the system would not display.
+    pub fn to_render_model(&self) -> ToastRenderModel {
+        let mut model = ToastRenderModel {
+            header: self.header.as_ref().map(|h| h.title.clone()),
+            ..Default::default()
+        };
+
+        let mut texts = Vec::new();
+        for child in &self.visual.binding.children {
+            match child {
+                ToastBindingChild::Text(text) => match text.placement {
+                    Some(ToastTextPlacement::Attribution) => {
+                        model.attribution = Some(text.content.clone())
+                    }
+                    _ => texts.push(text.content.clone()),
+                },
+                ToastBindingChild::Image(image) => {
+                    let rendered = self.render_image(image);
+                    match image.placement {
+                        Some(ToastImagePlacement::Hero) if model.hero.is_none() => {
+                            model.hero = Some(rendered)
+                        }
+                        Some(ToastImagePlacement::AppLogoOverride) => {
+                            model.app_logo = Some(rendered)
+                        }
+                        Some(ToastImagePlacement::Hero) => {}
+                        _ => model.inline_images.push(rendered),
+                    }
+                }
+                ToastBindingChild::Group(group) => {
+                    // columns are displayed as lines on the flattened model
+                    for subgroup in &group.subgroup {
+                        let line: Vec<&str> = subgroup
+                            .children
+                            .iter()
+                            .filter_map(|child| match child {
+                                ToastSubGroupChild::Text(text) => Some(text.content.as_str()),
+                                ToastSubGroupChild::Image(_) => None,
+                            })
+                            .filter(|text| !text.is_empty())
+                            .collect();
+                        if !line.is_empty() {
+                            model.body.push(line.join(" "));
+                        }
+                    }
+                }
+                ToastBindingChild::Progress(progress) => model.progress.push(progress.clone()),
+            }
+        }
+
+        if matches!(
+            self.visual.binding.template,
+            ToastTemplateType::ToastGeneric
+        ) {
+            texts.truncate(TOAST_MAX_TEXTS);
+        }
+        let mut texts = texts.into_iter().filter(|t| !t.is_empty());
+        model.title = texts.next();
+        let group_lines = std::mem::take(&mut model.body);
+        model.body = texts.chain(group_lines).collect();
+
+        model.inputs = self.inputs().take(TOAST_MAX_ACTIONS).cloned().collect();
+        for child in self.actions.iter().flat_map(|a| &a.children) {
+            let ToastActionsChild::Action(action) = child else {
+                continue;
+            };
+            let button = ToastRenderButton {
+                content: action.content.clone(),
+                arguments: action.arguments.clone(),
+                activation_type: action.activation_type.clone(),
+                image: action.image_uri.as_deref().map(|src| self.resolve_src(src)),
+                style: action.hint_button_style.clone(),
+                tooltip: action.hint_tooltip.clone(),
+                input_id: action.hint_inputid.clone(),
+            };
+            match action.placement {
+                Some(ToastActionPlacement::ContextMenu) => model.context_menu.push(button),
+                _ if model.buttons.len() < TOAST_MAX_ACTIONS => model.buttons.push(button),
+                _ => {}
+            }
+        }
+        model
+    }
+}
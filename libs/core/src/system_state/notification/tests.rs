@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use crate::{
+    error::Result,
+    system_state::{Toast, ToastActionActivationType, ToastActionButtonStyle},
+};
+
+fn load(name: &str) -> Result<Toast> {
+    let xml = std::fs::read_to_string(Path::new("./mocks/toasts").join(name))?;
+    Toast::from_xml(&xml).map_err(|e| format!("{name}: {e}").into())
+}
+
+#[test]
+fn test_corpus_is_parsed_and_valid() -> Result<()> {
+    for entry in std::fs::read_dir("./mocks/toasts")? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        let toast = load(&name)?;
+        if name != "invalid_hints.xml" {
+            assert_eq!(toast.validate(), Vec::<String>::new(), "{name}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_chat_message_render_model() -> Result<()> {
+    let model = load("chat_message.xml")?.to_render_model();
+    assert_eq!(model.title.as_deref(), Some("Andrew"));
+    assert_eq!(model.body, ["Are you coming to the meeting?"]);
+    let logo = model.app_logo.expect("app logo");
+    assert!(logo.circle);
+    assert_eq!(logo.src, "https://cdn.example.com/avatars/andrew.png");
+    assert_eq!(model.inputs.len(), 1);
+    assert_eq!(model.buttons.len(), 1);
+    assert_eq!(model.buttons[0].input_id.as_deref(), Some("reply"));
+    assert!(matches!(
+        model.buttons[0].activation_type,
+        ToastActionActivationType::Background
+    ));
+    assert_eq!(model.context_menu.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_groups_are_flattened() -> Result<()> {
+    let toast = load("weather_groups.xml")?;
+    let model = toast.to_render_model();
+    assert_eq!(model.header.as_deref(), Some("Weather alerts"));
+    assert_eq!(model.title.as_deref(), Some("Today will be mostly sunny"));
+    assert_eq!(model.attribution.as_deref(), Some("via Weather Service"));
+    assert_eq!(model.body, ["Mon 63° 42°", "Tue 57° 38°"]);
+    assert_eq!(
+        model.hero.expect("hero").src,
+        "https://weather.example.com/icons/banners/sunny.jpg"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_progress_and_legacy_templates() -> Result<()> {
+    let model = load("download_progress.xml")?.to_render_model();
+    assert_eq!(model.progress.len(), 1);
+    assert_eq!(model.progress[0].value, "0.6");
+    assert!(matches!(
+        model.buttons[0].style,
+        Some(ToastActionButtonStyle::Critical)
+    ));
+    assert_eq!(
+        model.buttons[0].tooltip.as_deref(),
+        Some("Stop the download")
+    );
+
+    let model = load("legacy_text02.xml")?.to_render_model();
+    assert_eq!(model.title.as_deref(), Some("Backup complete"));
+    assert_eq!(model.body.len(), 1);
+
+    let toast = load("calendar_reminder.xml")?;
+    let model = toast.to_render_model();
+    assert_eq!(model.inputs[0].default_input.as_deref(), Some("15"));
+    assert_eq!(model.inputs[0].selection.len(), 3);
+    assert_eq!(model.buttons.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_hints_are_reported() -> Result<()> {
+    let toast = load("invalid_hints.xml")?;
+    let issues = toast.validate();
+    let expected = [
+        "unknown text style `gigantic`",
+        "hint-maxLines should be greater than zero",
+        "groups should contain at least one subgroup",
+        "only the first 3 texts are displayed, found 4",
+        "selection input `choice` has no options",
+        "action `Go` references unknown input `missing`",
+    ];
+    assert_eq!(issues, expected);
+    // extra texts are not displayed
+    let model = toast.to_render_model();
+    assert_eq!(model.title.as_deref(), Some("Title"));
+    assert_eq!(model.body, ["One", "Two"]);
+    Ok(())
+}
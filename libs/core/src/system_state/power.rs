@@ -11,7 +11,7 @@ pub struct PowerStatus {
 }
 
 // https://learn.microsoft.com/en-us/windows/win32/api/powersetting/ne-powersetting-effective_power_mode
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, TS)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[repr(i32)]
 #[ts(repr(enum = name))]
 pub enum PowerMode {
@@ -0,0 +1,36 @@
+use std::io::Read;
+
+use crate::error::Result;
+
+/// Response of a GET request, the body is streamed so interrupted downloads can be resumed.
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl std::fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Minimal http client used by the library to fetch remote resources.\
+/// The app provides the real implementation, so the library doesn't depend on any
+/// http stack and tests can use a local stand-in.
+pub trait HttpClient {
+    /// GET request to `url`. If `offset` is greater than zero only the bytes from `offset`
+    /// should be requested (`Range: bytes={offset}-`), servers that doesn't support ranges
+    /// will answer with a `200` and the full body.
+    fn get(&self, url: &str, offset: u64) -> Result<HttpResponse>;
+
+    /// Request described by a fetch `RequestInit` object (method, headers, body).\
+    /// By default only requests without init are supported, done as a plain GET.
+    fn request(&self, url: &str, init: Option<&serde_json::Value>) -> Result<HttpResponse> {
+        if init.is_some() {
+            return Err("this http client doesn't support request init".into());
+        }
+        self.get(url, 0)
+    }
+}
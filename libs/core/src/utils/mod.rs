@@ -1,6 +1,11 @@
+mod http;
+
+pub use http::*;
+
 use std::path::{Path, PathBuf};
 
 use schemars::JsonSchema;
+use sha2::{Digest, Sha256};
 
 #[macro_export(local_inner_macros)]
 macro_rules! __switch {
@@ -16,7 +21,7 @@ macro_rules! __switch {
     } => { $($else)* };
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, TS)]
 #[ts(type = "unknown")]
 pub struct TsUnknown(pub serde_json::Value);
 
@@ -42,3 +47,23 @@ pub fn search_resource_entrypoint(folder: &Path) -> Option<PathBuf> {
     }
     None
 }
+
+/// Hex encoded sha256 of the bytes, used to name files by its content.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Writes the file on a temporal sibling and then moves it to the final path,
+/// so readers never see a partially written file.
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    let tmp = path.with_file_name(tmp_name);
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
@@ -34,6 +34,10 @@ pub fn get_app_handle() -> &'static tauri::AppHandle<tauri::Wry> {
 #[tokio::main]
 async fn main() {
     set_tokio_handle(tokio::runtime::Handle::current());
+    // resources target the Seelen UI version implemented by the core library
+    if let Err(err) = seelen_core::resource::init_app_version() {
+        log::error!("{err:?}");
+    }
 
     // Tray menu will be created in setup
     
@@ -1,6 +1,11 @@
 import { signal } from "@preact/signals";
 import { invoke, SeelenCommand, SeelenEvent, subscribe, Widget } from "@seelen-ui/lib";
-import type { SluPopupConfig, SluPopupContent as ISluPopupContent } from "@seelen-ui/lib/types";
+import type {
+  SluPopupConfig,
+  SluPopupContent as ISluPopupContent,
+  SluPopupResult,
+  SluPopupValue,
+} from "@seelen-ui/lib/types";
 import { Icon } from "@shared/components/Icon";
 import type { IconName } from "@shared/components/Icon/icons";
 
@@ -12,12 +17,14 @@ const state = signal<SluPopupConfig>({
   content: [],
   footer: [],
 });
+const values = signal<Record<string, SluPopupValue>>({});
 
 invoke(SeelenCommand.GetPopupConfig, {
   instanceId: currentWidget.decoded.instanceId!,
 })
   .then(async (data) => {
     state.value = data;
+    values.value = collectValues(data);
     currentWidget.webview.setTitle(getOnlyText(data.title));
     await currentWidget.webview.show();
     await currentWidget.webview.setFocus();
@@ -28,7 +35,9 @@ invoke(SeelenCommand.GetPopupConfig, {
   });
 
 subscribe(SeelenEvent.PopupContentChanged, (e) => {
+  const previous = collectValues(state.value);
   state.value = e.payload;
+  values.value = mergeValues(previous, collectValues(e.payload), values.value);
   currentWidget.webview.setTitle(getOnlyText(e.payload.title));
 });
 
@@ -93,9 +102,14 @@ function SluPopupContent({ entry }: { entry: ISluPopupContent }) {
               closePopup();
               return;
             }
+            const result: SluPopupResult = {
+              button: entry.id ?? entry.onClick,
+              values: values.value,
+            };
             currentWidget.webview.emitTo(
               currentWidget.webview.label,
               `${entry.onClick}`,
+              result,
             );
           }}
           style={entry.styles || {}}
@@ -109,11 +123,96 @@ function SluPopupContent({ entry }: { entry: ISluPopupContent }) {
           {entry.items.map((subEntry, idx) => <SluPopupContent key={idx} entry={subEntry} />)}
         </div>
       );
+    case "inputText":
+      return (
+        <input
+          className="input"
+          type="text"
+          value={String(values.value[entry.key] ?? "")}
+          placeholder={entry.placeholder ?? undefined}
+          onInput={(e) => setValue(entry.key, e.currentTarget.value)}
+          style={entry.styles || {}}
+        />
+      );
+    case "checkbox":
+      return (
+        <label className="checkbox" style={entry.styles || {}}>
+          <input
+            type="checkbox"
+            checked={values.value[entry.key] === true}
+            onChange={(e) => setValue(entry.key, e.currentTarget.checked)}
+          />
+          {entry.label}
+        </label>
+      );
+    case "select":
+      return (
+        <select
+          className="select"
+          value={String(values.value[entry.key] ?? "")}
+          onChange={(e) => setValue(entry.key, e.currentTarget.value)}
+          style={entry.styles || {}}
+        >
+          {entry.options.map((option) => (
+            <option key={option.value} value={option.value}>
+              {option.label}
+            </option>
+          ))}
+        </select>
+      );
+    case "progressBar":
+      return <progress className="progress" value={entry.value ?? undefined} style={entry.styles || {}} />;
+    case "divider":
+      return <hr className="divider" style={entry.styles || {}} />;
     default:
       return null;
   }
 }
 
+function setValue(key: string, value: SluPopupValue) {
+  values.value = { ...values.value, [key]: value };
+}
+
+function collectValues(config: SluPopupConfig) {
+  const collected: Record<string, SluPopupValue> = {};
+  const walk = (entries: ISluPopupContent[]) => {
+    for (const entry of entries) {
+      if (entry.type === "inputText") {
+        collected[entry.key] = entry.value;
+      } else if (entry.type === "checkbox") {
+        collected[entry.key] = entry.checked;
+      } else if (entry.type === "select" && entry.value != null) {
+        collected[entry.key] = entry.value;
+      } else if (entry.type === "group") {
+        walk(entry.items);
+      } else if (entry.type === "button") {
+        walk(entry.inner);
+      }
+    }
+  };
+  walk(config.title);
+  walk(config.content);
+  walk(config.footer);
+  return collected;
+}
+
+/**
+ * Keeps the user input of the inputs that still exist, unless the update changed their value.
+ * Inputs removed by the update are dropped.
+ */
+function mergeValues(
+  previous: Record<string, SluPopupValue>,
+  next: Record<string, SluPopupValue>,
+  current: Record<string, SluPopupValue>,
+) {
+  const merged: Record<string, SluPopupValue> = {};
+  for (const [key, value] of Object.entries(next)) {
+    const unchanged = key in previous && previous[key] === value;
+    merged[key] = unchanged && key in current ? current[key]! : value;
+  }
+  return merged;
+}
+
 function getOnlyText(content: ISluPopupContent[]) {
   let text = "";
   for (const entry of content) {
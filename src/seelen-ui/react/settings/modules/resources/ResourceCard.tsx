@@ -10,7 +10,6 @@ import type { ComponentChildren } from "preact";
 import { useEffect, useState } from "preact/hooks";
 import { useTranslation } from "react-i18next";
 
-import { EnvConfig } from "../shared/config/infra.ts";
 import cs from "./infra.module.css";
 
 type AnyResource = {
@@ -44,21 +43,10 @@ export function ResourceCard({ resource, kind, actions }: ResourceCardProps) {
     checkUpdate();
   }, []);
 
-  const [major = 0, minor = 0, patch = 0] = EnvConfig.version.split(".").map(Number);
-  const [majorTarget = 0, minorTarget = 0, patchTarget = 0] = resource.metadata.appTargetVersion || [];
-
-  const targetIsOlder = !!resource.metadata.appTargetVersion &&
-    (majorTarget < major ||
-      (majorTarget === major && minorTarget < minor) ||
-      (majorTarget === major && minorTarget === minor && patchTarget < patch));
-
-  const targetIsNewer = !!resource.metadata.appTargetVersion &&
-    (majorTarget > major ||
-      (majorTarget === major && minorTarget > minor) ||
-      (majorTarget === major && minorTarget === minor && patchTarget > patch));
-
-  const showWarning = targetIsOlder && !resource.metadata.bundled;
-  const showDanger = targetIsNewer && !resource.metadata.bundled;
+  const { compatibility } = resource.metadata;
+  const showWarning = compatibility === "Outdated" && !resource.metadata.bundled;
+  const showDanger = (compatibility === "RequiresNewerApp" || compatibility === "Incompatible") &&
+    !resource.metadata.bundled;
 
   const resourceLink = `https://seelen.io/resources/${resource.id.replace("@", "")}`;
   return (
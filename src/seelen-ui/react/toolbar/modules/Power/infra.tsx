@@ -13,6 +13,7 @@ export function PowerModule({ module }: Props) {
   const power = useSelector(Selectors.powerStatus);
   const powerMode = useSelector(Selectors.powerPlan);
   const batteries = useSelector(Selectors.batteries);
+  const batteryInsights = useSelector(Selectors.batteryInsights);
 
   return (
     <Item
@@ -20,6 +21,7 @@ export function PowerModule({ module }: Props) {
         power,
         powerMode,
         batteries,
+        batteryInsights,
       }}
       module={module}
     />
@@ -45,6 +45,7 @@ const initialState: RootState = {
   },
   powerPlan: PowerMode.Unknown,
   batteries: [],
+  batteryInsights: null,
   networkAdapters: [],
   networkLocalIp: null,
   online: false,
@@ -85,6 +86,7 @@ export async function lazySlice(d: Dispatch) {
   invoke(SeelenCommand.GetPowerStatus).then((status) => d(RootActions.setPowerStatus(status)));
   invoke(SeelenCommand.GetPowerMode).then((plan) => d(RootActions.setPowerPlan(plan)));
   invoke(SeelenCommand.GetBatteries).then((batteries) => d(RootActions.setBatteries(batteries)));
+  invoke(SeelenCommand.GetBatteryInsights).then((insights) => d(RootActions.setBatteryInsights(insights)));
 
   invoke(SeelenCommand.GetMediaDevices).then(([inputs, outputs]) => {
     d(RootActions.setMediaInputs(inputs));
@@ -2,6 +2,7 @@ import type { SystemLanguage } from "@seelen-ui/lib";
 import type {
   AppNotification,
   Battery,
+  BatteryInsights,
   BluetoothDevice,
   File,
   FocusedApp,
@@ -32,6 +33,7 @@ export interface RootState {
   powerStatus: PowerStatus;
   powerPlan: PowerMode;
   batteries: Battery[];
+  batteryInsights: BatteryInsights | null;
   networkAdapters: NetworkAdapter[];
   networkLocalIp: string | null;
   online: boolean;
@@ -110,6 +110,10 @@ export async function registerStoreEvents() {
     store.dispatch(RootActions.setBatteries(event.payload));
   });
 
+  await subscribe(SeelenEvent.BatteryInsights, (event) => {
+    store.dispatch(RootActions.setBatteryInsights(event.payload));
+  });
+
   await subscribe(SeelenEvent.MediaSessions, (event) => {
     store.dispatch(RootActions.setMediaSessions(event.payload));
   });